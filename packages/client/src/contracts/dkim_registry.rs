@@ -0,0 +1,153 @@
+//! Contract-specific abstraction for different backends (Climb, Climb Pool, MultiTest)
+//! Define helper methods here and they'll be available for all backends
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
+
+use crate::{
+    address::AnyAddr,
+    executor::{AnyExecutor, AnyTxResponse, Simulation},
+    querier::AnyQuerier,
+};
+
+use app_contract_api::dkim_registry::msg::{
+    AdminsResponse, DkimKey, DkimKeyType, ExecuteMsg, KeyResponse, KeysResponse, QueryMsg,
+};
+use cosmwasm_std::Binary;
+
+#[derive(Clone)]
+pub struct DkimRegistryContract {
+    pub querier: DkimRegistryQuerier,
+    pub executor: DkimRegistryExecutor,
+    pub address: AnyAddr,
+}
+
+impl DkimRegistryContract {
+    pub fn new(querier: AnyQuerier, executor: AnyExecutor, address: AnyAddr) -> Self {
+        Self {
+            querier: DkimRegistryQuerier::new(querier, address.clone()),
+            executor: DkimRegistryExecutor::new(executor, address.clone()),
+            address,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DkimRegistryQuerier {
+    pub inner: AnyQuerier,
+    pub addr: AnyAddr,
+}
+
+impl DkimRegistryQuerier {
+    pub fn new(inner: AnyQuerier, addr: AnyAddr) -> Self {
+        Self { inner, addr }
+    }
+    pub async fn query<RESP: DeserializeOwned + Send + Sync + Debug>(
+        &self,
+        msg: &QueryMsg,
+    ) -> Result<RESP> {
+        self.inner.contract_query(&self.addr, msg).await
+    }
+
+    pub async fn keys(
+        &self,
+        domain: impl ToString,
+        selector: impl ToString,
+    ) -> Result<Vec<DkimKey>> {
+        let resp: KeysResponse = self
+            .query(&QueryMsg::Keys {
+                domain: domain.to_string(),
+                selector: selector.to_string(),
+            })
+            .await?;
+
+        Ok(resp.keys)
+    }
+
+    pub async fn key(
+        &self,
+        domain: impl ToString,
+        selector: impl ToString,
+        key_hash: impl ToString,
+    ) -> Result<Option<DkimKey>> {
+        let resp: KeyResponse = self
+            .query(&QueryMsg::Key {
+                domain: domain.to_string(),
+                selector: selector.to_string(),
+                key_hash: key_hash.to_string(),
+            })
+            .await?;
+
+        Ok(resp.key)
+    }
+
+    pub async fn admins(&self) -> Result<Vec<AnyAddr>> {
+        let resp: AdminsResponse = self.query(&QueryMsg::Admins {}).await?;
+
+        Ok(resp.admins.into_iter().map(AnyAddr::from).collect())
+    }
+}
+
+#[derive(Clone)]
+pub struct DkimRegistryExecutor {
+    pub inner: AnyExecutor,
+    pub addr: AnyAddr,
+}
+
+impl DkimRegistryExecutor {
+    pub fn new(inner: AnyExecutor, addr: AnyAddr) -> Self {
+        Self { inner, addr }
+    }
+    pub async fn exec(
+        &self,
+        msg: &ExecuteMsg,
+        funds: &[cosmwasm_std::Coin],
+    ) -> Result<AnyTxResponse> {
+        self.inner.contract_exec(&self.addr, msg, funds).await
+    }
+
+    pub async fn simulate(
+        &self,
+        msg: &ExecuteMsg,
+        funds: &[cosmwasm_std::Coin],
+    ) -> Result<Simulation> {
+        self.inner.contract_simulate(&self.addr, msg, funds).await
+    }
+
+    pub async fn record_key(
+        &self,
+        domain: impl ToString,
+        selector: impl ToString,
+        key_type: DkimKeyType,
+        public_key: Binary,
+    ) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::RecordKey {
+                domain: domain.to_string(),
+                selector: selector.to_string(),
+                key_type,
+                public_key,
+            },
+            &[],
+        )
+        .await
+    }
+
+    pub async fn revoke_key(
+        &self,
+        domain: impl ToString,
+        selector: impl ToString,
+        key_hash: impl ToString,
+    ) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::RevokeKey {
+                domain: domain.to_string(),
+                selector: selector.to_string(),
+                key_hash: key_hash.to_string(),
+            },
+            &[],
+        )
+        .await
+    }
+}
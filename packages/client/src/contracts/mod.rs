@@ -1,3 +1,41 @@
+pub mod dkim_registry;
 pub mod proxy;
 pub mod service_handler;
 pub mod user_registry;
+
+use std::future::Future;
+
+use anyhow::Result;
+use futures::{stream, Stream, TryStreamExt};
+
+/// How many items the paginated helpers ask for at a time
+pub const PAGE_SIZE: u32 = 100;
+
+/// Streams every item of a paginated query, following `start_after` cursors until a page comes back empty
+///
+/// `fetch` gets the cursor to start after (`None` for the first page), `cursor` gives an item's cursor.
+pub fn paginate<T, C, Fetch, Fut>(
+    fetch: Fetch,
+    cursor: fn(&T) -> C,
+) -> impl Stream<Item = Result<T>>
+where
+    Fetch: Fn(Option<C>) -> Fut,
+    Fut: Future<Output = Result<Vec<T>>>,
+{
+    // `None` once the last page has been fetched
+    stream::try_unfold(Some(None), move |start_after: Option<Option<C>>| {
+        let page = start_after.map(&fetch);
+
+        async move {
+            let Some(page) = page else {
+                return Ok(None);
+            };
+
+            let page = page.await?;
+            let next = page.last().map(|item| Some(cursor(item)));
+
+            Ok(Some((stream::iter(page.into_iter().map(Ok)), next)))
+        }
+    })
+    .try_flatten()
+}
@@ -2,12 +2,14 @@
 //! Define helper methods here and they'll be available for all backends
 
 use anyhow::Result;
+use app_contract_api::proxy::ProxyExecuteMsg;
+use cosmwasm_std::Coin;
 use serde::de::DeserializeOwned;
 use std::fmt::Debug;
 
 use crate::{
     address::AnyAddr,
-    executor::{AnyExecutor, AnyTxResponse},
+    executor::{AnyExecutor, AnyTxResponse, Simulation},
     querier::AnyQuerier,
 };
 
@@ -77,4 +79,56 @@ impl ProxyExecutor {
     ) -> Result<AnyTxResponse> {
         self.inner.contract_exec(&self.addr, msg, funds).await
     }
+
+    pub async fn simulate(
+        &self,
+        msg: &ExecuteMsg,
+        funds: &[cosmwasm_std::Coin],
+    ) -> Result<Simulation> {
+        self.inner.contract_simulate(&self.addr, msg, funds).await
+    }
+
+    /// Built from `ProxyExecuteMsg` rather than `ExecuteMsg`, whose `Coin` is from an older cosmwasm-std
+    pub async fn exec_proxy_msg(
+        &self,
+        msg: &ProxyExecuteMsg,
+        funds: &[cosmwasm_std::Coin],
+    ) -> Result<AnyTxResponse> {
+        self.inner.contract_exec(&self.addr, msg, funds).await
+    }
+
+    pub async fn forward_to_inflow(&self, funds: &[cosmwasm_std::Coin]) -> Result<AnyTxResponse> {
+        self.exec_proxy_msg(&ProxyExecuteMsg::ForwardToInflow {}, funds)
+            .await
+    }
+
+    pub async fn withdraw_receipt_tokens(
+        &self,
+        address: impl ToString,
+        coin: Coin,
+    ) -> Result<AnyTxResponse> {
+        self.exec_proxy_msg(
+            &ProxyExecuteMsg::WithdrawReceiptTokens {
+                address: address.to_string(),
+                coin,
+            },
+            &[],
+        )
+        .await
+    }
+
+    pub async fn withdraw_funds(
+        &self,
+        address: impl ToString,
+        coin: Coin,
+    ) -> Result<AnyTxResponse> {
+        self.exec_proxy_msg(
+            &ProxyExecuteMsg::WithdrawFunds {
+                address: address.to_string(),
+                coin,
+            },
+            &[],
+        )
+        .await
+    }
 }
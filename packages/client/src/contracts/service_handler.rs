@@ -2,19 +2,29 @@
 //! Define helper methods here and they'll be available for all backends
 
 use anyhow::Result;
+use cosmwasm_std::{Addr, Timestamp};
+use futures::{Stream, TryStreamExt};
 use serde::de::DeserializeOwned;
 use std::fmt::Debug;
+use wavs_types::contracts::cosmwasm::service_handler::{
+    ServiceHandlerExecuteMessages, ServiceHandlerQueryMessages,
+};
 
 use crate::{
     address::AnyAddr,
-    executor::{AnyExecutor, AnyTxResponse},
+    contracts::{paginate, PAGE_SIZE},
+    executor::{AnyExecutor, AnyTxResponse, Simulation},
     querier::AnyQuerier,
 };
 
 use app_contract_api::{
+    dkim_proof::DkimProof,
     service_handler::msg::{
-        AdminResponse, CustomExecuteMsg, CustomQueryMsg, EmailUserIdsResponse, ExecuteMsg,
-        QueryMsg, UserIdEmail, UserRegistryResponse,
+        AdminResponse, ConfirmationConfig, ConfirmationResponse, CustomExecuteMsg, CustomQueryMsg,
+        EmailMessageOnly, EmailUserIdsResponse, EmailsFromResponse, EmailsResponse, ExecuteMsg,
+        HandledEventResponse, MaxEmailAgeResponse, PendingAction, PendingActionResponse,
+        PrivacyResponse, PrivateEmail, PrivateEmailsResponse, ProofVerifierResponse, QueryMsg,
+        UserIdEmail, UserRegistryResponse,
     },
     user_registry::msg::UserId,
 };
@@ -69,68 +79,66 @@ impl ServiceHandlerQuerier {
         Ok(resp.admin)
     }
 
-    pub async fn all_email_user_ids(&self) -> Result<Vec<UserId>> {
-        let mut emails = Vec::new();
-        let mut start_after: Option<UserId> = None;
+    pub async fn confirmation_config(&self) -> Result<Option<ConfirmationConfig>> {
+        let resp: ConfirmationResponse = self
+            .query(&QueryMsg::Custom(CustomQueryMsg::Confirmation {}))
+            .await?;
 
-        loop {
-            let batch = self.email_user_ids(Some(100), start_after.clone()).await?;
+        Ok(resp.config)
+    }
 
-            if batch.is_empty() {
-                break;
-            }
+    /// By `PendingAction::nonce_hash`, the nonce itself never goes on-chain until it's used
+    pub async fn pending_action(&self, nonce_hash: &str) -> Result<Option<PendingAction>> {
+        let resp: PendingActionResponse = self
+            .query(&QueryMsg::Custom(CustomQueryMsg::PendingAction {
+                nonce_hash: nonce_hash.to_string(),
+            }))
+            .await?;
 
-            start_after = batch.last().cloned();
-            emails.extend(batch);
-        }
+        Ok(resp.action)
+    }
 
-        Ok(emails)
+    pub async fn private_emails_enabled(&self) -> Result<bool> {
+        let resp: PrivacyResponse = self
+            .query(&QueryMsg::Custom(CustomQueryMsg::Privacy {}))
+            .await?;
+
+        Ok(resp.private_emails)
     }
 
-    pub async fn all_emails_from(
-        &self,
-        from: &UserId,
-    ) -> Result<
-        Vec<(
-            app_contract_api::service_handler::msg::EmailMessageOnly,
-            u64,
-        )>,
-    > {
-        let mut emails = Vec::new();
-        let mut start_after: Option<u64> = None;
-
-        loop {
-            let batch = self.emails_from(from, Some(100), start_after).await?;
-
-            if batch.is_empty() {
-                break;
-            }
-
-            start_after = Some(batch.last().unwrap().1);
-            emails.extend(batch);
-        }
+    pub async fn proof_verifier(&self) -> Result<Option<AnyAddr>> {
+        let resp: ProofVerifierResponse = self
+            .query(&QueryMsg::Custom(CustomQueryMsg::ProofVerifier {}))
+            .await?;
 
-        Ok(emails)
+        Ok(resp.address.map(AnyAddr::from))
     }
 
-    pub async fn all_emails(
-        &self,
-    ) -> Result<Vec<(app_contract_api::service_handler::msg::UserIdEmail, u64)>> {
-        let mut emails = Vec::new();
-        let mut start_after: Option<u64> = None;
+    pub async fn dkim_registry(&self) -> Result<Option<AnyAddr>> {
+        let resp: ProofVerifierResponse = self
+            .query(&QueryMsg::Custom(CustomQueryMsg::ProofVerifier {}))
+            .await?;
 
-        loop {
-            let batch = self.emails(Some(100), start_after).await?;
+        Ok(resp.dkim_registry.map(AnyAddr::from))
+    }
 
-            if batch.is_empty() {
-                break;
-            }
+    pub async fn max_email_age_seconds(&self) -> Result<Option<u64>> {
+        let resp: MaxEmailAgeResponse = self
+            .query(&QueryMsg::Custom(CustomQueryMsg::MaxEmailAge {}))
+            .await?;
 
-            start_after = Some(batch.last().unwrap().1);
-            emails.extend(batch);
-        }
+        Ok(resp.max_email_age_seconds)
+    }
 
-        Ok(emails)
+    /// The height `event_id` ran at, if it did
+    pub async fn handled_event(&self, event_id: &[u8]) -> Result<Option<u64>> {
+        let resp: HandledEventResponse = self
+            .query(&QueryMsg::Custom(CustomQueryMsg::HandledEvent {
+                event_id: event_id.into(),
+            }))
+            .await?;
+
+        Ok(resp.height)
     }
 
     pub async fn email_user_ids(
@@ -153,13 +161,8 @@ impl ServiceHandlerQuerier {
         from: &UserId,
         limit: Option<u32>,
         start_after: Option<u64>,
-    ) -> Result<
-        Vec<(
-            app_contract_api::service_handler::msg::EmailMessageOnly,
-            u64,
-        )>,
-    > {
-        let resp: app_contract_api::service_handler::msg::EmailsFromResponse = self
+    ) -> Result<Vec<(EmailMessageOnly, u64)>> {
+        let resp: EmailsFromResponse = self
             .query(&QueryMsg::Custom(CustomQueryMsg::EmailsFrom {
                 from: from.to_string(),
                 limit,
@@ -174,8 +177,8 @@ impl ServiceHandlerQuerier {
         &self,
         limit: Option<u32>,
         start_after: Option<u64>,
-    ) -> Result<Vec<(app_contract_api::service_handler::msg::UserIdEmail, u64)>> {
-        let resp: app_contract_api::service_handler::msg::EmailsResponse = self
+    ) -> Result<Vec<(UserIdEmail, u64)>> {
+        let resp: EmailsResponse = self
             .query(&QueryMsg::Custom(CustomQueryMsg::Emails {
                 limit,
                 start_after,
@@ -183,6 +186,78 @@ impl ServiceHandlerQuerier {
             .await?;
         Ok(resp.emails)
     }
+
+    pub async fn private_emails(
+        &self,
+        limit: Option<u32>,
+        start_after: Option<u64>,
+    ) -> Result<Vec<(PrivateEmail, u64)>> {
+        let resp: PrivateEmailsResponse = self
+            .query(&QueryMsg::Custom(CustomQueryMsg::PrivateEmails {
+                limit,
+                start_after,
+            }))
+            .await?;
+
+        Ok(resp.emails)
+    }
+
+    pub async fn service_manager(&self) -> Result<AnyAddr> {
+        let address: Addr = self
+            .query(&QueryMsg::Wavs(
+                ServiceHandlerQueryMessages::WavsServiceManager {},
+            ))
+            .await?;
+
+        Ok(AnyAddr::from(address))
+    }
+
+    pub fn email_user_ids_stream(&self) -> impl Stream<Item = Result<UserId>> + '_ {
+        paginate(
+            move |start_after| self.email_user_ids(Some(PAGE_SIZE), start_after),
+            UserId::clone,
+        )
+    }
+
+    pub async fn all_email_user_ids(&self) -> Result<Vec<UserId>> {
+        self.email_user_ids_stream().try_collect().await
+    }
+
+    pub fn emails_from_stream<'a>(
+        &'a self,
+        from: &'a UserId,
+    ) -> impl Stream<Item = Result<(EmailMessageOnly, u64)>> + 'a {
+        paginate(
+            move |start_after| self.emails_from(from, Some(PAGE_SIZE), start_after),
+            |(_, id): &(EmailMessageOnly, u64)| *id,
+        )
+    }
+
+    pub async fn all_emails_from(&self, from: &UserId) -> Result<Vec<(EmailMessageOnly, u64)>> {
+        self.emails_from_stream(from).try_collect().await
+    }
+
+    pub fn emails_stream(&self) -> impl Stream<Item = Result<(UserIdEmail, u64)>> + '_ {
+        paginate(
+            move |start_after| self.emails(Some(PAGE_SIZE), start_after),
+            |(_, id): &(UserIdEmail, u64)| *id,
+        )
+    }
+
+    pub async fn all_emails(&self) -> Result<Vec<(UserIdEmail, u64)>> {
+        self.emails_stream().try_collect().await
+    }
+
+    pub fn private_emails_stream(&self) -> impl Stream<Item = Result<(PrivateEmail, u64)>> + '_ {
+        paginate(
+            move |start_after| self.private_emails(Some(PAGE_SIZE), start_after),
+            |(_, id): &(PrivateEmail, u64)| *id,
+        )
+    }
+
+    pub async fn all_private_emails(&self) -> Result<Vec<(PrivateEmail, u64)>> {
+        self.private_emails_stream().try_collect().await
+    }
 }
 
 #[derive(Clone)]
@@ -203,8 +278,91 @@ impl ServiceHandlerExecutor {
         self.inner.contract_exec(&self.addr, msg, funds).await
     }
 
+    pub async fn simulate(
+        &self,
+        msg: &ExecuteMsg,
+        funds: &[cosmwasm_std::Coin],
+    ) -> Result<Simulation> {
+        self.inner.contract_simulate(&self.addr, msg, funds).await
+    }
+
     pub async fn push_email(&self, email: UserIdEmail) -> Result<AnyTxResponse> {
         self.exec(&ExecuteMsg::Custom(CustomExecuteMsg::Email(email)), &[])
             .await
     }
+
+    pub async fn push_private_email(&self, email: PrivateEmail) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::Custom(CustomExecuteMsg::PrivateEmail(email)),
+            &[],
+        )
+        .await
+    }
+
+    pub async fn push_proven_email(
+        &self,
+        email: UserIdEmail,
+        proof: DkimProof,
+    ) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::Custom(CustomExecuteMsg::ProvenEmail { email, proof }),
+            &[],
+        )
+        .await
+    }
+
+    pub async fn push_timestamped(
+        &self,
+        msg: CustomExecuteMsg,
+        signed_at: Timestamp,
+    ) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::Custom(CustomExecuteMsg::Timestamped {
+                signed_at,
+                msg: Box::new(msg),
+            }),
+            &[],
+        )
+        .await
+    }
+
+    /// `msg` is held under `nonce`'s hash if it needs confirmation, like the operators submit it
+    pub async fn push_confirmable(
+        &self,
+        msg: CustomExecuteMsg,
+        nonce: &str,
+    ) -> Result<AnyTxResponse> {
+        self.exec(&ExecuteMsg::Custom(msg.confirmable(nonce)), &[])
+            .await
+    }
+
+    pub async fn confirm(&self, from: UserId, nonce: String) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::Custom(CustomExecuteMsg::Confirm { from, nonce }),
+            &[],
+        )
+        .await
+    }
+
+    /// Held until `user_id` confirms with `nonce`
+    pub async fn link(&self, from: UserId, user_id: UserId, nonce: &str) -> Result<AnyTxResponse> {
+        self.push_confirmable(CustomExecuteMsg::Link { from, user_id }, nonce)
+            .await
+    }
+
+    pub async fn unlink(&self, from: UserId, user_id: UserId) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::Custom(CustomExecuteMsg::Unlink { from, user_id }),
+            &[],
+        )
+        .await
+    }
+
+    /// What the aggregator submits, for when there's no aggregator (e.g. tests against a mock service manager)
+    pub async fn handle_signed_envelope(
+        &self,
+        msg: ServiceHandlerExecuteMessages,
+    ) -> Result<AnyTxResponse> {
+        self.exec(&ExecuteMsg::Wavs(msg), &[]).await
+    }
 }
@@ -2,16 +2,22 @@
 //! Define helper methods here and they'll be available for all backends
 
 use anyhow::Result;
+use futures::{Stream, TryStreamExt};
 use serde::de::DeserializeOwned;
 use std::fmt::Debug;
 
 use crate::{
     address::AnyAddr,
-    executor::{AnyExecutor, AnyTxResponse},
+    contracts::{paginate, PAGE_SIZE},
+    executor::{AnyExecutor, AnyTxResponse, Simulation},
     querier::AnyQuerier,
 };
 
-use app_contract_api::user_registry::msg::{ExecuteMsg, ProxyAddressResponse, QueryMsg, UserId};
+use app_contract_api::user_registry::msg::{
+    AccountResponse, AdminsResponse, ExecuteMsg, ProxyAddressResponse, ProxyOwnerResponse,
+    QueryMsg, Registration, RegistrationsResponse, ServiceHandlerResponse, UserByEmailResponse,
+    UserCountResponse, UserId, UsersResponse,
+};
 
 #[derive(Clone)]
 pub struct UserRegistryContract {
@@ -52,6 +58,84 @@ impl UserRegistryQuerier {
 
         Ok(AnyAddr::from(resp.address))
     }
+
+    pub async fn account(&self, user_id: UserId) -> Result<AccountResponse> {
+        self.query(&QueryMsg::Account { user_id }).await
+    }
+
+    pub async fn service_handler(&self) -> Result<Option<AnyAddr>> {
+        let resp: ServiceHandlerResponse = self.query(&QueryMsg::ServiceHandler {}).await?;
+
+        Ok(resp.address.map(AnyAddr::from))
+    }
+
+    pub async fn admins(&self) -> Result<Vec<AnyAddr>> {
+        let resp: AdminsResponse = self.query(&QueryMsg::Admins {}).await?;
+
+        Ok(resp.admins.into_iter().map(AnyAddr::from).collect())
+    }
+
+    pub async fn registrations(
+        &self,
+        limit: Option<u32>,
+        start_after: Option<UserId>,
+    ) -> Result<Vec<Registration>> {
+        let resp: RegistrationsResponse = self
+            .query(&QueryMsg::Registrations { limit, start_after })
+            .await?;
+
+        Ok(resp.registrations)
+    }
+
+    pub fn registrations_stream(&self) -> impl Stream<Item = Result<Registration>> + '_ {
+        paginate(
+            move |start_after| self.registrations(Some(PAGE_SIZE), start_after),
+            |registration: &Registration| registration.user_id.clone(),
+        )
+    }
+
+    pub async fn all_registrations(&self) -> Result<Vec<Registration>> {
+        self.registrations_stream().try_collect().await
+    }
+
+    pub async fn proxy_owner(&self, proxy_address: AnyAddr) -> Result<ProxyOwnerResponse> {
+        self.query(&QueryMsg::ProxyOwner {
+            proxy_address: proxy_address.to_string(),
+        })
+        .await
+    }
+
+    pub async fn users(
+        &self,
+        limit: Option<u32>,
+        start_after: Option<UserId>,
+    ) -> Result<Vec<UserId>> {
+        let resp: UsersResponse = self.query(&QueryMsg::Users { limit, start_after }).await?;
+
+        Ok(resp.user_ids)
+    }
+
+    pub fn users_stream(&self) -> impl Stream<Item = Result<UserId>> + '_ {
+        paginate(
+            move |start_after| self.users(Some(PAGE_SIZE), start_after),
+            UserId::clone,
+        )
+    }
+
+    pub async fn all_users(&self) -> Result<Vec<UserId>> {
+        self.users_stream().try_collect().await
+    }
+
+    pub async fn user_count(&self) -> Result<UserCountResponse> {
+        self.query(&QueryMsg::UserCount {}).await
+    }
+
+    pub async fn user_by_email(&self, email: impl ToString) -> Result<UserByEmailResponse> {
+        self.query(&QueryMsg::UserByEmail {
+            email: email.to_string(),
+        })
+        .await
+    }
 }
 
 #[derive(Clone)]
@@ -72,6 +156,14 @@ impl UserRegistryExecutor {
         self.inner.contract_exec(&self.addr, msg, funds).await
     }
 
+    pub async fn simulate(
+        &self,
+        msg: &ExecuteMsg,
+        funds: &[cosmwasm_std::Coin],
+    ) -> Result<Simulation> {
+        self.inner.contract_simulate(&self.addr, msg, funds).await
+    }
+
     pub async fn register_user_id(
         &self,
         user_id: UserId,
@@ -86,4 +178,78 @@ impl UserRegistryExecutor {
 
         Ok((tx_resp, user_id))
     }
+
+    pub async fn set_service_handler(&self, address: AnyAddr) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::SetServiceHandler {
+                address: address.to_string(),
+            },
+            &[],
+        )
+        .await
+    }
+
+    pub async fn link_user_id(
+        &self,
+        user_id: UserId,
+        new_user_id: UserId,
+    ) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::LinkUserId {
+                user_id,
+                new_user_id,
+            },
+            &[],
+        )
+        .await
+    }
+
+    pub async fn unlink_user_id(
+        &self,
+        user_id: UserId,
+        unlink_user_id: UserId,
+    ) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::UnlinkUserId {
+                user_id,
+                unlink_user_id,
+            },
+            &[],
+        )
+        .await
+    }
+    pub async fn update_admins(
+        &self,
+        add: Vec<AnyAddr>,
+        remove: Vec<AnyAddr>,
+    ) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::UpdateAdmins {
+                add: add.iter().map(|a| a.to_string()).collect(),
+                remove: remove.iter().map(|a| a.to_string()).collect(),
+            },
+            &[],
+        )
+        .await
+    }
+
+    pub async fn update_user_proxy(
+        &self,
+        user_id: UserId,
+        proxy_address: AnyAddr,
+    ) -> Result<AnyTxResponse> {
+        self.exec(
+            &ExecuteMsg::UpdateUserProxy {
+                user_id,
+                proxy_address: proxy_address.to_string(),
+            },
+            &[],
+        )
+        .await
+    }
+
+    pub async fn deregister_user(&self, user_id: UserId) -> Result<AnyTxResponse> {
+        self.exec(&ExecuteMsg::DeregisterUser { user_id }, &[])
+            .await
+    }
 }
@@ -2,13 +2,14 @@
 use std::sync::Arc;
 
 use anyhow::Result;
-use layer_climb::{events::CosmosTxEvents, signing::SigningClient};
+use layer_climb::{events::CosmosTxEvents, prelude::proto_into_any, signing::SigningClient};
 use serde::Serialize;
 
 use crate::address::AnyAddr;
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "multitest")] {
+        use cosmwasm_std::{Order, Storage};
         use cw_multi_test::{App, Executor};
         use std::rc::Rc;
         use std::cell::RefCell;
@@ -126,6 +127,378 @@ impl AnyExecutor {
                 .map_err(|e| anyhow::anyhow!("{e:?}"))?),
         }
     }
+
+    /// Broadcasts `msgs` as one transaction, so either all of them go through or none do
+    ///
+    /// Fails on chains that don't tag events with their `msg_index` (before Cosmos SDK 0.50),
+    /// rather than guess which message they came from, even though the transaction landed.
+    pub async fn exec_multi(&self, msgs: &[AnyMsg]) -> Result<AnyMultiTxResponse> {
+        if msgs.is_empty() {
+            return Err(anyhow::anyhow!("A transaction needs at least one message"));
+        }
+
+        match self {
+            Self::Climb(client) => climb_exec_multi(client, msgs).await,
+            #[cfg(feature = "client-pool")]
+            Self::ClimbPool(pool) => {
+                let client = pool.get().await.map_err(|e| anyhow::anyhow!("{e:?}"))?;
+
+                climb_exec_multi(&client, msgs).await
+            }
+            #[cfg(feature = "client-pool")]
+            Self::ClimbPoolObject(client) => climb_exec_multi(client, msgs).await,
+            #[cfg(feature = "multitest")]
+            Self::MultiTest { app, admin } => {
+                let msgs = msgs
+                    .iter()
+                    .map(AnyMsg::cosmos_msg)
+                    .collect::<Result<Vec<_>>>()?;
+
+                let responses = app
+                    .borrow_mut()
+                    .execute_multi(admin.clone(), msgs)
+                    .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+
+                let msg_events = responses
+                    .into_iter()
+                    .map(|resp| resp.events)
+                    .collect::<Vec<_>>();
+
+                Ok(AnyMultiTxResponse {
+                    tx: AnyTxResponse::MultiTest(cw_multi_test::AppResponse {
+                        events: msg_events.concat(),
+                        data: None,
+                    }),
+                    msg_events,
+                })
+            }
+        }
+    }
+
+    /// Runs `msg` like `contract_exec` would, without committing anything
+    pub async fn contract_simulate<MSG: Serialize + std::fmt::Debug>(
+        &self,
+        address: &AnyAddr,
+        msg: &MSG,
+        funds: &[cosmwasm_std::Coin],
+    ) -> Result<Simulation> {
+        match self {
+            Self::Climb(client) => {
+                let msg = client.contract_execute_msg(&address.into(), msg, climb_coins(funds))?;
+
+                climb_simulate(client, vec![proto_into_any(&msg)?]).await
+            }
+            #[cfg(feature = "client-pool")]
+            Self::ClimbPool(pool) => {
+                let client = pool.get().await.map_err(|e| anyhow::anyhow!("{e:?}"))?;
+                let msg = client.contract_execute_msg(&address.into(), msg, climb_coins(funds))?;
+
+                climb_simulate(&client, vec![proto_into_any(&msg)?]).await
+            }
+            #[cfg(feature = "client-pool")]
+            Self::ClimbPoolObject(client) => {
+                let msg = client.contract_execute_msg(&address.into(), msg, climb_coins(funds))?;
+
+                climb_simulate(client, vec![proto_into_any(&msg)?]).await
+            }
+            #[cfg(feature = "multitest")]
+            Self::MultiTest { app, admin } => {
+                let mut app = app.borrow_mut();
+
+                // MockStorage can't be cloned, so the app can't be either. Everything
+                // executing touches is in storage though, so putting that back is enough
+                let snapshot = app
+                    .storage()
+                    .range(None, None, Order::Ascending)
+                    .collect::<Vec<_>>();
+
+                let resp = app.execute_contract(admin.clone(), address.into(), msg, funds);
+
+                let keys = app
+                    .storage()
+                    .range_keys(None, None, Order::Ascending)
+                    .collect::<Vec<_>>();
+                for key in keys {
+                    app.storage_mut().remove(&key);
+                }
+                for (key, value) in snapshot {
+                    app.storage_mut().set(&key, &value);
+                }
+
+                let resp = resp.map_err(|e| anyhow::anyhow!("{e:?}"))?;
+
+                Ok(Simulation {
+                    gas_used: None,
+                    events: resp.events,
+                })
+            }
+        }
+    }
+}
+
+/// One message of a transaction sent with `AnyExecutor::exec_multi`
+#[derive(Debug, Clone)]
+pub enum AnyMsg {
+    Execute {
+        address: AnyAddr,
+        msg: serde_json::Value,
+        funds: Vec<cosmwasm_std::Coin>,
+    },
+    Instantiate {
+        admin: Option<AnyAddr>,
+        code_id: u64,
+        label: String,
+        msg: serde_json::Value,
+        funds: Vec<cosmwasm_std::Coin>,
+    },
+    BankSend {
+        to: AnyAddr,
+        amount: Vec<cosmwasm_std::Coin>,
+    },
+}
+
+impl AnyMsg {
+    pub fn execute(
+        address: impl Into<AnyAddr>,
+        msg: &impl Serialize,
+        funds: Vec<cosmwasm_std::Coin>,
+    ) -> Result<Self> {
+        Ok(Self::Execute {
+            address: address.into(),
+            msg: serde_json::to_value(msg)?,
+            funds,
+        })
+    }
+
+    pub fn instantiate(
+        admin: Option<AnyAddr>,
+        code_id: u64,
+        label: impl ToString,
+        msg: &impl Serialize,
+        funds: Vec<cosmwasm_std::Coin>,
+    ) -> Result<Self> {
+        Ok(Self::Instantiate {
+            admin,
+            code_id,
+            label: label.to_string(),
+            msg: serde_json::to_value(msg)?,
+            funds,
+        })
+    }
+
+    pub fn bank_send(to: impl Into<AnyAddr>, amount: Vec<cosmwasm_std::Coin>) -> Self {
+        Self::BankSend {
+            to: to.into(),
+            amount,
+        }
+    }
+
+    fn climb_any(&self, client: &SigningClient) -> Result<layer_climb::proto::Any> {
+        match self {
+            Self::Execute {
+                address,
+                msg,
+                funds,
+            } => proto_into_any(&client.contract_execute_msg(
+                &address.into(),
+                msg,
+                climb_coins(funds),
+            )?),
+            Self::Instantiate {
+                admin,
+                code_id,
+                label,
+                msg,
+                funds,
+            } => proto_into_any(&client.contract_instantiate_msg(
+                admin.as_ref().map(Into::into),
+                *code_id,
+                label,
+                msg,
+                climb_coins(funds),
+            )?),
+            Self::BankSend { to, amount } => proto_into_any(&layer_climb::proto::bank::MsgSend {
+                from_address: client.addr.to_string(),
+                to_address: to.to_string(),
+                amount: climb_coins(amount),
+            }),
+        }
+    }
+
+    #[cfg(feature = "multitest")]
+    fn cosmos_msg(&self) -> Result<cosmwasm_std::CosmosMsg> {
+        Ok(match self {
+            Self::Execute {
+                address,
+                msg,
+                funds,
+            } => cosmwasm_std::WasmMsg::Execute {
+                contract_addr: address.to_string(),
+                msg: cosmwasm_std::to_json_binary(msg)?,
+                funds: funds.clone(),
+            }
+            .into(),
+            Self::Instantiate {
+                admin,
+                code_id,
+                label,
+                msg,
+                funds,
+            } => cosmwasm_std::WasmMsg::Instantiate {
+                admin: admin.as_ref().map(ToString::to_string),
+                code_id: *code_id,
+                msg: cosmwasm_std::to_json_binary(msg)?,
+                funds: funds.clone(),
+                label: label.clone(),
+            }
+            .into(),
+            Self::BankSend { to, amount } => cosmwasm_std::BankMsg::Send {
+                to_address: to.to_string(),
+                amount: amount.clone(),
+            }
+            .into(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct AnyMultiTxResponse {
+    pub tx: AnyTxResponse,
+    /// The events of each message, in the order they were sent
+    pub msg_events: Vec<Vec<cosmwasm_std::Event>>,
+}
+
+impl AnyMultiTxResponse {
+    /// Like `AnyTxResponse::events`, for the message at `index`
+    pub fn events<E>(&self, index: usize, event_type: &str) -> Result<Vec<E>>
+    where
+        E: for<'e> TryFrom<&'e cosmwasm_std::Event, Error = anyhow::Error>,
+    {
+        let events = self
+            .msg_events
+            .get(index)
+            .ok_or_else(|| anyhow::anyhow!("No message {index} in the transaction"))?;
+
+        CosmosTxEvents::from(events.as_slice())
+            .filter_events_by_type(event_type)
+            .map(|event| E::try_from(&cosmwasm_std::Event::from(event)))
+            .collect()
+    }
+}
+
+async fn climb_exec_multi(client: &SigningClient, msgs: &[AnyMsg]) -> Result<AnyMultiTxResponse> {
+    let messages = msgs
+        .iter()
+        .map(|msg| msg.climb_any(client))
+        .collect::<Result<Vec<_>>>()?;
+
+    let resp = client.tx_builder().broadcast(messages).await?;
+
+    // the chain tags each message's events with its index, the rest belong to the tx itself
+    let mut msg_events = vec![Vec::new(); msgs.len()];
+
+    for event in &resp.events {
+        let Some(index) = event.attributes.iter().find(|attr| attr.key == "msg_index") else {
+            continue;
+        };
+
+        let events = index
+            .value
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| msg_events.get_mut(index))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Tx {} has a {} event for message {}, out of {}",
+                    resp.txhash,
+                    event.r#type,
+                    index.value,
+                    msgs.len()
+                )
+            })?;
+
+        events.push(
+            cosmwasm_std::Event::new(&event.r#type).add_attributes(
+                event
+                    .attributes
+                    .iter()
+                    .map(|attr| (attr.key.clone(), attr.value.clone())),
+            ),
+        );
+    }
+
+    // every message emits at least a `message` event, so older chains that don't tag them end up here
+    if let Some(index) = msg_events.iter().position(Vec::is_empty) {
+        return Err(anyhow::anyhow!(
+            "Tx {} landed, but none of its events are tagged as message {index}'s",
+            resp.txhash
+        ));
+    }
+
+    Ok(AnyMultiTxResponse {
+        tx: AnyTxResponse::Climb(resp),
+        msg_events,
+    })
+}
+
+/// What a transaction would do, had it been broadcast
+#[derive(Debug, Clone)]
+pub struct Simulation {
+    /// `None` for MultiTest, which doesn't meter gas
+    pub gas_used: Option<u64>,
+    pub events: Vec<cosmwasm_std::Event>,
+}
+
+impl Simulation {
+    /// Like `AnyTxResponse::events`
+    pub fn events<E>(&self, event_type: &str) -> Result<Vec<E>>
+    where
+        E: for<'e> TryFrom<&'e cosmwasm_std::Event, Error = anyhow::Error>,
+    {
+        CosmosTxEvents::from(self.events.as_slice())
+            .filter_events_by_type(event_type)
+            .map(|event| E::try_from(&cosmwasm_std::Event::from(event)))
+            .collect()
+    }
+}
+
+/// Simulates a transaction of `messages` from `client`, for whatever `AnyExecutor` doesn't cover
+/// (e.g. instantiating, or several messages at once)
+pub async fn climb_simulate(
+    client: &SigningClient,
+    messages: Vec<layer_climb::proto::Any>,
+) -> Result<Simulation> {
+    let resp = client.tx_builder().simulate(messages).await?;
+
+    let events = resp
+        .result
+        .map(|result| result.events)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|event| {
+            cosmwasm_std::Event::new(event.r#type).add_attributes(
+                event
+                    .attributes
+                    .into_iter()
+                    .map(|attr| (attr.key, attr.value)),
+            )
+        })
+        .collect();
+
+    Ok(Simulation {
+        gas_used: resp.gas_info.map(|gas_info| gas_info.gas_used),
+        events,
+    })
+}
+
+fn climb_coins(funds: &[cosmwasm_std::Coin]) -> Vec<layer_climb::prelude::Coin> {
+    funds
+        .iter()
+        .map(|c| layer_climb::prelude::Coin {
+            denom: c.denom.clone(),
+            amount: c.amount.to_string(),
+        })
+        .collect()
 }
 
 #[derive(Debug)]
@@ -147,6 +520,30 @@ impl<'a> From<&'a AnyTxResponse> for CosmosTxEvents<'a> {
 }
 
 impl AnyTxResponse {
+    /// Every event of `event_type` (with or without the `wasm-` prefix), e.g.
+    /// `resp.events::<EmailEvent>(EmailEvent::EVENT_TYPE)`
+    pub fn events<E>(&self, event_type: &str) -> Result<Vec<E>>
+    where
+        E: for<'e> TryFrom<&'e cosmwasm_std::Event, Error = anyhow::Error>,
+    {
+        CosmosTxEvents::from(self)
+            .filter_events_by_type(event_type)
+            .map(|event| E::try_from(&cosmwasm_std::Event::from(event)))
+            .collect()
+    }
+
+    /// The first event of `event_type`, if there is one
+    pub fn event<E>(&self, event_type: &str) -> Result<Option<E>>
+    where
+        E: for<'e> TryFrom<&'e cosmwasm_std::Event, Error = anyhow::Error>,
+    {
+        CosmosTxEvents::from(self)
+            .filter_events_by_type(event_type)
+            .next()
+            .map(|event| E::try_from(&cosmwasm_std::Event::from(event)))
+            .transpose()
+    }
+
     pub fn unchecked_into_tx_response(self) -> layer_climb::proto::abci::TxResponse {
         match self {
             Self::Climb(tx_resp) => tx_resp,
@@ -0,0 +1,146 @@
+//! Follows the chain block by block and indexes the service handler's emails, and who the
+//! user registry has registered where, into a local SQLite database
+//!
+//! The last indexed height is stored with the events, so a restarted indexer picks up
+//! where it left off.
+mod store;
+
+pub use store::{
+    BlockEvents, EventStore, IndexedEmail, IndexedPrivateEmail, IndexedRegistration, RegistryEvent,
+};
+
+use std::{path::Path, time::Duration};
+
+use anyhow::Result;
+use app_contract_api::{
+    service_handler::event::{EmailEvent, PrivateEmailEvent},
+    user_registry::event::{
+        UserDeregisteredEvent, UserIdLinkedEvent, UserIdUnlinkedEvent, UserProxyUpdatedEvent,
+        UserRegisteredEvent,
+    },
+};
+use layer_climb::{events::CosmosTxEvents, prelude::Address, querier::QueryClient};
+
+/// The attribute the chain adds to every wasm event
+const CONTRACT_ADDRESS_ATTR: &str = "_contract_address";
+
+pub struct Indexer {
+    client: QueryClient,
+    store: EventStore,
+    service_handler: Address,
+    user_registry: Address,
+    start_height: u64,
+}
+
+impl Indexer {
+    /// `start_height` is only used when the database has no cursor yet
+    pub fn new(
+        client: QueryClient,
+        db_path: impl AsRef<Path>,
+        service_handler: Address,
+        user_registry: Address,
+        start_height: u64,
+    ) -> Result<Self> {
+        Ok(Self {
+            client,
+            store: EventStore::open(db_path)?,
+            service_handler,
+            user_registry,
+            start_height,
+        })
+    }
+
+    /// For querying what's been indexed so far
+    pub fn store(&self) -> &EventStore {
+        &self.store
+    }
+
+    /// The next block to index
+    pub fn next_height(&self) -> Result<u64> {
+        Ok(match self.store.cursor()? {
+            Some(height) => height + 1,
+            None => self.start_height,
+        })
+    }
+
+    /// Indexes every block up to the chain's current height, returns how many were indexed
+    pub async fn catch_up(&mut self) -> Result<u64> {
+        let latest = self.client.block_height().await?;
+        let mut height = self.next_height()?;
+        let mut indexed = 0;
+
+        while height <= latest {
+            let block = self.fetch_block(height).await?;
+            self.store.insert_block(&block)?;
+
+            height += 1;
+            indexed += 1;
+        }
+
+        Ok(indexed)
+    }
+
+    /// Keeps catching up, waiting `poll_interval` whenever it's at the tip
+    pub async fn run(&mut self, poll_interval: Duration) -> Result<()> {
+        loop {
+            let indexed = self.catch_up().await?;
+
+            if indexed > 0 {
+                tracing::info!(
+                    "Indexed {indexed} blocks, up to {}",
+                    self.next_height()? - 1
+                );
+            }
+
+            tokio::time::sleep(poll_interval).await;
+        }
+    }
+
+    async fn fetch_block(&self, height: u64) -> Result<BlockEvents> {
+        let events = self.client.fetch_block_events(height).await?;
+        let events = CosmosTxEvents::from(events);
+
+        let mut block = BlockEvents {
+            height,
+            ..Default::default()
+        };
+
+        let service_handler = self.service_handler.to_string();
+        let user_registry = self.user_registry.to_string();
+
+        for event in events.events_iter() {
+            let event = cosmwasm_std::Event::from(event);
+
+            let contract = event
+                .attributes
+                .iter()
+                .find(|attr| attr.key == CONTRACT_ADDRESS_ATTR)
+                .map(|attr| attr.value.as_str());
+
+            // anyone can emit an event with the same type, only trust ours
+            if contract == Some(service_handler.as_str()) {
+                if let Ok(email) = EmailEvent::try_from(&event) {
+                    block.emails.push(email);
+                } else if let Ok(email) = PrivateEmailEvent::try_from(&event) {
+                    block.private_emails.push(email);
+                }
+            } else if contract == Some(user_registry.as_str()) {
+                if let Some(event) = registry_event(&event) {
+                    block.registry.push(event);
+                }
+            }
+        }
+
+        Ok(block)
+    }
+}
+
+fn registry_event(event: &cosmwasm_std::Event) -> Option<RegistryEvent> {
+    UserRegisteredEvent::try_from(event)
+        .map(RegistryEvent::Registered)
+        .or_else(|_| UserIdLinkedEvent::try_from(event).map(RegistryEvent::Linked))
+        .or_else(|_| UserIdUnlinkedEvent::try_from(event).map(RegistryEvent::Unlinked))
+        .or_else(|_| UserProxyUpdatedEvent::try_from(event).map(RegistryEvent::ProxyUpdated))
+        .or_else(|_| UserDeregisteredEvent::try_from(event).map(RegistryEvent::Deregistered))
+        .ok()
+}
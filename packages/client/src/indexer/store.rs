@@ -0,0 +1,523 @@
+//! SQLite storage for indexed events
+
+use std::{ops::RangeInclusive, path::Path};
+
+use anyhow::Result;
+use app_contract_api::{
+    service_handler::{
+        event::{EmailEvent, PrivateEmailEvent},
+        msg::UserIdEmail,
+    },
+    user_registry::{
+        event::{
+            UserDeregisteredEvent, UserIdLinkedEvent, UserIdUnlinkedEvent, UserProxyUpdatedEvent,
+            UserRegisteredEvent,
+        },
+        msg::UserId,
+    },
+};
+use cosmwasm_std::Addr;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS cursor (
+        id INTEGER PRIMARY KEY CHECK (id = 0),
+        height INTEGER NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS emails (
+        pagination_id INTEGER PRIMARY KEY,
+        height INTEGER NOT NULL,
+        user_id TEXT NOT NULL,
+        subject TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS emails_by_user ON emails (user_id, height);
+    CREATE INDEX IF NOT EXISTS emails_by_height ON emails (height);
+
+    CREATE TABLE IF NOT EXISTS private_emails (
+        pagination_id INTEGER PRIMARY KEY,
+        height INTEGER NOT NULL,
+        user_id TEXT NOT NULL,
+        commitment TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS private_emails_by_user ON private_emails (user_id, height);
+
+    CREATE TABLE IF NOT EXISTS registrations (
+        user_id TEXT PRIMARY KEY,
+        account_id INTEGER,
+        proxy_address TEXT NOT NULL,
+        height INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS registrations_by_account ON registrations (account_id);
+    CREATE INDEX IF NOT EXISTS registrations_by_proxy ON registrations (proxy_address);
+";
+
+/// An email, and the block it landed in
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedEmail {
+    pub height: u64,
+    pub event: EmailEvent,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedPrivateEmail {
+    pub height: u64,
+    pub event: PrivateEmailEvent,
+}
+
+/// Where a user id is registered now, as of the last indexed block
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedRegistration {
+    /// When it last changed
+    pub height: u64,
+    pub user_id: UserId,
+    /// `None` until an event names it, plain registrations don't
+    pub account_id: Option<u64>,
+    pub proxy_address: Addr,
+}
+
+/// Anything the user registry emits that changes who's registered where
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryEvent {
+    Registered(UserRegisteredEvent),
+    Linked(UserIdLinkedEvent),
+    Unlinked(UserIdUnlinkedEvent),
+    ProxyUpdated(UserProxyUpdatedEvent),
+    Deregistered(UserDeregisteredEvent),
+}
+
+/// The events of one block, written together with the cursor
+#[derive(Debug, Clone, Default)]
+pub struct BlockEvents {
+    pub height: u64,
+    pub emails: Vec<EmailEvent>,
+    pub private_emails: Vec<PrivateEmailEvent>,
+    /// In the order they were emitted, later ones build on earlier ones
+    pub registry: Vec<RegistryEvent>,
+}
+
+pub struct EventStore {
+    conn: Connection,
+}
+
+impl EventStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::new(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::new(Connection::open_in_memory()?)
+    }
+
+    fn new(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// The last block that was fully indexed
+    pub fn cursor(&self) -> Result<Option<u64>> {
+        Ok(self
+            .conn
+            .query_row("SELECT height FROM cursor WHERE id = 0", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .optional()?
+            .map(|height| height as u64))
+    }
+
+    /// Stores a block's events and moves the cursor past it, all or nothing
+    ///
+    /// Blocks at or below the cursor were already stored and are skipped, so resuming from an
+    /// older cursor is harmless, and registry events are never applied twice.
+    pub fn insert_block(&mut self, block: &BlockEvents) -> Result<()> {
+        if self.cursor()?.is_some_and(|cursor| cursor >= block.height) {
+            return Ok(());
+        }
+
+        let tx = self.conn.transaction()?;
+        let height = block.height as i64;
+
+        for event in &block.emails {
+            tx.execute(
+                "INSERT OR IGNORE INTO emails (pagination_id, height, user_id, subject)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    event.pagination_id as i64,
+                    height,
+                    event.email.from.as_str(),
+                    event.email.subject
+                ],
+            )?;
+        }
+
+        for event in &block.private_emails {
+            tx.execute(
+                "INSERT OR IGNORE INTO private_emails (pagination_id, height, user_id, commitment)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    event.pagination_id as i64,
+                    height,
+                    event.from.as_str(),
+                    event.commitment
+                ],
+            )?;
+        }
+
+        for event in &block.registry {
+            apply_registry_event(&tx, height, event)?;
+        }
+
+        tx.execute(
+            "INSERT INTO cursor (id, height) VALUES (0, ?1)
+             ON CONFLICT (id) DO UPDATE SET height = MAX(height, excluded.height)",
+            params![height],
+        )?;
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Emails from `user_id` that landed within `heights`, oldest first
+    pub fn emails_from(
+        &self,
+        user_id: &UserId,
+        heights: RangeInclusive<u64>,
+    ) -> Result<Vec<IndexedEmail>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT height, pagination_id, user_id, subject FROM emails
+             WHERE user_id = ?1 AND height BETWEEN ?2 AND ?3
+             ORDER BY pagination_id",
+        )?;
+
+        let rows = stmt.query_map(
+            params![
+                user_id.as_str(),
+                *heights.start() as i64,
+                *heights.end() as i64
+            ],
+            email_row,
+        )?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Every email that landed within `heights`, oldest first
+    pub fn emails_between(&self, heights: RangeInclusive<u64>) -> Result<Vec<IndexedEmail>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT height, pagination_id, user_id, subject FROM emails
+             WHERE height BETWEEN ?1 AND ?2
+             ORDER BY pagination_id",
+        )?;
+
+        let rows = stmt.query_map(
+            params![*heights.start() as i64, *heights.end() as i64],
+            email_row,
+        )?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Like `emails_from`, for a service handler in privacy mode
+    pub fn private_emails_from(
+        &self,
+        user_id: &UserId,
+        heights: RangeInclusive<u64>,
+    ) -> Result<Vec<IndexedPrivateEmail>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT height, pagination_id, user_id, commitment FROM private_emails
+             WHERE user_id = ?1 AND height BETWEEN ?2 AND ?3
+             ORDER BY pagination_id",
+        )?;
+
+        let rows = stmt.query_map(
+            params![
+                user_id.as_str(),
+                *heights.start() as i64,
+                *heights.end() as i64
+            ],
+            |row| {
+                Ok(IndexedPrivateEmail {
+                    height: row.get::<_, i64>(0)? as u64,
+                    event: PrivateEmailEvent {
+                        pagination_id: row.get::<_, i64>(1)? as u64,
+                        from: UserId::new_raw(row.get(2)?),
+                        commitment: row.get(3)?,
+                    },
+                })
+            },
+        )?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Where `user_id` is registered, `None` if it isn't (anymore)
+    pub fn registration(&self, user_id: &UserId) -> Result<Option<IndexedRegistration>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT height, user_id, account_id, proxy_address FROM registrations
+                 WHERE user_id = ?1",
+                params![user_id.as_str()],
+                registration_row,
+            )
+            .optional()?)
+    }
+
+    /// Every user id registered to `proxy_address`
+    pub fn registrations_for_proxy(
+        &self,
+        proxy_address: &Addr,
+    ) -> Result<Vec<IndexedRegistration>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT height, user_id, account_id, proxy_address FROM registrations
+             WHERE proxy_address = ?1
+             ORDER BY height, user_id",
+        )?;
+
+        let rows = stmt.query_map(params![proxy_address.as_str()], registration_row)?;
+
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+}
+
+fn apply_registry_event(tx: &Transaction, height: i64, event: &RegistryEvent) -> Result<()> {
+    match event {
+        RegistryEvent::Registered(event) => {
+            tx.execute(
+                "INSERT OR REPLACE INTO registrations (user_id, account_id, proxy_address, height)
+                 VALUES (?1, NULL, ?2, ?3)",
+                params![event.user_id.as_str(), event.proxy_address.as_str(), height],
+            )?;
+        }
+        RegistryEvent::Linked(event) => {
+            let account_id = event.account_id as i64;
+
+            tx.execute(
+                "INSERT OR REPLACE INTO registrations (user_id, account_id, proxy_address, height)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    event.user_id.as_str(),
+                    account_id,
+                    event.proxy_address.as_str(),
+                    height
+                ],
+            )?;
+            // the account's first user id was registered without one
+            tx.execute(
+                "UPDATE registrations SET account_id = ?1
+                 WHERE proxy_address = ?2 AND account_id IS NULL",
+                params![account_id, event.proxy_address.as_str()],
+            )?;
+        }
+        RegistryEvent::Unlinked(event) => {
+            tx.execute(
+                "DELETE FROM registrations WHERE user_id = ?1",
+                params![event.user_id.as_str()],
+            )?;
+        }
+        RegistryEvent::ProxyUpdated(event) => {
+            tx.execute(
+                "UPDATE registrations SET account_id = ?1, proxy_address = ?2, height = ?3
+                 WHERE account_id = ?1 OR (account_id IS NULL AND proxy_address = ?4)",
+                params![
+                    event.account_id as i64,
+                    event.proxy_address.as_str(),
+                    height,
+                    event.old_proxy_address.as_str()
+                ],
+            )?;
+        }
+        RegistryEvent::Deregistered(event) => {
+            for user_id in &event.user_ids {
+                tx.execute(
+                    "DELETE FROM registrations WHERE user_id = ?1",
+                    params![user_id.as_str()],
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn email_row(row: &rusqlite::Row) -> rusqlite::Result<IndexedEmail> {
+    Ok(IndexedEmail {
+        height: row.get::<_, i64>(0)? as u64,
+        event: EmailEvent {
+            pagination_id: row.get::<_, i64>(1)? as u64,
+            email: UserIdEmail {
+                from: UserId::new_raw(row.get(2)?),
+                subject: row.get(3)?,
+            },
+        },
+    })
+}
+
+fn registration_row(row: &rusqlite::Row) -> rusqlite::Result<IndexedRegistration> {
+    Ok(IndexedRegistration {
+        height: row.get::<_, i64>(0)? as u64,
+        user_id: UserId::new_raw(row.get(1)?),
+        account_id: row.get::<_, Option<i64>>(2)?.map(|id| id as u64),
+        proxy_address: Addr::unchecked(row.get::<_, String>(3)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email(user: &str, pagination_id: u64) -> EmailEvent {
+        EmailEvent {
+            email: UserIdEmail {
+                from: UserId::new_email_address(user),
+                subject: "deposit".to_string(),
+            },
+            pagination_id,
+        }
+    }
+
+    #[test]
+    fn test_emails_from_by_height() {
+        let mut store = EventStore::open_in_memory().unwrap();
+
+        for (height, pagination_id, user) in [
+            (10, 1, "alice@example.com"),
+            (11, 2, "bob@example.com"),
+            (12, 3, "alice@example.com"),
+            (20, 4, "alice@example.com"),
+        ] {
+            store
+                .insert_block(&BlockEvents {
+                    height,
+                    emails: vec![email(user, pagination_id)],
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+
+        let alice = UserId::new_email_address("alice@example.com");
+        let emails = store.emails_from(&alice, 10..=12).unwrap();
+
+        assert_eq!(
+            emails
+                .iter()
+                .map(|email| (email.height, email.event.pagination_id))
+                .collect::<Vec<_>>(),
+            vec![(10, 1), (12, 3)]
+        );
+        assert_eq!(store.emails_between(11..=20).unwrap().len(), 3);
+        assert_eq!(store.cursor().unwrap(), Some(20));
+    }
+
+    #[test]
+    fn test_reindexing_is_idempotent() {
+        let mut store = EventStore::open_in_memory().unwrap();
+
+        let alice = UserId::new_email_address("alice@example.com");
+
+        let block = BlockEvents {
+            height: 5,
+            emails: vec![email("alice@example.com", 1)],
+            private_emails: vec![PrivateEmailEvent {
+                from: alice.clone(),
+                commitment: "commitment".to_string(),
+                pagination_id: 2,
+            }],
+            registry: vec![RegistryEvent::Registered(UserRegisteredEvent {
+                user_id: alice.clone(),
+                proxy_address: Addr::unchecked("proxy"),
+            })],
+        };
+
+        store.insert_block(&block).unwrap();
+        store.insert_block(&block).unwrap();
+        // an older block doesn't move the cursor back
+        store
+            .insert_block(&BlockEvents {
+                height: 4,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(store.emails_between(0..=10).unwrap().len(), 1);
+        assert_eq!(store.private_emails_from(&alice, 0..=10).unwrap().len(), 1);
+        assert!(store.registration(&alice).unwrap().is_some());
+        assert_eq!(store.cursor().unwrap(), Some(5));
+    }
+
+    #[test]
+    fn test_registrations_follow_the_registry() {
+        let mut store = EventStore::open_in_memory().unwrap();
+
+        let home = UserId::new_email_address("alice@example.com");
+        let work = UserId::new_email_address("alice@work.example.com");
+        let proxy = Addr::unchecked("proxy");
+        let new_proxy = Addr::unchecked("new-proxy");
+
+        let blocks = [
+            vec![RegistryEvent::Registered(UserRegisteredEvent {
+                user_id: home.clone(),
+                proxy_address: proxy.clone(),
+            })],
+            vec![RegistryEvent::Linked(UserIdLinkedEvent {
+                account_id: 7,
+                user_id: work.clone(),
+                proxy_address: proxy.clone(),
+            })],
+            vec![RegistryEvent::ProxyUpdated(UserProxyUpdatedEvent {
+                account_id: 7,
+                old_proxy_address: proxy.clone(),
+                proxy_address: new_proxy.clone(),
+            })],
+        ];
+
+        for (height, registry) in blocks.into_iter().enumerate() {
+            store
+                .insert_block(&BlockEvents {
+                    height: height as u64 + 1,
+                    registry,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+
+        let registrations = store.registrations_for_proxy(&new_proxy).unwrap();
+        assert_eq!(registrations.len(), 2);
+        assert!(registrations
+            .iter()
+            .all(|registration| registration.account_id == Some(7)));
+        assert!(store.registrations_for_proxy(&proxy).unwrap().is_empty());
+
+        store
+            .insert_block(&BlockEvents {
+                height: 4,
+                registry: vec![RegistryEvent::Unlinked(UserIdUnlinkedEvent {
+                    account_id: 7,
+                    user_id: home.clone(),
+                })],
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(store.registration(&home).unwrap(), None);
+        assert_eq!(
+            store.registration(&work).unwrap().unwrap().proxy_address,
+            new_proxy
+        );
+
+        store
+            .insert_block(&BlockEvents {
+                height: 5,
+                registry: vec![RegistryEvent::Deregistered(UserDeregisteredEvent {
+                    account_id: 7,
+                    user_ids: vec![work.clone()],
+                    proxy_address: new_proxy,
+                })],
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(store.registration(&work).unwrap(), None);
+    }
+}
@@ -5,4 +5,6 @@
 pub mod address;
 pub mod contracts;
 pub mod executor;
+#[cfg(feature = "indexer")]
+pub mod indexer;
 pub mod querier;
@@ -0,0 +1,105 @@
+use std::str::FromStr;
+
+use cosmwasm_std::Decimal;
+
+use crate::wavs::types::chain::CosmosChainConfig;
+
+/// The gas price to submit at, `None` to leave it to the chain config
+///
+/// Set from the service's config vars, all in the chain's gas denom per unit of gas,
+/// and the same for every target:
+/// - `GAS_PRICE`: a fixed price, instead of the chain config's
+/// - `GAS_PRICE_MULTIPLIER`: applied on top, e.g. 1.5 to outbid a fee market
+/// - `MAX_GAS_PRICE`: never pay more than this per unit of gas
+///
+/// The gas used is only known once WAVS estimates it, so the fee cap is a cap on the price.
+/// Prices are usually fractions of a unit (0.0053untrn), so they're kept as decimals
+/// throughout and handed to the submit action as fixed-point, see [`submit_gas_price`].
+pub fn gas_price(chain_config: &CosmosChainConfig) -> Result<Option<u128>, String> {
+    let fixed = decimal_config_var("GAS_PRICE")?;
+    let multiplier = decimal_config_var("GAS_PRICE_MULTIPLIER")?;
+    let max = decimal_config_var("MAX_GAS_PRICE")?;
+
+    if fixed.is_none() && multiplier.is_none() && max.is_none() {
+        return Ok(None);
+    }
+
+    let base = match fixed {
+        Some(fixed) => fixed,
+        // the shortest string that round-trips, so 0.025f32 is 0.025 and not 0.0250000004
+        None => Decimal::from_str(&chain_config.gas_price.to_string()).map_err(|e| {
+            format!(
+                "chain config gas price {} is not a decimal: {e}",
+                chain_config.gas_price
+            )
+        })?,
+    };
+
+    let price = base
+        .checked_mul(multiplier.unwrap_or(Decimal::one()))
+        .map_err(|e| format!("gas price {base} times multiplier overflows: {e}"))?;
+    let capped = capped_price(price, max);
+
+    if capped != price {
+        crate::host::log(
+            crate::wavs::types::core::LogLevel::Warn,
+            &format!("gas price {price} is over the cap, submitting at {capped}"),
+        );
+    }
+
+    Ok(Some(submit_gas_price(capped)))
+}
+
+fn capped_price(price: Decimal, max: Option<Decimal>) -> Decimal {
+    match max {
+        Some(max) => price.min(max),
+        None => price,
+    }
+}
+
+/// The submit action's gas price is fixed-point, the price times 10^18
+/// (a [`Decimal`]'s atomics), so a fraction of a unit of the gas denom isn't rounded away
+fn submit_gas_price(price: Decimal) -> u128 {
+    price.atomics().u128()
+}
+
+fn decimal_config_var(key: &str) -> Result<Option<Decimal>, String> {
+    match crate::host::config_var(key) {
+        Some(value) => match Decimal::from_str(&value) {
+            Ok(decimal) if !decimal.is_zero() => Ok(Some(decimal)),
+            _ => Err(format!(
+                "{key} config var must be a positive decimal, got {value}"
+            )),
+        },
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_fractional_prices_are_kept() {
+        assert_eq!(submit_gas_price(decimal("0.0053")), 5_300_000_000_000_000);
+        assert_eq!(
+            submit_gas_price(decimal("0.0053") * decimal("1.5")),
+            7_950_000_000_000_000
+        );
+    }
+
+    #[test]
+    fn test_cap_under_one_unit() {
+        // the documented .env.example config, 0.05 * 1.5 under a 0.5 cap
+        let price = decimal("0.05") * decimal("1.5");
+        assert_eq!(capped_price(price, Some(decimal("0.5"))), decimal("0.075"));
+
+        let price = decimal("0.4") * decimal("1.5");
+        assert_eq!(capped_price(price, Some(decimal("0.5"))), decimal("0.5"));
+        assert_eq!(capped_price(price, None), decimal("0.6"));
+    }
+}
@@ -1,6 +1,24 @@
+mod gas;
+mod retry;
+mod routing;
+
 use layer_climb::prelude::CosmosAddr;
 
-use crate::wavs::aggregator::output::{CosmosAddress, CosmosSubmitAction, SubmitAction};
+use crate::{
+    retry::{
+        backoff_seconds, retry_in, DeadLetter, DeadLetterReason, FailureKind, Submission,
+        SubmissionStatus, MAX_ATTEMPTS,
+    },
+    routing::{SubmitTargets, Target},
+    wasi::clocks::wall_clock,
+    wavs::{
+        aggregator::output::{
+            CosmosAddress, CosmosSubmitAction, EvmAddress, EvmSubmitAction, SubmitAction,
+            TimerAction,
+        },
+        types::core::Duration,
+    },
+};
 
 wit_bindgen::generate!({
     path: "../../../../wit-definitions/aggregator/wit",
@@ -15,39 +33,211 @@ wit_bindgen::generate!({
 struct Component;
 
 impl Guest for Component {
-    fn process_input(_input: AggregatorInput) -> Result<Vec<AggregatorAction>, String> {
-        let chain = host::config_var("CHAIN").ok_or("CHAIN config var is required")?;
-        let service_handler_addr = host::config_var("SERVICE_HANDLER_CONTRACT_ADDRESS")
-            .ok_or("SERVICE_HANDLER_CONTRACT_ADDRESS config var is required")?;
-
-        host::get_cosmos_chain_config(&chain)
-            .ok_or(format!("failed to get chain config for {}", chain))?;
-
-        let service_handler_addr =
-            CosmosAddr::new_str(&service_handler_addr, None).map_err(|e| e.to_string())?;
-
-        Ok(vec![AggregatorAction::Submit(SubmitAction::Cosmos(
-            CosmosSubmitAction {
-                chain: chain.to_string(),
-                address: CosmosAddress {
-                    bech32_addr: service_handler_addr.to_string(),
-                    prefix_len: service_handler_addr.prefix().len() as u32,
-                },
-                gas_price: None,
-            },
-        ))])
+    fn process_input(input: AggregatorInput) -> Result<Vec<AggregatorAction>, String> {
+        let targets = SubmitTargets::new()?.route(&input.operator_response.payload)?;
+
+        if targets.is_empty() {
+            host::log(
+                wavs::types::core::LogLevel::Info,
+                &format!("submission {} is routed nowhere", event_key()),
+            );
+            return Ok(vec![]);
+        }
+
+        submit_current(&mut Submission::new(targets))
     }
 
-    fn handle_timer_callback(_input: AggregatorInput) -> Result<Vec<AggregatorAction>, String> {
-        Ok(vec![])
+    /// Follows up on the submission: retries it if it failed, moves on to the next target
+    /// if it landed, or waits a bit longer for the result
+    fn handle_timer_callback(input: AggregatorInput) -> Result<Vec<AggregatorAction>, String> {
+        let key = event_key();
+
+        // every target has it, or it was dead-lettered
+        let Some(mut submission) = Submission::load(&key)? else {
+            return Ok(vec![]);
+        };
+
+        match submission.status {
+            SubmissionStatus::Failed { failed_at, .. } => {
+                match retry_in(submission.attempt, failed_at, wall_clock::now().seconds) {
+                    0 => {
+                        submission.attempt += 1;
+                        submit_current(&mut submission)
+                    }
+                    // the check came before the backoff from the failure was up
+                    secs => Ok(vec![timer(secs)]),
+                }
+            }
+            SubmissionStatus::Queued => submit_current(&mut submission),
+            SubmissionStatus::Pending { .. } if submission.pending_too_long() => {
+                dead_letter(&input, &submission, DeadLetterReason::NoResult, None)?;
+
+                if submission.advance() {
+                    submit_current(&mut submission)
+                } else {
+                    Submission::delete(&key)?;
+                    Ok(vec![])
+                }
+            }
+            SubmissionStatus::Pending { checks } => {
+                submission.status = SubmissionStatus::Pending { checks: checks + 1 };
+                submission.save(&key)?;
+
+                Ok(vec![check_after(submission.attempt)])
+            }
+        }
     }
 
     fn handle_submit_callback(
-        _input: AggregatorInput,
-        _tx_result: Result<AnyTxHash, String>,
+        input: AggregatorInput,
+        tx_result: Result<AnyTxHash, String>,
     ) -> Result<(), String> {
-        Ok(())
+        let key = event_key();
+
+        // a late result for the last target, given up on already
+        let Some(mut submission) = Submission::load(&key)? else {
+            host::log(
+                wavs::types::core::LogLevel::Warn,
+                &format!("ignoring a late result for submission {key}: {tx_result:?}"),
+            );
+            return Ok(());
+        };
+
+        if !submission.record_result() {
+            host::log(
+                wavs::types::core::LogLevel::Warn,
+                &format!(
+                    "ignoring a late result for submission {key}, now on {}: {tx_result:?}",
+                    submission.target().name
+                ),
+            );
+            return submission.save(&key);
+        }
+
+        let error = match tx_result {
+            Ok(_) => return finish_current(&key, submission),
+            Err(error) => error,
+        };
+
+        let reason = match FailureKind::classify(&error) {
+            FailureKind::Retryable if submission.attempt < MAX_ATTEMPTS => {
+                host::log(
+                    wavs::types::core::LogLevel::Warn,
+                    &format!(
+                        "submission {key} to {} attempt {} failed, retrying: {error}",
+                        submission.target().name,
+                        submission.attempt
+                    ),
+                );
+
+                // the pending timer picks it up from here
+                submission.status = SubmissionStatus::Failed {
+                    error,
+                    failed_at: wall_clock::now().seconds,
+                };
+                return submission.save(&key);
+            }
+            FailureKind::Retryable => DeadLetterReason::RetriesExhausted,
+            FailureKind::Permanent => DeadLetterReason::Permanent,
+        };
+
+        dead_letter(&input, &submission, reason, Some(error))?;
+        finish_current(&key, submission)
+    }
+}
+
+/// Submits to the current target, with a timer to follow up on it
+fn submit_current(submission: &mut Submission) -> Result<Vec<AggregatorAction>, String> {
+    let action = submit_action(submission.target())?;
+
+    submission.status = SubmissionStatus::Pending { checks: 0 };
+    submission.submitted += 1;
+    submission.save(&event_key())?;
+
+    Ok(vec![action, check_after(submission.attempt)])
+}
+
+/// The current target is done with, one way or another; the pending timer moves on from here
+fn finish_current(key: &str, mut submission: Submission) -> Result<(), String> {
+    if submission.advance() {
+        submission.save(key)
+    } else {
+        Submission::delete(key)
+    }
+}
+
+/// Chains in the WAVS config as EVM get the EVM service handler, the rest the CosmWasm one
+fn submit_action(target: &Target) -> Result<AggregatorAction, String> {
+    if host::get_evm_chain_config(&target.chain).is_some() {
+        let address = const_hex::decode(&target.address)
+            .ok()
+            .filter(|address| address.len() == 20)
+            .ok_or(format!("{} is not an EVM address", target.address))?;
+
+        // left to WAVS to estimate, the gas price config is in Cosmos gas denoms
+        return Ok(AggregatorAction::Submit(SubmitAction::Evm(
+            EvmSubmitAction {
+                chain: target.chain.clone(),
+                address: EvmAddress { raw_bytes: address },
+                gas_price: None,
+            },
+        )));
+    }
+
+    let chain_config = host::get_cosmos_chain_config(&target.chain)
+        .ok_or(format!("failed to get chain config for {}", target.chain))?;
+
+    let service_handler_addr =
+        CosmosAddr::new_str(&target.address, None).map_err(|e| e.to_string())?;
+
+    Ok(AggregatorAction::Submit(SubmitAction::Cosmos(
+        CosmosSubmitAction {
+            chain: target.chain.clone(),
+            address: CosmosAddress {
+                bech32_addr: service_handler_addr.to_string(),
+                prefix_len: service_handler_addr.prefix().len() as u32,
+            },
+            gas_price: gas::gas_price(&chain_config)?,
+        },
+    )))
+}
+
+/// A timer to see how the `attempt`th submission went
+fn check_after(attempt: u32) -> AggregatorAction {
+    timer(backoff_seconds(attempt))
+}
+
+fn timer(secs: u64) -> AggregatorAction {
+    AggregatorAction::Timer(TimerAction {
+        delay: Duration { secs },
+    })
+}
+
+fn dead_letter(
+    input: &AggregatorInput,
+    submission: &Submission,
+    reason: DeadLetterReason,
+    error: Option<String>,
+) -> Result<(), String> {
+    let error = error.or_else(|| match &submission.status {
+        SubmissionStatus::Failed { error, .. } => Some(error.clone()),
+        SubmissionStatus::Pending { .. } | SubmissionStatus::Queued => None,
+    });
+
+    DeadLetter {
+        event_id: event_key(),
+        target: submission.target().clone(),
+        attempts: submission.attempt,
+        reason,
+        error,
+        payload: String::from_utf8_lossy(&input.operator_response.payload).into_owned(),
     }
+    .save()
+}
+
+/// Submission state is kept per envelope, under its event id
+fn event_key() -> String {
+    const_hex::encode(host::get_event_id())
 }
 
 export!(Component);
@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{routing::Target, wasi::keyvalue::store};
+
+/// Submissions are given up on after this many attempts
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry, doubled for every one after it
+const BASE_DELAY_SECONDS: u64 = 10;
+
+/// No retry waits longer than this
+const MAX_DELAY_SECONDS: u64 = 300;
+
+/// How many times a check can find the submission still without a result before giving up
+const MAX_PENDING_CHECKS: u32 = 3;
+
+const SUBMISSIONS_BUCKET: &str = "submissions";
+const DEAD_LETTERS_BUCKET: &str = "dead-letters";
+
+/// Whether submitting the same envelope again could go differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    Retryable,
+    Permanent,
+}
+
+impl FailureKind {
+    /// Sorts a failed submission by the chain's error message
+    ///
+    /// Contract errors are deterministic, so anything not known to be transient is permanent.
+    pub fn classify(error: &str) -> Self {
+        const RETRYABLE: &[&str] = &[
+            // gas is estimated again on every submission
+            "out of gas",
+            // another transaction from the same signer got in first
+            "account sequence mismatch",
+            "incorrect account sequence",
+            "mempool is full",
+            "timed out",
+            "timeout",
+            "deadline exceeded",
+            "connection",
+            "unavailable",
+        ];
+
+        let error = error.to_lowercase();
+
+        if RETRYABLE.iter().any(|pattern| error.contains(pattern)) {
+            Self::Retryable
+        } else {
+            Self::Permanent
+        }
+    }
+}
+
+/// Seconds to wait before the `attempt`th submission is checked on, or retried once it failed
+pub fn backoff_seconds(attempt: u32) -> u64 {
+    BASE_DELAY_SECONDS
+        .saturating_mul(1u64 << attempt.saturating_sub(1).min(32))
+        .min(MAX_DELAY_SECONDS)
+}
+
+/// Seconds left before the `attempt`th submission, which failed at `failed_at`, is retried
+///
+/// The backoff counts from the failure, the check that finds it may come sooner.
+pub fn retry_in(attempt: u32, failed_at: u64, now: u64) -> u64 {
+    failed_at
+        .saturating_add(backoff_seconds(attempt))
+        .saturating_sub(now)
+}
+
+/// Where an envelope's submission stands, kept until every target has it or was dead-lettered
+///
+/// Targets are submitted to one at a time. Submit results only say how it went, not for which
+/// submission, so they're counted: the `n`th result is for the `n`th submission, and one for a
+/// submission given up on before is ignored rather than applied to the current target.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Submission {
+    pub targets: Vec<Target>,
+    /// Index into `targets`
+    pub current: usize,
+    /// 1 for the first submission to the current target
+    pub attempt: u32,
+    pub status: SubmissionStatus,
+    /// Submissions made so far, to any target
+    #[serde(default)]
+    pub submitted: u32,
+    /// Submit results received so far
+    #[serde(default)]
+    pub reported: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmissionStatus {
+    /// Submitted, no result yet
+    Pending { checks: u32 },
+    /// The last attempt failed in a way that's worth another try
+    Failed {
+        error: String,
+        /// Unix seconds, the retry backs off from here
+        #[serde(default)]
+        failed_at: u64,
+    },
+    /// The previous target is done, the current one is up next
+    Queued,
+}
+
+impl Submission {
+    pub fn new(targets: Vec<Target>) -> Self {
+        Self {
+            targets,
+            current: 0,
+            attempt: 1,
+            status: SubmissionStatus::Pending { checks: 0 },
+            submitted: 0,
+            reported: 0,
+        }
+    }
+
+    /// Counts a submit result in, `false` if it's for an earlier submission than the current one
+    pub fn record_result(&mut self) -> bool {
+        self.reported += 1;
+        self.reported == self.submitted
+    }
+
+    pub fn target(&self) -> &Target {
+        &self.targets[self.current]
+    }
+
+    /// Moves on to the next target, `false` if there are none left
+    pub fn advance(&mut self) -> bool {
+        if self.current + 1 >= self.targets.len() {
+            return false;
+        }
+
+        self.current += 1;
+        self.attempt = 1;
+        self.status = SubmissionStatus::Queued;
+        true
+    }
+
+    pub fn load(key: &str) -> Result<Option<Self>, String> {
+        let Some(bytes) = bucket(SUBMISSIONS_BUCKET)?
+            .get(key)
+            .map_err(|e| format!("Failed to read submission {key}: {e:?}"))?
+        else {
+            return Ok(None);
+        };
+
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| format!("Corrupt submission record {key}: {e}"))
+    }
+
+    pub fn save(&self, key: &str) -> Result<(), String> {
+        let bytes = serde_json::to_vec(self).map_err(|e| e.to_string())?;
+
+        bucket(SUBMISSIONS_BUCKET)?
+            .set(key, &bytes)
+            .map_err(|e| format!("Failed to write submission {key}: {e:?}"))
+    }
+
+    pub fn delete(key: &str) -> Result<(), String> {
+        bucket(SUBMISSIONS_BUCKET)?
+            .delete(key)
+            .map_err(|e| format!("Failed to delete submission {key}: {e:?}"))
+    }
+
+    pub fn pending_too_long(&self) -> bool {
+        matches!(self.status, SubmissionStatus::Pending { checks } if checks >= MAX_PENDING_CHECKS)
+    }
+}
+
+/// An envelope that won't be submitted again, for the operator to look into
+///
+/// Kept in the `dead-letters` bucket under the event id and target name, and logged as a JSON line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Hex encoded
+    pub event_id: String,
+    pub target: Target,
+    pub attempts: u32,
+    pub reason: DeadLetterReason,
+    /// The last error the chain gave, if any
+    pub error: Option<String>,
+    /// The operator's payload, the service handler's execute msg as JSON
+    pub payload: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadLetterReason {
+    Permanent,
+    RetriesExhausted,
+    NoResult,
+}
+
+impl DeadLetter {
+    pub fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+
+        crate::host::log(
+            crate::wavs::types::core::LogLevel::Error,
+            &format!("dead letter: {json}"),
+        );
+
+        let key = format!("{}/{}", self.event_id, self.target.name);
+
+        bucket(DEAD_LETTERS_BUCKET)?
+            .set(&key, json.as_bytes())
+            .map_err(|e| format!("Failed to write dead letter {key}: {e:?}"))
+    }
+}
+
+fn bucket(name: &str) -> Result<store::Bucket, String> {
+    store::open(name).map_err(|e| format!("Failed to open {name} bucket: {e:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submission(targets: &[&str]) -> Submission {
+        Submission::new(
+            targets
+                .iter()
+                .map(|name| Target {
+                    name: name.to_string(),
+                    chain: "cosmos:neutron-fork-1".to_string(),
+                    address: "neutron1handler".to_string(),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_classify() {
+        for error in [
+            "out of gas in location: wasm contract; gasWanted: 200000",
+            "account sequence mismatch, expected 12, got 11",
+            "Mempool is full",
+            "rpc error: code = Unavailable desc = connection refused",
+            "context deadline exceeded",
+        ] {
+            assert_eq!(
+                FailureKind::classify(error),
+                FailureKind::Retryable,
+                "{error}"
+            );
+        }
+
+        for error in [
+            "execute wasm contract failed: Unknown user",
+            "Already handled",
+            "",
+        ] {
+            assert_eq!(
+                FailureKind::classify(error),
+                FailureKind::Permanent,
+                "{error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_backoff_seconds() {
+        assert_eq!(backoff_seconds(0), 10);
+        assert_eq!(backoff_seconds(1), 10);
+        assert_eq!(backoff_seconds(2), 20);
+        assert_eq!(backoff_seconds(3), 40);
+        assert_eq!(backoff_seconds(5), 160);
+        assert_eq!(backoff_seconds(6), MAX_DELAY_SECONDS);
+        assert_eq!(backoff_seconds(u32::MAX), MAX_DELAY_SECONDS);
+    }
+
+    #[test]
+    fn test_retry_in_counts_from_the_failure() {
+        // failed 15 seconds after the first submission at 1000, found by the check at 1010
+        assert_eq!(retry_in(1, 1015, 1010), 15);
+        assert_eq!(retry_in(1, 1015, 1025), 0);
+        assert_eq!(retry_in(1, 1015, 2000), 0);
+        assert_eq!(retry_in(3, 1000, 1000), 40);
+    }
+
+    #[test]
+    fn test_results_for_abandoned_submissions_are_ignored() {
+        let mut submission = submission(&["neutron", "osmosis"]);
+
+        submission.submitted += 1;
+        // no result in time, on to the next target
+        assert!(submission.advance());
+        submission.submitted += 1;
+
+        // the first target's result arrives late
+        assert!(!submission.record_result());
+        assert_eq!(submission.target().name, "osmosis");
+
+        assert!(submission.record_result());
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut submission = submission(&["neutron", "osmosis"]);
+        submission.attempt = 3;
+
+        assert!(submission.advance());
+        assert_eq!(submission.target().name, "osmosis");
+        assert_eq!(submission.attempt, 1);
+        assert!(matches!(submission.status, SubmissionStatus::Queued));
+
+        assert!(!submission.advance());
+        assert_eq!(submission.target().name, "osmosis");
+    }
+}
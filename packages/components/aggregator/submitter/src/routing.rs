@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use app_contract_api::{service_handler::msg::CustomExecuteMsg, user_registry::msg::UserId};
+use serde::{Deserialize, Serialize};
+
+use crate::host;
+
+/// A service handler to submit to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Target {
+    /// The key it's listed under in SUBMIT_TARGETS
+    #[serde(default)]
+    pub name: String,
+    pub chain: String,
+    pub address: String,
+}
+
+/// Where each envelope is submitted to
+///
+/// Set from a JSON file when the service is deployed (the SUBMIT_TARGETS config var), e.g.
+///
+/// ```json
+/// {
+///   "targets": {
+///     "testnet": { "chain": "cosmos:pion-1", "address": "neutron1..." },
+///     "mirror": { "chain": "cosmos:other-1", "address": "other1..." },
+///     "vaults": { "chain": "evm:31337", "address": "0x..." }
+///   },
+///   "routes": [
+///     { "messages": ["link", "unlink"], "targets": ["testnet", "mirror"] },
+///     { "user_ids": ["<user id>"], "targets": ["mirror"] }
+///   ],
+///   "default": ["testnet"]
+/// }
+/// ```
+///
+/// The first route whose conditions all match wins, otherwise it's the default (or every target).
+/// Without it, the only target is CHAIN and SERVICE_HANDLER_CONTRACT_ADDRESS.
+///
+/// EVM targets need a workflow whose operators send the EVM payload (PAYLOAD_ENCODING=abi),
+/// so they can't share a workflow with CosmWasm ones.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubmitTargets {
+    targets: BTreeMap<String, Target>,
+    #[serde(default)]
+    routes: Vec<Route>,
+    default: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Route {
+    /// `CustomExecuteMsg` variants, as they're tagged in JSON (e.g. "email", "private_email")
+    #[serde(default)]
+    messages: Vec<String>,
+    /// Senders, as registered in the user registry
+    #[serde(default)]
+    user_ids: Vec<String>,
+    targets: Vec<String>,
+}
+
+impl SubmitTargets {
+    pub fn new() -> Result<Self, String> {
+        let mut submit_targets: Self = match host::config_var("SUBMIT_TARGETS") {
+            Some(config) => serde_json::from_str(&config)
+                .map_err(|e| format!("Invalid SUBMIT_TARGETS config: {e}"))?,
+            None => {
+                let chain = host::config_var("CHAIN").ok_or("CHAIN config var is required")?;
+                let address = host::config_var("SERVICE_HANDLER_CONTRACT_ADDRESS")
+                    .ok_or("SERVICE_HANDLER_CONTRACT_ADDRESS config var is required")?;
+
+                Self {
+                    targets: [(
+                        "default".to_string(),
+                        Target {
+                            name: String::new(),
+                            chain,
+                            address,
+                        },
+                    )]
+                    .into_iter()
+                    .collect(),
+                    routes: Vec::new(),
+                    default: None,
+                }
+            }
+        };
+
+        if submit_targets.targets.is_empty() {
+            return Err("SUBMIT_TARGETS has no targets".to_string());
+        }
+
+        for (name, target) in &mut submit_targets.targets {
+            target.name = name.clone();
+        }
+
+        let referenced = submit_targets
+            .routes
+            .iter()
+            .flat_map(|route| &route.targets)
+            .chain(submit_targets.default.iter().flatten());
+
+        for name in referenced {
+            if !submit_targets.targets.contains_key(name) {
+                return Err(format!("SUBMIT_TARGETS routes to unknown target {name}"));
+            }
+        }
+
+        Ok(submit_targets)
+    }
+
+    /// The targets for an operator's payload, in the order they're submitted to
+    pub fn route(&self, payload: &[u8]) -> Result<Vec<Target>, String> {
+        let names = match serde_json::from_slice::<CustomExecuteMsg>(payload) {
+            Ok(msg) => self.route_msg(&msg)?,
+            // nothing to route on
+            Err(_) => self.default_names(),
+        };
+
+        Ok(names
+            .into_iter()
+            .filter_map(|name| self.targets.get(&name).cloned())
+            .collect())
+    }
+
+    fn route_msg(&self, msg: &CustomExecuteMsg) -> Result<Vec<String>, String> {
+        match msg {
+            CustomExecuteMsg::Timestamped { msg, .. }
+            | CustomExecuteMsg::Confirmable { msg, .. } => self.route_msg(msg),
+            msg => {
+                let kind = message_kind(msg);
+                let sender = sender(msg);
+
+                let route = self.routes.iter().find(|route| {
+                    (route.messages.is_empty() || route.messages.iter().any(|m| m == kind))
+                        && (route.user_ids.is_empty()
+                            || sender.is_some_and(|sender| {
+                                route.user_ids.iter().any(|id| id == sender.as_str())
+                            }))
+                });
+
+                Ok(match route {
+                    Some(route) => route.targets.clone(),
+                    None => self.default_names(),
+                })
+            }
+        }
+    }
+
+    fn default_names(&self) -> Vec<String> {
+        self.default
+            .clone()
+            .unwrap_or_else(|| self.targets.keys().cloned().collect())
+    }
+}
+
+/// The variant's tag in JSON
+fn message_kind(msg: &CustomExecuteMsg) -> &'static str {
+    match msg {
+        CustomExecuteMsg::Email(_) => "email",
+        CustomExecuteMsg::Confirm { .. } => "confirm",
+        CustomExecuteMsg::Link { .. } => "link",
+        CustomExecuteMsg::Unlink { .. } => "unlink",
+        CustomExecuteMsg::PrivateEmail(_) => "private_email",
+        CustomExecuteMsg::ProvenEmail { .. } => "proven_email",
+        CustomExecuteMsg::Timestamped { .. } => "timestamped",
+        CustomExecuteMsg::Confirmable { .. } => "confirmable",
+    }
+}
+
+fn sender(msg: &CustomExecuteMsg) -> Option<&UserId> {
+    match msg {
+        CustomExecuteMsg::Email(email) => Some(&email.from),
+        CustomExecuteMsg::Confirm { from, .. }
+        | CustomExecuteMsg::Link { from, .. }
+        | CustomExecuteMsg::Unlink { from, .. } => Some(from),
+        CustomExecuteMsg::PrivateEmail(email) => Some(&email.from),
+        CustomExecuteMsg::ProvenEmail { email, .. } => Some(&email.from),
+        CustomExecuteMsg::Timestamped { msg, .. } | CustomExecuteMsg::Confirmable { msg, .. } => {
+            sender(msg)
+        }
+    }
+}
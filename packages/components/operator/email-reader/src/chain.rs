@@ -0,0 +1,150 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::Engine;
+use serde::{de::DeserializeOwned, Serialize};
+use wstd::http::{Body, Request};
+
+use crate::host;
+
+/// A contract on the service's chain, queried over its CometBFT RPC
+pub struct ContractQuerier {
+    address: String,
+    rpc_endpoint: String,
+}
+
+impl ContractQuerier {
+    /// The contract whose address is in the `address_var` config var, `None` if it isn't set
+    pub fn from_config(address_var: &str) -> Result<Option<Self>> {
+        let Some(address) = host::config_var(address_var) else {
+            return Ok(None);
+        };
+
+        let chain = host::config_var("CHAIN")
+            .ok_or_else(|| anyhow!("{address_var} is set without CHAIN"))?;
+
+        let rpc_endpoint = host::get_cosmos_chain_config(&chain)
+            .and_then(|config| config.rpc_endpoint)
+            .ok_or_else(|| anyhow!("No rpc endpoint for chain {}", chain))?;
+
+        Ok(Some(Self {
+            address,
+            rpc_endpoint: rpc_endpoint.trim_end_matches('/').to_string(),
+        }))
+    }
+
+    pub async fn query<T: DeserializeOwned>(&self, msg: &impl Serialize) -> Result<T> {
+        let query = serde_json::to_vec(msg)?;
+
+        serde_json::from_slice(&self.smart_query(&query).await?)
+            .context("Failed to parse contract query response")
+    }
+
+    /// CometBFT `abci_query` of `QuerySmartContractState`, the protobuf is simple enough to do by hand
+    async fn smart_query(&self, query: &[u8]) -> Result<Vec<u8>> {
+        let mut request_data = Vec::new();
+        encode_bytes_field(&mut request_data, 1, self.address.as_bytes());
+        encode_bytes_field(&mut request_data, 2, query);
+
+        let url = format!(
+            "{}/abci_query?path=%22/cosmwasm.wasm.v1.Query/SmartContractState%22&data=0x{}",
+            self.rpc_endpoint,
+            const_hex::encode(&request_data)
+        );
+
+        let http_client = wstd::http::Client::new();
+
+        let request = Request::get(url.as_str())
+            .header("Accept", "application/json")
+            .body(Body::empty())
+            .map_err(|e| anyhow!("Failed to build contract query: {}", e))?;
+
+        let response = http_client
+            .send(request)
+            .await
+            .map_err(|e| anyhow!("Contract query failed: {}", e))?;
+
+        if !response.status().is_success() {
+            bail!(
+                "Contract query returned error status: {}",
+                response.status()
+            );
+        }
+
+        let mut body = response.into_body();
+        let body = body.contents().await?;
+
+        let json_body: serde_json::Value =
+            serde_json::from_slice(&body).context("Failed to parse abci_query JSON")?;
+
+        let abci_response = &json_body["result"]["response"];
+
+        if abci_response["code"].as_u64().unwrap_or_default() != 0 {
+            bail!("Contract query failed: {}", abci_response["log"]);
+        }
+
+        let value = abci_response["value"]
+            .as_str()
+            .ok_or_else(|| anyhow!("No value in abci_query response"))?;
+
+        let value = base64::engine::general_purpose::STANDARD.decode(value)?;
+
+        decode_bytes_field(&value, 1)
+            .map(|data| data.to_vec())
+            .ok_or_else(|| anyhow!("Malformed QuerySmartContractStateResponse"))
+    }
+}
+
+fn encode_bytes_field(out: &mut Vec<u8>, field: u8, bytes: &[u8]) {
+    out.push(field << 3 | 2);
+    encode_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+fn encode_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn decode_varint(buf: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = buf.split_first()?;
+        *buf = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+/// First length-delimited `field`, skipping over any others
+fn decode_bytes_field(mut buf: &[u8], field: u64) -> Option<&[u8]> {
+    while !buf.is_empty() {
+        let key = decode_varint(&mut buf)?;
+
+        match key & 7 {
+            0 => {
+                decode_varint(&mut buf)?;
+            }
+            2 => {
+                let len = decode_varint(&mut buf)? as usize;
+                if buf.len() < len {
+                    return None;
+                }
+                let (value, rest) = buf.split_at(len);
+                if key >> 3 == field {
+                    return Some(value);
+                }
+                buf = rest;
+            }
+            _ => return None,
+        }
+    }
+
+    None
+}
@@ -1,9 +1,13 @@
 use std::sync::LazyLock;
 
 use anyhow::Result;
+use app_contract_api::user_registry::msg::UserId;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
-use crate::error::{AppError, AppResult};
+use crate::{
+    error::{AppError, AppResult},
+    host,
+};
 
 #[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
 pub struct ImapConfig {
@@ -13,6 +17,35 @@ pub struct ImapConfig {
     pub credentials: ImapCredentials,
 }
 
+/// Outgoing mail: confirmation requests, receipts and failure notices
+#[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: bool,
+    /// Envelope sender and From header of outgoing mail
+    pub from: String,
+    pub credentials: SmtpCredentials,
+    /// Whether to reply to every command that ran, not just the ones needing confirmation
+    pub receipts: bool,
+}
+
+#[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
+pub enum SmtpCredentials {
+    /// e.g. a local sink, or a relay that trusts the operator's network
+    None,
+
+    /// AUTH PLAIN
+    Plain { username: String, password: String },
+
+    /// AUTH XOAUTH2, sharing the gmail credentials used for reading
+    Gmail {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+    },
+}
+
 #[derive(Debug, Clone, Zeroize, ZeroizeOnDrop)]
 pub struct GmailRestApiConfig {
     pub client_id: String,
@@ -62,6 +95,46 @@ impl std::fmt::Display for ImapConfig {
     }
 }
 
+impl std::fmt::Display for SmtpConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let SmtpConfig {
+            host, port, tls, ..
+        } = self;
+        write!(f, "SMTP {}:{} (TLS: {})", host, port, tls)
+    }
+}
+
+/// Anything we can open a (possibly TLS) socket to
+pub trait ConnectionTarget: std::fmt::Display {
+    fn host(&self) -> &str;
+    fn port(&self) -> u16;
+    fn tls(&self) -> bool;
+}
+
+impl ConnectionTarget for ImapConfig {
+    fn host(&self) -> &str {
+        &self.host
+    }
+    fn port(&self) -> u16 {
+        self.port
+    }
+    fn tls(&self) -> bool {
+        self.tls
+    }
+}
+
+impl ConnectionTarget for SmtpConfig {
+    fn host(&self) -> &str {
+        &self.host
+    }
+    fn port(&self) -> u16 {
+        self.port
+    }
+    fn tls(&self) -> bool {
+        self.tls
+    }
+}
+
 impl ImapConfig {
     pub fn new() -> AppResult<Self> {
         let credential_kind = get_env_var("WAVS_ENV_MAIL_CREDENTIAL_KIND")?.to_lowercase();
@@ -114,6 +187,80 @@ impl ImapConfig {
     }
 }
 
+impl SmtpConfig {
+    /// Sending mail is optional, so this is `None` when no SMTP host is configured
+    ///
+    /// Only the [notifying operator](is_notifying_operator) uses it.
+    pub fn new() -> AppResult<Option<Self>> {
+        let host = match get_env_var("WAVS_ENV_SMTP_HOST") {
+            Ok(host) => host,
+            Err(AppError::MissingEnv { .. }) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let port = get_env_var("WAVS_ENV_SMTP_PORT")?;
+        let tls = get_env_var("WAVS_ENV_SMTP_TLS")?;
+        let from = get_env_var("WAVS_ENV_SMTP_FROM")?;
+        let receipts = get_env_var_bool("WAVS_ENV_SMTP_RECEIPTS").unwrap_or_default();
+
+        let credential_kind = match get_env_var("WAVS_ENV_SMTP_CREDENTIAL_KIND") {
+            Ok(kind) => kind.to_lowercase(),
+            Err(AppError::MissingEnv { .. }) => "none".to_string(),
+            Err(e) => return Err(e),
+        };
+
+        let credentials = match credential_kind.as_str() {
+            "none" => SmtpCredentials::None,
+            "plain" => {
+                let username = get_env_var("WAVS_ENV_SMTP_USERNAME")?;
+                let password = get_env_var("WAVS_ENV_SMTP_PASSWORD")?;
+                SmtpCredentials::Plain { username, password }
+            }
+            "gmail" => {
+                let client_id = get_env_var("WAVS_ENV_GMAIL_CLIENT_ID")?;
+                let client_secret = get_env_var("WAVS_ENV_GMAIL_CLIENT_SECRET")?;
+                let refresh_token = get_env_var("WAVS_ENV_GMAIL_TOKEN")?;
+                SmtpCredentials::Gmail {
+                    client_id,
+                    client_secret,
+                    refresh_token,
+                }
+            }
+            _ => {
+                return Err(AppError::InvalidEnv {
+                    key: "WAVS_ENV_SMTP_CREDENTIAL_KIND",
+                    reason: "Not a valid credential kind (expected 'none', 'plain', or 'gmail')",
+                })
+            }
+        };
+
+        let port: u16 = port.parse().map_err(|_| AppError::InvalidEnv {
+            key: "WAVS_ENV_SMTP_PORT",
+            reason: "Not a valid u16",
+        })?;
+
+        let tls = match tls.to_lowercase().as_str() {
+            "true" => true,
+            "false" => false,
+            _ => {
+                return Err(AppError::InvalidEnv {
+                    key: "WAVS_ENV_SMTP_TLS",
+                    reason: "Not a valid boolean",
+                })
+            }
+        };
+
+        Ok(Some(Self {
+            host,
+            port,
+            tls,
+            from,
+            credentials,
+            receipts,
+        }))
+    }
+}
+
 impl GmailRestApiConfig {
     pub fn new() -> AppResult<Self> {
         let client_id = get_env_var("WAVS_ENV_GMAIL_CLIENT_ID")?;
@@ -127,6 +274,73 @@ impl GmailRestApiConfig {
     }
 }
 
+/// Whether this operator is the one that mails senders back
+///
+/// Every operator reads the same mail, so the service names one of them in its NOTIFYING_OPERATOR
+/// config var, matched against each operator's own WAVS_ENV_OPERATOR_NAME. Without it, nobody does.
+pub fn is_notifying_operator() -> AppResult<bool> {
+    let Some(notifying_operator) = host::config_var("NOTIFYING_OPERATOR") else {
+        return Ok(false);
+    };
+
+    match get_env_var("WAVS_ENV_OPERATOR_NAME") {
+        Ok(name) => Ok(name == notifying_operator),
+        Err(AppError::MissingEnv { .. }) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Secret salt for deriving user ids, hex encoded
+///
+/// Falls back to the public `UserId::SALT`, which anyone can brute-force known addresses against.
+/// All operators in a set must share it, and it can't change without re-keying registrations.
+pub fn user_id_salt() -> AppResult<Vec<u8>> {
+    match get_env_var("WAVS_ENV_USER_ID_SALT") {
+        Ok(salt) => const_hex::decode(salt).map_err(|_| AppError::InvalidEnv {
+            key: "WAVS_ENV_USER_ID_SALT",
+            reason: "must be hex",
+        }),
+        Err(AppError::MissingEnv { .. }) => Ok(UserId::SALT.to_vec()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Secret (hex) the nonces of confirmation requests are derived from
+///
+/// Only the nonce's hash goes on-chain, so nobody without it can confirm a held command.
+/// All operators in a set must share it. Without it, commands that need confirmation are
+/// rejected by the service handler, since there's nothing safe to hold them under.
+pub fn confirm_nonce_secret() -> AppResult<Option<Vec<u8>>> {
+    match get_env_var("WAVS_ENV_CONFIRM_NONCE_SECRET") {
+        Ok(secret) => match const_hex::decode(secret) {
+            Ok(secret) if !secret.is_empty() => Ok(Some(secret)),
+            _ => Err(AppError::InvalidEnv {
+                key: "WAVS_ENV_CONFIRM_NONCE_SECRET",
+                reason: "must be non-empty hex",
+            }),
+        },
+        Err(AppError::MissingEnv { .. }) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// How many emails one cron trigger reads, each is still submitted in its own envelope
+///
+/// Defaults to one.
+pub fn max_emails_per_trigger() -> AppResult<usize> {
+    match get_env_var("WAVS_ENV_MAX_EMAILS_PER_TRIGGER") {
+        Ok(max) => match max.parse::<usize>() {
+            Ok(max) if max > 0 => Ok(max),
+            _ => Err(AppError::InvalidEnv {
+                key: "WAVS_ENV_MAX_EMAILS_PER_TRIGGER",
+                reason: "Not a positive integer",
+            }),
+        },
+        Err(AppError::MissingEnv { .. }) => Ok(1),
+        Err(e) => Err(e),
+    }
+}
+
 pub fn get_env_var(key: &str) -> AppResult<String> {
     let value = std::env::var(key).unwrap_or_default();
 
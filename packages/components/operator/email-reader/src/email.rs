@@ -1,6 +1,15 @@
+pub mod age;
+pub mod auth_results;
 pub mod imap;
-mod parser;
+pub mod notify;
+pub mod parser;
+pub mod pinned_keys;
+pub mod policy;
+pub mod proof;
+pub mod resolver;
 pub mod rest_api;
+pub mod signature;
+pub mod smtp;
 pub mod verify;
 
 use futures::StreamExt;
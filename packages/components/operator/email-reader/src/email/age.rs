@@ -0,0 +1,124 @@
+use app_contract_api::service_handler::msg::MAX_CLOCK_SKEW_SECONDS;
+
+use crate::{
+    email::{parser::EmailMessage, signature::DkimSignature},
+    error::{AppError, AppResult},
+    host,
+};
+
+/// Unix seconds by the WASI wall clock
+pub fn now() -> AppResult<u64> {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .map_err(|e| AppError::EmailAge(format!("Clock is before the unix epoch: {}", e)))
+}
+
+/// Rejects emails that are too old, or dated in the future, and gives when the email was signed
+///
+/// That's the `t=` of the signature the email verified through, or the Date header if it
+/// has none. The max age is the service handler's, set as MAX_EMAIL_AGE_SECONDS when the
+/// service is deployed; without it only a signature from the future is ruled out, and the
+/// Date header isn't looked at, so a malformed one doesn't cost an otherwise valid email.
+pub fn check_email_age(
+    email: &EmailMessage,
+    signature: &DkimSignature,
+    now: u64,
+) -> AppResult<Option<u64>> {
+    let max_age_seconds = match host::config_var("MAX_EMAIL_AGE_SECONDS") {
+        Some(max_age) => Some(max_age.parse::<u64>().map_err(|_| {
+            AppError::EmailAge(format!("Invalid MAX_EMAIL_AGE_SECONDS config: {}", max_age))
+        })?),
+        None => None,
+    };
+
+    check_age(email.date(), signature.signed_at, max_age_seconds, now)
+}
+
+fn check_age(
+    date: Option<&str>,
+    signed_at: Option<u64>,
+    max_age_seconds: Option<u64>,
+    now: u64,
+) -> AppResult<Option<u64>> {
+    let date = match (date, max_age_seconds) {
+        (Some(date), Some(_)) => Some(
+            mailparse::dateparse(date)
+                .ok()
+                .and_then(|date| u64::try_from(date).ok())
+                .ok_or_else(|| AppError::EmailAge(format!("Unparseable Date header: {}", date)))?,
+        ),
+        _ => None,
+    };
+
+    for (what, timestamp) in [("signed", signed_at), ("dated", date)] {
+        let Some(timestamp) = timestamp else {
+            continue;
+        };
+
+        if timestamp > now + MAX_CLOCK_SKEW_SECONDS {
+            return Err(AppError::EmailAge(format!(
+                "Email is {} in the future, at {}",
+                what, timestamp
+            )));
+        }
+
+        if let Some(max_age_seconds) = max_age_seconds {
+            if now.saturating_sub(timestamp) > max_age_seconds {
+                return Err(AppError::EmailAge(format!(
+                    "Email {} at {} is older than {} seconds",
+                    what, timestamp, max_age_seconds
+                )));
+            }
+        }
+    }
+
+    let signed_at = signed_at.or(date);
+
+    if max_age_seconds.is_some() && signed_at.is_none() {
+        return Err(AppError::EmailAge(
+            "Email has neither a signature timestamp nor a Date header".to_string(),
+        ));
+    }
+
+    Ok(signed_at)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+    // 1_700_000_000 - 60
+    const DATE: &str = "Tue, 14 Nov 2023 22:12:20 +0000";
+
+    #[test]
+    fn test_date_ignored_without_max_age() {
+        assert_eq!(
+            check_age(Some("not a date"), None, None, NOW).unwrap(),
+            None
+        );
+        assert_eq!(
+            check_age(Some("not a date"), Some(NOW - 10), None, NOW).unwrap(),
+            Some(NOW - 10)
+        );
+        assert_eq!(check_age(Some(DATE), None, None, NOW).unwrap(), None);
+    }
+
+    #[test]
+    fn test_date_checked_with_max_age() {
+        assert!(check_age(Some("not a date"), Some(NOW - 10), Some(3600), NOW).is_err());
+        assert!(check_age(None, None, Some(3600), NOW).is_err());
+        assert_eq!(
+            check_age(Some(DATE), None, Some(3600), NOW).unwrap(),
+            Some(NOW - 60)
+        );
+        assert!(check_age(Some(DATE), None, Some(30), NOW).is_err());
+    }
+
+    #[test]
+    fn test_signed_in_the_future() {
+        let future = NOW + MAX_CLOCK_SKEW_SECONDS + 1;
+        assert!(check_age(None, Some(future), None, NOW).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use app_contract_api::dkim_registry::msg::DkimKey;
+
+use crate::{
+    config::get_env_var,
+    email::{parser::EmailMessage, signature::DkimSignature},
+    error::{AppError, AppResult},
+};
+
+/// The receiving provider's own verdict, as a second opinion on our DKIM result
+///
+/// Only the `Authentication-Results` header stamped by WAVS_ENV_TRUSTED_AUTHSERV_ID
+/// (e.g. `mx.google.com`) is read. Receivers prepend theirs, so it's the topmost one with that id;
+/// any further down were added before the mail reached the receiver and can say anything.
+pub struct ReceiverVerdict {
+    authserv_id: String,
+}
+
+impl ReceiverVerdict {
+    /// `None` unless a trusted authserv-id is configured
+    pub fn new() -> AppResult<Option<Self>> {
+        match get_env_var("WAVS_ENV_TRUSTED_AUTHSERV_ID") {
+            Ok(authserv_id) => Ok(Some(Self {
+                authserv_id: authserv_id.to_ascii_lowercase(),
+            })),
+            Err(AppError::MissingEnv { .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The receiver has to have passed DKIM for one of the signatures we accepted
+    pub fn check(&self, email: &EmailMessage, signatures: &[DkimSignature]) -> AppResult<()> {
+        let results = email
+            .headers
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case("Authentication-Results"))
+            .map(|(_, value)| AuthenticationResults::parse(value))
+            .find(|results| results.authserv_id == self.authserv_id)
+            .ok_or_else(|| {
+                AppError::ReceiverVerdict(format!(
+                    "No Authentication-Results from {}",
+                    self.authserv_id
+                ))
+            })?;
+
+        let passed = results
+            .dkim
+            .iter()
+            .filter(|result| result.result == "pass")
+            .any(|result| {
+                signatures
+                    .iter()
+                    .any(|signature| DkimKey::normalize(&signature.domain) == result.domain)
+            });
+
+        if !passed {
+            return Err(AppError::ReceiverVerdict(format!(
+                "{} did not pass DKIM for any accepted signature (got {:?})",
+                self.authserv_id, results.dkim
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// The parts of an RFC 8601 `Authentication-Results` header we look at
+struct AuthenticationResults {
+    authserv_id: String,
+    dkim: Vec<DkimResult>,
+}
+
+#[derive(Debug)]
+struct DkimResult {
+    result: String,
+    /// `header.d`, or the domain of `header.i` when that's all there is
+    domain: String,
+}
+
+impl AuthenticationResults {
+    fn parse(value: &str) -> Self {
+        let value = strip_comments(value);
+        let mut statements = value.split(';');
+
+        // the authserv-id may be followed by a version
+        let authserv_id = statements
+            .next()
+            .and_then(|id| id.split_whitespace().next())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        let dkim = statements
+            .filter_map(|statement| {
+                let mut words = statement.split_whitespace();
+
+                let result = words
+                    .next()?
+                    .to_ascii_lowercase()
+                    .strip_prefix("dkim=")?
+                    .to_string();
+
+                let mut domain = None;
+                let mut identity = None;
+
+                for property in words {
+                    match property.split_once('=') {
+                        Some((name, value)) if name.eq_ignore_ascii_case("header.d") => {
+                            domain = Some(value)
+                        }
+                        Some((name, value)) if name.eq_ignore_ascii_case("header.i") => {
+                            identity = value.rsplit_once('@').map(|(_, domain)| domain)
+                        }
+                        _ => {}
+                    }
+                }
+
+                Some(DkimResult {
+                    result,
+                    domain: DkimKey::normalize(domain.or(identity)?.trim_matches('"')),
+                })
+            })
+            .collect();
+
+        Self { authserv_id, dkim }
+    }
+}
+
+/// Blanks out `(comments)`, which may nest and may hold `;` or `=`
+fn strip_comments(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut depth = 0usize;
+    let mut escaped = false;
+
+    for c in value.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if depth > 0 => escaped = true,
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            _ if depth == 0 => {
+                out.push(c);
+                continue;
+            }
+            _ => {}
+        }
+
+        out.push(' ');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verdict() -> ReceiverVerdict {
+        ReceiverVerdict {
+            authserv_id: "mx.google.com".to_string(),
+        }
+    }
+
+    /// An email with these Authentication-Results headers, topmost first
+    fn email(results: &[&str]) -> EmailMessage {
+        let mut raw = String::new();
+        for value in results {
+            raw.push_str(&format!("Authentication-Results: {value}\r\n"));
+        }
+        raw.push_str("From: alice@example.com\r\nSubject: withdraw 10\r\n\r\nhi\r\n");
+
+        EmailMessage::parse_rest_api(raw.as_bytes()).unwrap()
+    }
+
+    fn signature(domain: &str) -> DkimSignature {
+        DkimSignature::parse(&format!(
+            "v=1; a=rsa-sha256; d={domain}; s=sel; h=from:subject; bh=AAAA; b=BBBB"
+        ))
+        .unwrap()
+    }
+
+    fn dkim(value: &str) -> Vec<(String, String)> {
+        AuthenticationResults::parse(value)
+            .dkim
+            .into_iter()
+            .map(|result| (result.result, result.domain))
+            .collect()
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        assert_eq!(strip_comments("a (b) c"), "a     c");
+        assert_eq!(
+            strip_comments("a (b (c) d) e"),
+            format!("a{}e", " ".repeat(11))
+        );
+        assert_eq!(
+            strip_comments(r"x (a \) b) y"),
+            format!("x{}y", " ".repeat(10))
+        );
+        // a stray closing paren isn't a comment
+        assert_eq!(strip_comments("a) b"), "a) b");
+    }
+
+    #[test]
+    fn test_parse_with_nested_comments() {
+        let results = AuthenticationResults::parse(
+            "MX.Google.com 1 (a (nested; dkim=fail) comment); \
+             dkim=pass (good (very; good) sig) header.d=Example.com header.s=sel; \
+             spf=pass smtp.mailfrom=example.com",
+        );
+
+        assert_eq!(results.authserv_id, "mx.google.com");
+        assert_eq!(results.dkim.len(), 1);
+        assert_eq!(results.dkim[0].result, "pass");
+        assert_eq!(results.dkim[0].domain, "example.com");
+
+        // a property inside a comment isn't one
+        assert_eq!(
+            dkim("mx.google.com; dkim=pass (nested (header.d=evil.com)) header.d=example.com"),
+            [("pass".to_string(), "example.com".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_header_i_fallback() {
+        assert_eq!(
+            dkim("mx.google.com; dkim=pass header.i=@example.com"),
+            [("pass".to_string(), "example.com".to_string())]
+        );
+        assert_eq!(
+            dkim(r#"mx.google.com; dkim=pass header.i="alice@Example.com""#),
+            [("pass".to_string(), "example.com".to_string())]
+        );
+        // header.d wins when both are there
+        assert_eq!(
+            dkim("mx.google.com; dkim=pass header.i=@example.org header.d=example.com"),
+            [("pass".to_string(), "example.com".to_string())]
+        );
+        // neither, so nothing to match a signature against
+        assert!(dkim("mx.google.com; dkim=pass header.s=sel").is_empty());
+    }
+
+    #[test]
+    fn test_check() {
+        let signatures = [signature("example.com")];
+
+        assert!(verdict()
+            .check(
+                &email(&["mx.google.com; dkim=pass header.d=example.com"]),
+                &signatures
+            )
+            .is_ok());
+        // passed, but not for a signature we accepted
+        assert!(verdict()
+            .check(
+                &email(&["mx.google.com; dkim=pass header.d=example.org"]),
+                &signatures
+            )
+            .is_err());
+        assert!(verdict()
+            .check(
+                &email(&["mx.google.com; dkim=fail header.d=example.com"]),
+                &signatures
+            )
+            .is_err());
+        // only an untrusted receiver's verdict
+        assert!(verdict()
+            .check(
+                &email(&["mx.example.org; dkim=pass header.d=example.com"]),
+                &signatures
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_forged_lower_header_is_ignored() {
+        let signatures = [signature("example.com")];
+
+        // the sender added a passing verdict under the trusted id before sending
+        let email = email(&[
+            "mx.google.com; dkim=fail header.d=example.com",
+            "mx.google.com; dkim=pass header.d=example.com",
+        ]);
+
+        assert!(verdict().check(&email, &signatures).is_err());
+    }
+}
@@ -5,7 +5,7 @@ use thiserror::Error;
 use wstd::io::AsyncPollable;
 
 use crate::{
-    config::ImapConfig,
+    config::ConnectionTarget,
     wasi::{
         clocks::monotonic_clock,
         io::streams::StreamError,
@@ -34,23 +34,23 @@ pub struct ImapConnection {
 }
 
 impl ImapConnection {
-    pub async fn new(config: &ImapConfig) -> Result<Self> {
-        println!("Connecting to imap server on {config}");
+    pub async fn new(config: &impl ConnectionTarget) -> Result<Self> {
+        println!("Connecting to {config}");
 
         // We need to use underlying primitives to split the TcpStream because
         // TcpStream.split() returns borrows to the halves and tls needs owned halves
 
-        let addr = Address::new(&config).await?;
+        let addr = Address::new(config).await?;
         let sock = ConnectedSocket::new(&addr).await?;
 
-        if config.tls {
+        if config.tls() {
             let TlsConnection {
                 pollable,
                 recv,
                 send,
                 connection,
                 stream,
-            } = TlsConnection::new(&config.host, sock).await?;
+            } = TlsConnection::new(config.host(), sock).await?;
 
             Ok(Self {
                 _pollable: pollable,
@@ -80,7 +80,7 @@ impl ImapConnection {
     }
 
     // for STARTTLS
-    pub async fn upgrade_tls(self, config: &ImapConfig) -> Result<Self> {
+    pub async fn upgrade_tls(self, config: &impl ConnectionTarget) -> Result<Self> {
         if self._tls_connection.is_some() {
             // already tls
             return Ok(self);
@@ -99,7 +99,7 @@ impl ImapConnection {
             send,
             connection,
             stream,
-        } = TlsConnection::new(&config.host, sock).await?;
+        } = TlsConnection::new(config.host(), sock).await?;
 
         Ok(Self {
             _pollable: pollable,
@@ -206,10 +206,10 @@ struct Address {
 }
 
 impl Address {
-    pub async fn new(config: &ImapConfig) -> Result<Self> {
+    pub async fn new(config: &impl ConnectionTarget) -> Result<Self> {
         let network = instance_network();
 
-        let stream = resolve_addresses(&network, &config.host)?;
+        let stream = resolve_addresses(&network, config.host())?;
 
         let pollable = AsyncPollable::new(stream.subscribe().into());
 
@@ -218,7 +218,7 @@ impl Address {
                 Ok(Some(addr)) => break addr,
                 Ok(None) => {
                     return Err(ImapConnectionError::NoAddress {
-                        host: config.host.to_string(),
+                        host: config.host().to_string(),
                     });
                 }
                 Err(ErrorCode::WouldBlock) => {
@@ -235,14 +235,14 @@ impl Address {
                 IpAddressFamily::Ipv4,
                 IpSocketAddress::Ipv4(Ipv4SocketAddress {
                     address: addr,
-                    port: config.port,
+                    port: config.port(),
                 }),
             ),
             IpAddress::Ipv6(addr) => (
                 IpAddressFamily::Ipv6,
                 IpSocketAddress::Ipv6(Ipv6SocketAddress {
                     address: addr,
-                    port: config.port,
+                    port: config.port(),
                     // TODO: handle these properly?
                     flow_info: 0,
                     scope_id: 0,
@@ -0,0 +1,496 @@
+use anyhow::{anyhow, ensure, Context};
+use app_contract_api::{
+    proxy::ProxyExecuteMsg,
+    service_handler::msg::{
+        ConfirmationConfig, CustomExecuteMsg, CustomQueryMsg, HandledEventResponse, PendingAction,
+        QueryMsg, LINK_EXPIRES_AFTER_SECONDS,
+    },
+    user_registry::msg::normalize_email,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    chain::ContractQuerier,
+    config::{is_notifying_operator, SmtpConfig},
+    email::{
+        parser::EmailMessage,
+        smtp::{send_email, OutgoingEmail},
+    },
+    host,
+    wasi::{clocks::wall_clock, keyvalue::store},
+};
+
+/// How long a submitted command can go without running before its sender hears it failed,
+/// comfortably past the aggregator's retries
+const GIVE_UP_AFTER_SECONDS: u64 = 30 * 60;
+
+const OUTCOMES_BUCKET: &str = "outcomes";
+
+/// Every command still waiting on an outcome is kept under this one key, only the
+/// notifying operator ever writes it
+const PENDING_OUTCOMES_KEY: &str = "pending";
+
+/// Where replies to an incoming email go
+///
+/// Only build this for emails that passed verification, otherwise we'd be
+/// mailing whoever the From header was forged to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplyTarget {
+    /// Bare address of the original sender
+    pub to: String,
+    pub message_id: Option<String>,
+    pub subject: String,
+}
+
+impl ReplyTarget {
+    pub fn new(email: &EmailMessage) -> Option<Self> {
+        let to = mailparse::addrparse(&email.original_sender)
+            .ok()?
+            .extract_single_info()?
+            .addr;
+
+        Some(Self {
+            to,
+            message_id: email.message_id().map(|s| s.to_string()),
+            subject: email.subject.clone().unwrap_or_default(),
+        })
+    }
+
+    fn reply_subject(&self) -> String {
+        if self.subject.to_lowercase().starts_with("re:") {
+            self.subject.clone()
+        } else {
+            format!("Re: {}", self.subject)
+        }
+    }
+
+    /// A reply in the original email's thread
+    fn reply(&self, body: String) -> Notice {
+        Notice {
+            to: self.clone(),
+            subject: self.reply_subject(),
+            body,
+        }
+    }
+
+    async fn send(&self, config: &SmtpConfig, subject: &str, body: &str) -> anyhow::Result<()> {
+        send_email(
+            config,
+            OutgoingEmail {
+                to: &self.to,
+                subject,
+                body,
+                in_reply_to: self.message_id.as_deref(),
+            },
+        )
+        .await?;
+
+        println!("Sent \"{subject}\" to {}", self.to);
+
+        Ok(())
+    }
+}
+
+/// An email about a command, sent once the command ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Notice {
+    to: ReplyTarget,
+    subject: String,
+    body: String,
+}
+
+impl Notice {
+    async fn send(&self, config: &SmtpConfig) -> anyhow::Result<()> {
+        self.to.send(config, &self.subject, &self.body).await
+    }
+}
+
+/// A submitted command, kept until the service handler ran it or we give up on it
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingOutcome {
+    /// The envelope's event id, what the service handler records once it ran
+    event_id: Vec<u8>,
+    /// Unix seconds
+    submitted_at: u64,
+    sender: ReplyTarget,
+    notice: Option<Notice>,
+}
+
+impl PendingOutcome {
+    fn load_all() -> anyhow::Result<Vec<Self>> {
+        let Some(bytes) = outcomes_bucket()?
+            .get(PENDING_OUTCOMES_KEY)
+            .map_err(|e| anyhow!("Failed to read pending outcomes: {e:?}"))?
+        else {
+            return Ok(Vec::new());
+        };
+
+        serde_json::from_slice(&bytes).context("Corrupt pending outcomes")
+    }
+
+    fn save_all(pending: &[Self]) -> anyhow::Result<()> {
+        let bytes = serde_json::to_vec(pending)?;
+
+        outcomes_bucket()?
+            .set(PENDING_OUTCOMES_KEY, &bytes)
+            .map_err(|e| anyhow!("Failed to write pending outcomes: {e:?}"))
+    }
+
+    fn push(self) -> anyhow::Result<()> {
+        let mut pending = Self::load_all()?;
+        pending.push(self);
+        Self::save_all(&pending)
+    }
+}
+
+fn outcomes_bucket() -> anyhow::Result<store::Bucket> {
+    store::open(OUTCOMES_BUCKET)
+        .map_err(|e| anyhow!("Failed to open {OUTCOMES_BUCKET} bucket: {e:?}"))
+}
+
+/// Set when the service is deployed, so we can ask whether a command ran
+fn service_handler() -> anyhow::Result<Option<ContractQuerier>> {
+    ContractQuerier::from_config("SERVICE_HANDLER_CONTRACT_ADDRESS")
+}
+
+/// Tell the sender what happened to a command we're about to submit
+///
+/// `seed` is the envelope's event id, and `nonce` the one its hash was submitted with, if any.
+/// Confirmation requests and receipts wait until the command ran, and the sender gets a
+/// failure notice if it never does, see [report_outcomes].
+///
+/// Sending is best-effort: the command is submitted either way, so errors are only logged.
+/// Only the notifying operator sends anything, so each sender gets one copy.
+pub async fn notify_submitted(
+    target: &ReplyTarget,
+    msg: &CustomExecuteMsg,
+    seed: &[u8],
+    nonce: Option<&str>,
+) {
+    let result = match is_notifying_operator() {
+        Ok(true) => try_notify_submitted(target, msg, seed, nonce).await,
+        Ok(false) => return,
+        Err(e) => Err(e.into()),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to notify {}: {e:?}", target.to);
+    }
+}
+
+/// Tell the sender their (verified) email could not be turned into a command
+///
+/// Sent whenever SMTP is configured, unlike receipts.
+pub async fn notify_failed(target: &ReplyTarget, error: &anyhow::Error) {
+    let result = match is_notifying_operator() {
+        Ok(true) => try_notify_failed(target, error).await,
+        Ok(false) => return,
+        Err(e) => Err(e.into()),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to notify {}: {e:?}", target.to);
+    }
+}
+
+/// Tell senders how the commands submitted on earlier triggers went
+///
+/// The chain has had a trigger's worth of time to run them by now. Ones that ran get their
+/// confirmation request or receipt, ones that still haven't after [GIVE_UP_AFTER_SECONDS]
+/// get a failure notice.
+pub async fn report_outcomes() {
+    let result = match is_notifying_operator() {
+        Ok(true) => try_report_outcomes().await,
+        Ok(false) => return,
+        Err(e) => Err(e.into()),
+    };
+
+    if let Err(e) = result {
+        eprintln!("Failed to report outcomes: {e:?}");
+    }
+}
+
+async fn try_notify_failed(target: &ReplyTarget, error: &anyhow::Error) -> anyhow::Result<()> {
+    let Some(config) = SmtpConfig::new()? else {
+        return Ok(());
+    };
+
+    let body = format!(
+        "We could not process your request.\r\n\
+         \r\n\
+         Reason: {error}\r\n"
+    );
+
+    target.send(&config, &target.reply_subject(), &body).await
+}
+
+async fn try_notify_submitted(
+    target: &ReplyTarget,
+    msg: &CustomExecuteMsg,
+    seed: &[u8],
+    nonce: Option<&str>,
+) -> anyhow::Result<()> {
+    let config = SmtpConfig::new()?;
+    let service_handler = service_handler()?;
+
+    let notice = submitted_notice(
+        target,
+        msg,
+        nonce,
+        config.as_ref(),
+        service_handler.is_some(),
+    )?;
+
+    // nothing to send with, not even a failure notice
+    let Some(config) = config else {
+        return Ok(());
+    };
+
+    if service_handler.is_none() {
+        // no asking whether it ran, so the sender hears about it right away
+        return match notice {
+            Some(notice) => notice.send(&config).await,
+            None => Ok(()),
+        };
+    }
+
+    PendingOutcome {
+        event_id: seed.to_vec(),
+        submitted_at: wall_clock::now().seconds,
+        sender: target.clone(),
+        notice,
+    }
+    .push()
+}
+
+async fn try_report_outcomes() -> anyhow::Result<()> {
+    let pending = PendingOutcome::load_all()?;
+
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let (Some(service_handler), Some(config)) = (service_handler()?, SmtpConfig::new()?) else {
+        // redeployed without them, these can't be reported anymore
+        return PendingOutcome::save_all(&[]);
+    };
+
+    let now = wall_clock::now().seconds;
+    let mut waiting = Vec::new();
+
+    for outcome in pending {
+        let query = QueryMsg::Custom(CustomQueryMsg::HandledEvent {
+            event_id: outcome.event_id.clone().into(),
+        });
+
+        let handled: HandledEventResponse = match service_handler.query(&query).await {
+            Ok(handled) => handled,
+            Err(e) => {
+                eprintln!(
+                    "Failed to look up event {}: {e:?}",
+                    const_hex::encode(&outcome.event_id)
+                );
+                waiting.push(outcome);
+                continue;
+            }
+        };
+
+        let result = match handled.height {
+            Some(_) => match &outcome.notice {
+                Some(notice) => notice.send(&config).await,
+                None => Ok(()),
+            },
+            None if now.saturating_sub(outcome.submitted_at) > GIVE_UP_AFTER_SECONDS => {
+                let body = format!(
+                    "We could not carry out your request \"{}\".\r\n\
+                     \r\n\
+                     It was submitted, but the chain did not accept it.\r\n",
+                    outcome.sender.subject
+                );
+
+                outcome
+                    .sender
+                    .send(&config, &outcome.sender.reply_subject(), &body)
+                    .await
+            }
+            None => {
+                waiting.push(outcome);
+                continue;
+            }
+        };
+
+        // best-effort like the rest, it isn't tried again
+        if let Err(e) = result {
+            eprintln!("Failed to notify {}: {e:?}", outcome.sender.to);
+        }
+    }
+
+    PendingOutcome::save_all(&waiting)
+}
+
+/// The email to send about `msg` once it ran, if any
+///
+/// `tracked` is whether we'll know it ran, otherwise a receipt can only say it was submitted.
+fn submitted_notice(
+    target: &ReplyTarget,
+    msg: &CustomExecuteMsg,
+    nonce: Option<&str>,
+    config: Option<&SmtpConfig>,
+    tracked: bool,
+) -> anyhow::Result<Option<Notice>> {
+    let confirmation: Option<ConfirmationConfig> = match host::config_var("CONFIRMATION") {
+        Some(config) => Some(serde_json::from_str(&config)?),
+        None => None,
+    };
+
+    if let Some(address) = link_target(msg, &target.subject)? {
+        ensure!(
+            config.is_some(),
+            "link requires confirmation, but SMTP is not configured"
+        );
+        let nonce = nonce.ok_or_else(|| {
+            anyhow!("link requires confirmation, but WAVS_ENV_CONFIRM_NONCE_SECRET is not set")
+        })?;
+
+        let subject = PendingAction::confirm_subject(nonce);
+        let body = format!(
+            "{} asked to link this address to their account.\r\n\
+             \r\n\
+             To approve it, reply with the subject \"{subject}\" within {} seconds.\r\n\
+             If you did not expect this request, ignore this email.\r\n",
+            target.to,
+            confirmation
+                .map(|confirmation| confirmation.expires_after_seconds)
+                .unwrap_or(LINK_EXPIRES_AFTER_SECONDS),
+        );
+
+        // the linked address confirms, not the sender, so this starts a thread of its own
+        return Ok(Some(Notice {
+            to: ReplyTarget {
+                to: address,
+                message_id: None,
+                subject: String::new(),
+            },
+            subject,
+            body,
+        }));
+    }
+
+    let receipt = |request: String| {
+        let outcome = if tracked {
+            "went through"
+        } else {
+            "was submitted"
+        };
+        target.reply(format!("Your request \"{request}\" {outcome}.\r\n"))
+    };
+
+    let receipts = config.is_some_and(|config| config.receipts);
+
+    Ok(match proxy_action(msg) {
+        Some(action) => match confirmation {
+            Some(confirmation) if confirmation.requires_confirmation(&action) => {
+                ensure!(
+                    config.is_some(),
+                    "action requires confirmation, but SMTP is not configured"
+                );
+                let nonce = nonce.ok_or_else(|| {
+                    anyhow!(
+                        "action requires confirmation, but WAVS_ENV_CONFIRM_NONCE_SECRET is not set"
+                    )
+                })?;
+
+                let subject = PendingAction::confirm_subject(nonce);
+                let body = format!(
+                    "We received a request to run \"{}\" from this address.\r\n\
+                     \r\n\
+                     To approve it, reply with the subject \"{subject}\" within {} seconds.\r\n\
+                     If you did not send this request, ignore this email.\r\n",
+                    action.to_email_subject(),
+                    confirmation.expires_after_seconds,
+                );
+
+                Some(Notice {
+                    to: target.clone(),
+                    subject,
+                    body,
+                })
+            }
+            _ if receipts => Some(receipt(action.to_email_subject())),
+            _ => None,
+        },
+        None if receipts => Some(receipt(target.subject.clone())),
+        None => None,
+    })
+}
+
+/// The address a "link <email>" asks to add, which is who has to confirm it
+fn link_target(msg: &CustomExecuteMsg, subject: &str) -> anyhow::Result<Option<String>> {
+    match msg {
+        CustomExecuteMsg::Link { .. } => {
+            let token = subject
+                .split_whitespace()
+                .nth(1)
+                .ok_or_else(|| anyhow!("link has no address to confirm"))?;
+            link_address(token).map(Some)
+        }
+        // the contract parses these itself, the same way
+        CustomExecuteMsg::ProvenEmail { email, .. } => {
+            link_target(&CustomExecuteMsg::from_email(email.clone()), subject)
+        }
+        CustomExecuteMsg::Timestamped { msg, .. } | CustomExecuteMsg::Confirmable { msg, .. } => {
+            link_target(msg, subject)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// The one mailbox `token` names, safe to put in an SMTP envelope
+///
+/// It's the sender's to choose, so anything that could end the RCPT line or the
+/// angle brackets around it is turned away here rather than sent on.
+fn link_address(token: &str) -> anyhow::Result<String> {
+    ensure!(
+        !token.contains(['\r', '\n', '<', '>']),
+        "link address {token:?} has characters an email address can't"
+    );
+
+    normalize_email(token)
+        .ok_or_else(|| anyhow!("link address {token:?} is not a single email address"))
+}
+
+/// The proxy command `msg` carries, if any
+fn proxy_action(msg: &CustomExecuteMsg) -> Option<ProxyExecuteMsg> {
+    match msg {
+        CustomExecuteMsg::Email(email) => Some(email.proxy_execute_msg()),
+        CustomExecuteMsg::PrivateEmail(email) => Some(email.action.clone()),
+        // the contract parses these itself, the same way
+        CustomExecuteMsg::ProvenEmail { email, .. } => {
+            proxy_action(&CustomExecuteMsg::from_email(email.clone()))
+        }
+        CustomExecuteMsg::Timestamped { msg, .. } | CustomExecuteMsg::Confirmable { msg, .. } => {
+            proxy_action(msg)
+        }
+        CustomExecuteMsg::Confirm { .. }
+        | CustomExecuteMsg::Link { .. }
+        | CustomExecuteMsg::Unlink { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_address() {
+        assert_eq!(link_address("bob@Example.com").unwrap(), "bob@example.com");
+
+        link_address("bob").unwrap_err();
+        link_address("bob@").unwrap_err();
+        link_address("bob@example.com,eve@example.com").unwrap_err();
+        link_address("friends:bob@example.com;").unwrap_err();
+        link_address("bob@example.com>\r\nRCPT TO:<eve@example.com").unwrap_err();
+        link_address("bob@example.com\nDATA").unwrap_err();
+        link_address("<bob@example.com>").unwrap_err();
+    }
+}
@@ -1,13 +1,17 @@
 use anyhow::{Context, Result};
+use app_contract_api::user_registry::msg::normalize_email;
 use imap::{types::Fetch, Session};
 use mailparse::*;
 use sha2::{Digest, Sha256};
 use std::borrow::Cow;
 
-use crate::error::AppResult;
+use crate::{email::signature::DkimSignature, error::AppResult};
+
+/// Bumped whenever what goes into `event_id_salt` changes, so old and new ids never collide
+const EVENT_ID_DOMAIN: &[u8] = b"hydro-email/event-id/v3";
 
 pub struct EmailMessage {
-    // 1) "Original sender" best-effort (From / Resent-From / Sender / envelope)
+    // 1) Author address, the normalized addr-spec of the single From mailbox
     pub original_sender: String,
     // 2) All DKIM-Signature header values (there can be multiple)
     pub dkim_signatures: Vec<String>,
@@ -44,11 +48,11 @@ impl std::fmt::Debug for EmailMessage {
 impl EmailMessage {
     pub fn parse_imap(f: &Fetch) -> anyhow::Result<Self> {
         let body_bytes = f.body().context("Missing email body")?;
-        parse_any(body_bytes, Some(f))
+        parse_any(body_bytes)
     }
 
     pub fn parse_rest_api(body_bytes: &[u8]) -> anyhow::Result<Self> {
-        parse_any(body_bytes, None)
+        parse_any(body_bytes)
     }
 
     /// Parse the raw email bytes and return a ParsedMail
@@ -72,12 +76,14 @@ impl EmailMessage {
             .map(|(_, v)| v.as_str())
     }
 
-    pub fn event_id_salt(&self) -> anyhow::Result<Vec<u8>> {
-        let unique_id = self
-            .message_id()
-            .or_else(|| self.date())
-            .ok_or_else(|| anyhow::anyhow!("Email must have either Message-ID or Date header"))?;
-
+    /// Same for every operator, whichever mailbox backend they fetched the email from
+    ///
+    /// Only what `verified`, the signature the email verified through, vouches for goes in:
+    /// an unsigned header or an extra signature added on the way would otherwise give a
+    /// replayed email a new id. Its `b=` and `bh=` make the id unique per signed message, even
+    /// with the same sender and subject in the same second. Every field is length-prefixed so
+    /// none can bleed into the next.
+    pub fn event_id_salt(&self, verified: &DkimSignature) -> anyhow::Result<Vec<u8>> {
         let subject = self
             .subject
             .as_ref()
@@ -85,50 +91,24 @@ impl EmailMessage {
 
         let mut hasher = Sha256::new();
 
-        hasher.update(unique_id);
-        hasher.update(&self.original_sender);
-        hasher.update(subject);
+        hasher.update(EVENT_ID_DOMAIN);
+        update_field(&mut hasher, self.original_sender.as_bytes());
+        update_field(&mut hasher, subject.as_bytes());
+        update_field(&mut hasher, &verified.signature);
+        update_field(&mut hasher, &verified.body_hash);
 
         Ok(hasher.finalize().to_vec())
     }
 }
 
-fn parse_any(body_bytes: &[u8], f: Option<&Fetch>) -> anyhow::Result<EmailMessage> {
+fn parse_any(body_bytes: &[u8]) -> anyhow::Result<EmailMessage> {
     let msg = parse_mail(body_bytes)
         .map_err(|e| anyhow::anyhow!("Failed to parse email message: {:?}", e))?;
 
-    let subject = msg
-        .headers
-        .iter()
-        .find(|h| h.get_key().eq_ignore_ascii_case("Subject"))
-        .map(|h| h.get_value());
+    // the command, so like From it must be the one instance a signature covers
+    let subject = only_header(&msg.headers, "Subject")?.map(|h| h.get_value());
 
-    // best effort original sender extraction
-    let original_sender = msg
-        .headers
-        .iter()
-        .find(|h| {
-            h.get_key().eq_ignore_ascii_case("Resent-From")
-                || h.get_key().eq_ignore_ascii_case("From")
-                || h.get_key().eq_ignore_ascii_case("Sender")
-        })
-        .map(|h| h.get_value())
-        .or_else(|| {
-            f.and_then(|f| {
-                f.envelope()
-                    .and_then(|env| {
-                        env.from.as_ref().map(|addrs| {
-                            addrs
-                                .first()
-                                .map(|a| a.mailbox.map(|s| String::from_utf8(s.to_vec()).ok()))
-                        })
-                    })
-                    .flatten()
-                    .flatten()
-                    .flatten()
-            })
-        })
-        .ok_or_else(|| anyhow::anyhow!("Failed to extract original sender"))?;
+    let original_sender = author_address(&msg.headers)?;
 
     // there can be multiple signatures
     let dkim_signatures = msg
@@ -150,10 +130,206 @@ fn parse_any(body_bytes: &[u8], f: Option<&Fetch>) -> anyhow::Result<EmailMessag
 
     Ok(EmailMessage {
         subject: subject.map(|s| s.to_string()),
-        original_sender: original_sender.to_string(),
+        original_sender,
         dkim_signatures: dkim_signatures.into_iter().map(|s| s.to_string()).collect(),
         body_text: body_text.map(|s| s.to_string()),
         raw_bytes: body_bytes.to_vec(),
         headers,
     })
 }
+
+/// The one address the email is from, as RFC 5322 parses the From header, normalized the way
+/// user ids are
+///
+/// Only From counts, since that's what DKIM signs. Anything ambiguous is rejected rather than
+/// guessed at: a second From header could be the one a signature covers while we read the
+/// other, and a group or several mailboxes don't name a single author.
+fn author_address(headers: &[MailHeader]) -> anyhow::Result<String> {
+    let header =
+        only_header(headers, "From")?.ok_or_else(|| anyhow::anyhow!("Email has no From header"))?;
+
+    let addrs = addrparse_header(header).context("Failed to parse From header")?;
+
+    let addr = match addrs.as_slice() {
+        [MailAddr::Single(single)] => &single.addr,
+        [MailAddr::Group(group)] => {
+            anyhow::bail!("From is the group \"{}\", not a mailbox", group.group_name)
+        }
+        [] => anyhow::bail!("From header has no address"),
+        _ => anyhow::bail!("From header has more than one address"),
+    };
+
+    normalize_email(addr).ok_or_else(|| anyhow::anyhow!("From address {} has no domain", addr))
+}
+
+/// The `name` header, if there's one, an error if there are several
+///
+/// DKIM verifiers check the bottom-most instance of a signed header, so with more than one
+/// an unsigned copy above it could be what we read.
+fn only_header<'a>(
+    headers: &'a [MailHeader<'a>],
+    name: &str,
+) -> anyhow::Result<Option<&'a MailHeader<'a>>> {
+    let mut matching = headers
+        .iter()
+        .filter(|h| h.get_key_ref().eq_ignore_ascii_case(name));
+
+    let header = matching.next();
+
+    if matching.next().is_some() {
+        anyhow::bail!("Email has multiple {} headers", name);
+    }
+
+    Ok(header)
+}
+
+fn update_field(hasher: &mut Sha256, field: &[u8]) {
+    hasher.update((field.len() as u64).to_be_bytes());
+    hasher.update(field);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn author(from_headers: &str) -> anyhow::Result<String> {
+        let raw = format!("{from_headers}Subject: hi\r\n\r\nbody\r\n");
+        let (headers, _) = parse_headers(raw.as_bytes()).unwrap();
+
+        author_address(&headers)
+    }
+
+    fn author_error(from_headers: &str) -> String {
+        author(from_headers).unwrap_err().to_string()
+    }
+
+    fn signed_email(signatures: &[&str], message_id: &str) -> EmailMessage {
+        let mut raw = String::new();
+        for tags in signatures {
+            raw.push_str(&format!(
+                "DKIM-Signature: v=1; a=rsa-sha256; d=example.com; s=sel; h=from:subject; {tags}\r\n"
+            ));
+        }
+        raw.push_str("From: Alice <alice@example.com>\r\nSubject: withdraw 10\r\n");
+        raw.push_str(&format!("Message-ID: {message_id}\r\n\r\nhi\r\n"));
+
+        EmailMessage::parse_rest_api(raw.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_bare_address() {
+        assert_eq!(
+            author("From: alice@example.com\r\n").unwrap(),
+            "alice@example.com"
+        );
+    }
+
+    #[test]
+    fn test_display_name_is_dropped() {
+        assert_eq!(
+            author("From: Alice <alice@example.com>\r\n").unwrap(),
+            "alice@example.com"
+        );
+        assert_eq!(
+            author("From: \"Smith, Alice\" <alice@example.com>\r\n").unwrap(),
+            "alice@example.com"
+        );
+        // only the angle-addr counts, not an address in the display name
+        assert_eq!(
+            author("From: \"bob@example.org\" <alice@example.com>\r\n").unwrap(),
+            "alice@example.com"
+        );
+        assert_eq!(
+            author("From: =?UTF-8?Q?Al=C3=AFce?= <alice@example.com>\r\n").unwrap(),
+            "alice@example.com"
+        );
+    }
+
+    #[test]
+    fn test_domain_is_lowercased() {
+        // the same address an admin registers, so it hashes to the same user id
+        assert_eq!(
+            author("From: Alice <Alice@Example.COM>\r\n").unwrap(),
+            "Alice@example.com"
+        );
+    }
+
+    #[test]
+    fn test_multiple_from_headers() {
+        assert!(
+            author_error("From: alice@example.com\r\nFrom: bob@example.org\r\n")
+                .contains("multiple From headers")
+        );
+        // header names are case-insensitive
+        assert!(
+            author_error("From: alice@example.com\r\nfrom: bob@example.org\r\n")
+                .contains("multiple From headers")
+        );
+    }
+
+    #[test]
+    fn test_multiple_subject_headers() {
+        // an unsigned Subject above the signed one
+        let raw =
+            "Subject: withdraw 1000\r\nFrom: alice@example.com\r\nSubject: deposit\r\n\r\nhi\r\n";
+
+        let error = EmailMessage::parse_rest_api(raw.as_bytes()).unwrap_err();
+
+        assert!(error.to_string().contains("multiple Subject headers"));
+    }
+
+    #[test]
+    fn test_groups() {
+        assert!(
+            author_error("From: Team: alice@example.com, bob@example.org;\r\n").contains("group")
+        );
+        assert!(author_error("From: undisclosed-recipients:;\r\n").contains("group"));
+    }
+
+    #[test]
+    fn test_multiple_mailboxes() {
+        assert!(author_error("From: alice@example.com, bob@example.org\r\n")
+            .contains("more than one address"));
+        assert!(
+            author_error("From: Alice <alice@example.com>, Bob <bob@example.org>\r\n")
+                .contains("more than one address")
+        );
+    }
+
+    #[test]
+    fn test_missing_or_incomplete() {
+        assert!(author_error("To: alice@example.com\r\n").contains("no From header"));
+        assert!(author("From: alice\r\n").is_err());
+        assert!(author("From: alice@\r\n").is_err());
+        assert!(author("From: @example.com\r\n").is_err());
+    }
+
+    /// Event ids are derived from this, so a change here must bump `EVENT_ID_DOMAIN`
+    #[test]
+    fn test_event_id_salt_is_pinned() {
+        let email = signed_email(&["bh=BwgJ; b=CQgH"], "<abc@example.com>");
+        let verified = DkimSignature::parse(&email.dkim_signatures[0]).unwrap();
+
+        assert_eq!(
+            const_hex::encode(email.event_id_salt(&verified).unwrap()),
+            "771dcb5c58b4d4610822bc2b93955b6b049bd41c97041cbdb0ee706c47980e71"
+        );
+    }
+
+    #[test]
+    fn test_event_id_salt_ignores_what_the_signature_does_not_cover() {
+        let email = signed_email(&["bh=BwgJ; b=CQgH"], "<abc@example.com>");
+        let verified = DkimSignature::parse(&email.dkim_signatures[0]).unwrap();
+        let salt = email.event_id_salt(&verified).unwrap();
+
+        // the same email re-sent with a junk signature added and its Message-ID changed
+        let resent = signed_email(&["bh=BAUG; b=AQID", "bh=BwgJ; b=CQgH"], "<new@example.org>");
+
+        assert_eq!(resent.event_id_salt(&verified).unwrap(), salt);
+
+        // a different signed message is a different event
+        let other = DkimSignature::parse(&resent.dkim_signatures[0]).unwrap();
+
+        assert_ne!(resent.event_id_salt(&other).unwrap(), salt);
+    }
+}
@@ -0,0 +1,108 @@
+use std::{collections::HashMap, sync::Arc};
+
+use app_contract_api::dkim_registry::msg::{DkimKey, KeysResponse, QueryMsg};
+use cfdkim::{dns::Lookup, DKIMError};
+use futures::future::BoxFuture;
+
+use crate::{
+    chain::ContractQuerier,
+    email::{policy::SenderPolicy, signature::DkimSignature},
+    error::{AppError, AppResult},
+};
+
+/// The DKIM registry contract, set when the service is deployed with one
+///
+/// Operators only trust keys pinned there, so they all verify against the same set
+/// no matter what DNS serves them, and mail signed before a rotation still verifies.
+pub struct DkimRegistry(ContractQuerier);
+
+impl DkimRegistry {
+    pub fn new() -> AppResult<Option<Self>> {
+        Ok(ContractQuerier::from_config("DKIM_REGISTRY_ADDRESS")
+            .map_err(AppError::DkimRegistry)?
+            .map(Self))
+    }
+
+    pub async fn keys(&self, domain: &str, selector: &str) -> AppResult<Vec<DkimKey>> {
+        let resp: KeysResponse = self
+            .0
+            .query(&QueryMsg::Keys {
+                domain: domain.to_string(),
+                selector: selector.to_string(),
+            })
+            .await
+            .map_err(AppError::DkimRegistry)?;
+
+        Ok(resp.keys)
+    }
+}
+
+/// The usable pinned keys for every signature we accept on an email, keyed by DNS name
+pub struct PinnedKeys(HashMap<String, Vec<DkimKey>>);
+
+impl PinnedKeys {
+    pub async fn fetch(
+        registry: &DkimRegistry,
+        signatures: &[DkimSignature],
+        policy: &SenderPolicy,
+    ) -> AppResult<Self> {
+        let mut keys = HashMap::new();
+
+        for DkimSignature {
+            domain, selector, ..
+        } in signatures
+        {
+            let name = DkimKey::dns_name(selector, domain);
+
+            if keys.contains_key(&name) || !policy.allows_signature(domain, selector) {
+                continue;
+            }
+
+            let pinned = registry
+                .keys(domain, selector)
+                .await?
+                .into_iter()
+                .filter(|key| !key.revoked && policy.allows_key(&key.txt_record()))
+                .collect::<Vec<_>>();
+
+            keys.insert(name, pinned);
+        }
+
+        Ok(Self(keys))
+    }
+
+    /// How many verification attempts it takes to try every key
+    pub fn max_attempts(&self) -> usize {
+        self.0.values().map(Vec::len).max().unwrap_or_default()
+    }
+}
+
+/// Serves the `attempt`th most recently seen pinned key for each name
+///
+/// The verifier only looks at the first record it gets back, so a selector with
+/// several pinned keys takes one attempt per key.
+pub struct PinnedKeyLookup {
+    keys: Arc<PinnedKeys>,
+    attempt: usize,
+}
+
+impl PinnedKeyLookup {
+    pub fn new(keys: Arc<PinnedKeys>, attempt: usize) -> Self {
+        Self { keys, attempt }
+    }
+}
+
+impl Lookup for PinnedKeyLookup {
+    fn lookup_txt<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, DKIMError>> {
+        Box::pin(async move {
+            let name = DkimKey::normalize(name);
+
+            self.keys
+                .0
+                .get(&name)
+                .and_then(|keys| keys.get(self.attempt))
+                .map(|key| vec![key.txt_record()])
+                .ok_or(DKIMError::NoKeyForSignature)
+        })
+    }
+}
@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+
+use app_contract_api::dkim_registry::msg::{DkimKey, DkimKeyType};
+use serde::Deserialize;
+
+use crate::{
+    error::{AppError, AppResult},
+    host,
+};
+
+/// Which senders are accepted, and what their signatures must look like
+///
+/// Set from a JSON file when the service is deployed (the DOMAIN_POLICY config var),
+/// so every operator applies the same one. Without it, any validly signed domain is accepted.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DomainPolicy {
+    /// If not empty, only these sender domains are accepted
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Never accepted, even if allowed
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// For sender domains without their own entry in `domains`
+    #[serde(default)]
+    pub default: DomainRequirements,
+    #[serde(default)]
+    pub domains: HashMap<String, DomainRequirements>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DomainRequirements {
+    /// The signing domain (`d=`) must be the From domain itself, not a parent domain
+    #[serde(default)]
+    pub require_aligned_signature: bool,
+    /// Reject signatures under RSA keys with a shorter modulus
+    pub min_rsa_bits: Option<usize>,
+    /// Only signatures under these selectors count
+    pub selectors: Option<Vec<String>>,
+}
+
+impl DomainPolicy {
+    pub fn new() -> AppResult<Self> {
+        match host::config_var("DOMAIN_POLICY") {
+            Some(policy) => serde_json::from_str(&policy).map_err(|e| {
+                AppError::Policy(anyhow::anyhow!("Invalid DOMAIN_POLICY config: {}", e))
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Rejects blocked or unlisted senders outright, otherwise gives what their signatures must meet
+    pub fn for_sender(&self, from_domain: &str) -> AppResult<SenderPolicy> {
+        let from_domain = DkimKey::normalize(from_domain);
+
+        if self
+            .deny
+            .iter()
+            .any(|pattern| matches(pattern, &from_domain))
+        {
+            return Err(AppError::Policy(anyhow::anyhow!(
+                "Sender domain {} is blocked",
+                from_domain
+            )));
+        }
+
+        if !self.allow.is_empty()
+            && !self
+                .allow
+                .iter()
+                .any(|pattern| matches(pattern, &from_domain))
+        {
+            return Err(AppError::Policy(anyhow::anyhow!(
+                "Sender domain {} is not allowed",
+                from_domain
+            )));
+        }
+
+        // the most specific entry wins
+        let requirements = self
+            .domains
+            .iter()
+            .filter(|(pattern, _)| matches(pattern, &from_domain))
+            .max_by_key(|(pattern, _)| (!pattern.starts_with("*."), pattern.len()))
+            .map(|(_, requirements)| requirements.clone())
+            .unwrap_or_else(|| self.default.clone());
+
+        Ok(SenderPolicy {
+            from_domain,
+            requirements,
+        })
+    }
+}
+
+/// `DomainRequirements` for one sender, applied while resolving keys, so a signature
+/// that doesn't meet them has no key to verify under
+pub struct SenderPolicy {
+    from_domain: String,
+    requirements: DomainRequirements,
+}
+
+impl SenderPolicy {
+    pub fn allows_signature(&self, domain: &str, selector: &str) -> bool {
+        if self.requirements.require_aligned_signature
+            && DkimKey::normalize(domain) != self.from_domain
+        {
+            return false;
+        }
+
+        match &self.requirements.selectors {
+            Some(selectors) => selectors
+                .iter()
+                .any(|allowed| DkimKey::normalize(allowed) == DkimKey::normalize(selector)),
+            None => true,
+        }
+    }
+
+    pub fn allows_key(&self, txt_record: &str) -> bool {
+        let Some(min_rsa_bits) = self.requirements.min_rsa_bits else {
+            return true;
+        };
+
+        match DkimKey::parse_txt_record(txt_record) {
+            Some((DkimKeyType::Rsa, public_key)) => {
+                rsa_modulus_bits(&public_key).is_some_and(|bits| bits >= min_rsa_bits)
+            }
+            // size requirements are about RSA
+            Some((DkimKeyType::Ed25519, _)) => true,
+            None => false,
+        }
+    }
+}
+
+/// `example.com` matches only itself, `*.example.com` matches its subdomains too
+fn matches(pattern: &str, domain: &str) -> bool {
+    let pattern = DkimKey::normalize(pattern);
+
+    match pattern.strip_prefix("*.") {
+        Some(parent) => domain == parent || domain.ends_with(&format!(".{parent}")),
+        None => domain == pattern,
+    }
+}
+
+/// Modulus size of an RSA key, published either as SubjectPublicKeyInfo or as a bare RSAPublicKey
+fn rsa_modulus_bits(der: &[u8]) -> Option<usize> {
+    const SEQUENCE: u8 = 0x30;
+    const INTEGER: u8 = 0x02;
+    const BIT_STRING: u8 = 0x03;
+
+    let (tag, outer, _) = der_element(der)?;
+    if tag != SEQUENCE {
+        return None;
+    }
+
+    let (tag, first, rest) = der_element(outer)?;
+
+    let modulus = match tag {
+        // RSAPublicKey { modulus, publicExponent }
+        INTEGER => first,
+        // SubjectPublicKeyInfo { algorithm, BIT STRING { RSAPublicKey } }
+        SEQUENCE => {
+            let (tag, bits, _) = der_element(rest)?;
+            if tag != BIT_STRING {
+                return None;
+            }
+            // skip the unused-bits byte
+            let (tag, rsa_public_key, _) = der_element(bits.get(1..)?)?;
+            if tag != SEQUENCE {
+                return None;
+            }
+            let (tag, modulus, _) = der_element(rsa_public_key)?;
+            if tag != INTEGER {
+                return None;
+            }
+            modulus
+        }
+        _ => return None,
+    };
+
+    // leading zero bytes keep the integer positive, they don't count
+    let modulus = match modulus.iter().position(|byte| *byte != 0) {
+        Some(start) => &modulus[start..],
+        None => return None,
+    };
+
+    Some(modulus.len() * 8 - modulus[0].leading_zeros() as usize)
+}
+
+/// (tag, contents, rest) of the DER element at the start of `buf`
+fn der_element(buf: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, buf) = buf.split_first()?;
+    let (&len, mut buf) = buf.split_first()?;
+
+    let len = if len & 0x80 == 0 {
+        len as usize
+    } else {
+        let len_bytes = (len & 0x7f) as usize;
+        if len_bytes == 0 || len_bytes > 4 || buf.len() < len_bytes {
+            return None;
+        }
+        let (len, rest) = buf.split_at(len_bytes);
+        buf = rest;
+        len.iter()
+            .fold(0usize, |acc, byte| acc << 8 | *byte as usize)
+    };
+
+    if buf.len() < len {
+        return None;
+    }
+
+    let (contents, rest) = buf.split_at(len);
+    Some((tag, contents, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+
+    use super::*;
+
+    fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        match contents.len() {
+            len @ 0..=0x7f => out.push(len as u8),
+            len @ 0x80..=0xff => out.extend([0x81, len as u8]),
+            len => out.extend([0x82, (len >> 8) as u8, len as u8]),
+        }
+        out.extend_from_slice(contents);
+        out
+    }
+
+    /// An RSAPublicKey with a `bits` long modulus, as DER integers are written
+    fn rsa_public_key(bits: usize) -> Vec<u8> {
+        let mut modulus = vec![0xff; bits.div_ceil(8)];
+        modulus[0] = 1 << ((bits - 1) % 8);
+        if modulus[0] & 0x80 != 0 {
+            modulus.insert(0, 0);
+        }
+
+        der(0x30, &[der(0x02, &modulus), der(0x02, &[1, 0, 1])].concat())
+    }
+
+    /// The same key wrapped in a SubjectPublicKeyInfo, as DKIM records usually carry it
+    fn subject_public_key_info(bits: usize) -> Vec<u8> {
+        // rsaEncryption, NULL
+        let algorithm = der(
+            0x30,
+            &[
+                der(
+                    0x06,
+                    &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01],
+                ),
+                der(0x05, &[]),
+            ]
+            .concat(),
+        );
+        let key = der(0x03, &[&[0][..], &rsa_public_key(bits)].concat());
+
+        der(0x30, &[algorithm, key].concat())
+    }
+
+    fn parse_policy(json: &str) -> DomainPolicy {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn sender(min_rsa_bits: usize) -> SenderPolicy {
+        SenderPolicy {
+            from_domain: "example.com".to_string(),
+            requirements: DomainRequirements {
+                min_rsa_bits: Some(min_rsa_bits),
+                ..Default::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_modulus_bits() {
+        assert_eq!(rsa_modulus_bits(&rsa_public_key(1024)), Some(1024));
+        assert_eq!(rsa_modulus_bits(&rsa_public_key(1023)), Some(1023));
+        assert_eq!(rsa_modulus_bits(&subject_public_key_info(2048)), Some(2048));
+        assert_eq!(rsa_modulus_bits(&subject_public_key_info(512)), Some(512));
+    }
+
+    #[test]
+    fn test_truncated_keys() {
+        for key in [rsa_public_key(1024), subject_public_key_info(2048)] {
+            for len in 0..key.len() {
+                assert_eq!(rsa_modulus_bits(&key[..len]), None, "cut at {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_oversized_lengths() {
+        // claims more than there is
+        assert_eq!(rsa_modulus_bits(&[0x30, 0x05, 0x02, 0x01, 0x01]), None);
+        assert_eq!(rsa_modulus_bits(&[0x30, 0x82, 0xff, 0xff, 0x02]), None);
+        // more length bytes than a usize fold is trusted with
+        assert_eq!(
+            rsa_modulus_bits(&[0x30, 0x85, 0, 0, 0, 0, 0x03, 0x02, 0x01, 0x01]),
+            None
+        );
+        // indefinite length isn't DER
+        assert_eq!(
+            rsa_modulus_bits(&[0x30, 0x80, 0x02, 0x01, 0x01, 0, 0]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_malformed_keys() {
+        // not a SEQUENCE
+        assert_eq!(rsa_modulus_bits(&der(0x02, &[0x80])), None);
+        // an all-zero modulus
+        assert_eq!(rsa_modulus_bits(&der(0x30, &der(0x02, &[0, 0, 0]))), None);
+        // a SubjectPublicKeyInfo with an OCTET STRING for the BIT STRING, which follows
+        // the outer header and the 15 byte algorithm
+        let mut key = subject_public_key_info(1024);
+        assert_eq!(key[18], 0x03);
+        key[18] = 0x04;
+        assert_eq!(rsa_modulus_bits(&key), None);
+    }
+
+    #[test]
+    fn test_allows_key() {
+        let encode = |key: &[u8]| base64::engine::general_purpose::STANDARD.encode(key);
+        let record = |key: &[u8]| format!("v=DKIM1; k=rsa; p={}", encode(key));
+
+        assert!(sender(1024).allows_key(&record(&subject_public_key_info(2048))));
+        assert!(sender(2048).allows_key(&record(&subject_public_key_info(2048))));
+        assert!(!sender(2048).allows_key(&record(&subject_public_key_info(1024))));
+        assert!(!sender(1024).allows_key(&record(b"not a key")));
+        // revoked
+        assert!(!sender(1024).allows_key("v=DKIM1; k=rsa; p="));
+        // size requirements are about RSA
+        assert!(sender(4096).allows_key(&format!("v=DKIM1; k=ed25519; p={}", encode(&[1; 32]))));
+    }
+
+    #[test]
+    fn test_wildcards() {
+        assert!(matches("example.com", "example.com"));
+        assert!(!matches("example.com", "mail.example.com"));
+        assert!(matches("*.example.com", "example.com"));
+        assert!(matches("*.example.com", "mail.example.com"));
+        assert!(matches("*.example.com", "a.b.example.com"));
+        assert!(!matches("*.example.com", "badexample.com"));
+        assert!(matches("*.Example.COM.", "mail.example.com"));
+    }
+
+    #[test]
+    fn test_allow_and_deny() {
+        let policy = parse_policy(
+            r#"{
+                "allow": ["*.example.com", "example.org"],
+                "deny": ["spam.example.com"]
+            }"#,
+        );
+
+        assert!(policy.for_sender("example.com").is_ok());
+        assert!(policy.for_sender("Mail.Example.com").is_ok());
+        assert!(policy.for_sender("example.org").is_ok());
+        assert!(policy.for_sender("mail.example.org").is_err());
+        assert!(policy.for_sender("example.net").is_err());
+        // denied even though allowed
+        assert!(policy.for_sender("spam.example.com").is_err());
+        assert!(policy.for_sender("a.spam.example.com").is_ok());
+
+        // without an allow list everything not denied is accepted
+        let policy = parse_policy(r#"{ "deny": ["*.example.com"] }"#);
+
+        assert!(policy.for_sender("example.net").is_ok());
+        assert!(policy.for_sender("a.spam.example.com").is_err());
+    }
+
+    #[test]
+    fn test_most_specific_requirements_win() {
+        let policy = parse_policy(
+            r#"{
+                "default": { "min_rsa_bits": 1024 },
+                "domains": {
+                    "*.example.com": { "min_rsa_bits": 2048 },
+                    "*.mail.example.com": { "min_rsa_bits": 3072 },
+                    "mail.example.com": { "require_aligned_signature": true }
+                }
+            }"#,
+        );
+
+        let bits = |domain: &str| policy.for_sender(domain).unwrap().requirements.min_rsa_bits;
+
+        assert_eq!(bits("example.net"), Some(1024));
+        assert_eq!(bits("example.com"), Some(2048));
+        assert_eq!(bits("a.mail.example.com"), Some(3072));
+        // an exact entry beats any wildcard
+        assert_eq!(bits("mail.example.com"), None);
+        assert!(
+            policy
+                .for_sender("mail.example.com")
+                .unwrap()
+                .requirements
+                .require_aligned_signature
+        );
+    }
+}
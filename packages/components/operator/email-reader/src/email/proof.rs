@@ -0,0 +1,46 @@
+use app_contract_api::dkim_proof::DkimProof;
+use wstd::http::{Body, Request};
+
+use crate::{
+    config::get_env_var,
+    email::parser::EmailMessage,
+    error::{AppError, AppResult},
+};
+
+/// Has the external prover generate a DKIM proof for the raw email
+///
+/// Proving is too heavy to run inside the component, so this hands the email to a
+/// prover service (WAVS_ENV_DKIM_PROVER_URL) that answers with a `DkimProof` as JSON.
+pub async fn prove_email(email: &EmailMessage) -> AppResult<DkimProof> {
+    let url = get_env_var("WAVS_ENV_DKIM_PROVER_URL")?;
+
+    let http_client = wstd::http::Client::new();
+
+    let request = Request::post(url.as_str())
+        .header("Content-Type", "message/rfc822")
+        .header("Accept", "application/json")
+        .body(Body::from(email.raw_bytes.clone()))
+        .map_err(|e| AppError::Proof(anyhow::anyhow!("Failed to build prover request: {}", e)))?;
+
+    let response = http_client
+        .send(request)
+        .await
+        .map_err(|e| AppError::Proof(anyhow::anyhow!("Prover request failed: {}", e)))?;
+
+    if !response.status().is_success() {
+        return Err(AppError::Proof(anyhow::anyhow!(
+            "Prover returned error status: {}",
+            response.status()
+        )));
+    }
+
+    let mut body = response.into_body();
+    let body = body.contents().await.map_err(AppError::Proof)?;
+
+    serde_json::from_slice(&body).map_err(|e| {
+        AppError::Proof(anyhow::anyhow!(
+            "Failed to parse prover response JSON: {}",
+            e
+        ))
+    })
+}
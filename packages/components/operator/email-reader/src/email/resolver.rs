@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+
+use app_contract_api::dkim_registry::msg::DkimKey;
+use cfdkim::{dns::Lookup, DKIMError};
+use futures::future::BoxFuture;
+use serde::Deserialize;
+use wstd::http::{Body, Request};
+
+use crate::{
+    config::get_env_var,
+    email::{policy::SenderPolicy, signature::DkimSignature},
+    error::{AppError, AppResult},
+};
+
+const CLOUDFLARE_DOH_URL: &str = "https://cloudflare-dns.com/dns-query";
+const GOOGLE_DOH_URL: &str = "https://dns.google/resolve";
+
+/// Where DKIM keys are looked up when they aren't pinned, set with WAVS_ENV_DKIM_RESOLVER
+///
+/// A comma separated list of `cloudflare`, `google`, `static` or a DoH JSON API url.
+/// With more than one, an answer is only used once WAVS_ENV_DKIM_RESOLVER_QUORUM of them
+/// (by default a majority) agree on it, so a single spoofed answer can't flip a verification.
+pub struct Resolver {
+    backends: Vec<Backend>,
+    quorum: usize,
+}
+
+enum Backend {
+    DnsOverHttps {
+        url: String,
+    },
+    /// Fixed records from WAVS_ENV_DKIM_STATIC_RECORDS, for offline tests
+    Static(HashMap<String, Vec<String>>),
+}
+
+impl Resolver {
+    pub fn new() -> AppResult<Self> {
+        let spec = match get_env_var("WAVS_ENV_DKIM_RESOLVER") {
+            Ok(spec) => spec,
+            Err(AppError::MissingEnv { .. }) => "cloudflare".to_string(),
+            Err(e) => return Err(e),
+        };
+
+        let backends = spec
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(Backend::new)
+            .collect::<AppResult<Vec<_>>>()?;
+
+        if backends.is_empty() {
+            return Err(AppError::InvalidEnv {
+                key: "WAVS_ENV_DKIM_RESOLVER",
+                reason: "No resolvers listed",
+            });
+        }
+
+        let quorum = match get_env_var("WAVS_ENV_DKIM_RESOLVER_QUORUM") {
+            Ok(quorum) => quorum.parse().map_err(|_| AppError::InvalidEnv {
+                key: "WAVS_ENV_DKIM_RESOLVER_QUORUM",
+                reason: "Not a valid usize",
+            })?,
+            Err(AppError::MissingEnv { .. }) => backends.len() / 2 + 1,
+            Err(e) => return Err(e),
+        };
+
+        if quorum == 0 || quorum > backends.len() {
+            return Err(AppError::InvalidEnv {
+                key: "WAVS_ENV_DKIM_RESOLVER_QUORUM",
+                reason: "Must be between 1 and the number of resolvers",
+            });
+        }
+
+        Ok(Self { backends, quorum })
+    }
+
+    /// Resolves every key the signatures need up front, the verifier then reads from the result
+    ///
+    /// Signatures and keys the sender's policy rules out are left unresolved, and so are
+    /// keys the resolvers don't reach a quorum on: like a key that fails to parse, that only
+    /// costs its own signature, the email can still verify through another. It's an error
+    /// only if no key could be resolved at all.
+    pub async fn resolve(
+        &self,
+        signatures: &[DkimSignature],
+        policy: &SenderPolicy,
+    ) -> AppResult<ResolvedLookup> {
+        let mut records = HashMap::new();
+        let mut failed = None;
+
+        for DkimSignature {
+            domain, selector, ..
+        } in signatures
+        {
+            let name = DkimKey::dns_name(selector, domain);
+
+            if records.contains_key(&name) || !policy.allows_signature(domain, selector) {
+                continue;
+            }
+
+            let txt = match self.lookup_txt(&name).await {
+                Ok(txt) => txt,
+                Err(e) => {
+                    eprintln!("Skipping the signature under {}: {}", name, e);
+                    failed.get_or_insert(e);
+                    continue;
+                }
+            };
+
+            let txt = txt
+                .into_iter()
+                .filter(|record| policy.allows_key(record))
+                .collect();
+
+            records.insert(name, txt);
+        }
+
+        match failed {
+            Some(e) if records.is_empty() => Err(e),
+            _ => Ok(ResolvedLookup(records)),
+        }
+    }
+
+    async fn lookup_txt(&self, name: &str) -> AppResult<Vec<String>> {
+        let mut answers: Vec<(Vec<String>, usize)> = Vec::new();
+        let mut errors = Vec::new();
+
+        for backend in &self.backends {
+            match backend.lookup_txt(name).await {
+                Ok(mut txt) => {
+                    // record order isn't significant in DNS
+                    txt.sort();
+
+                    match answers.iter_mut().find(|(answer, _)| *answer == txt) {
+                        Some((_, votes)) => *votes += 1,
+                        None => answers.push((txt, 1)),
+                    }
+                }
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+
+        answers
+            .into_iter()
+            .find(|(_, votes)| *votes >= self.quorum)
+            .map(|(txt, _)| txt)
+            .ok_or_else(|| {
+                AppError::Resolver(anyhow::anyhow!(
+                    "No {} of {} resolvers agree on {} (errors: {:?})",
+                    self.quorum,
+                    self.backends.len(),
+                    name,
+                    errors
+                ))
+            })
+    }
+}
+
+impl Backend {
+    fn new(spec: &str) -> AppResult<Self> {
+        match spec {
+            "cloudflare" => Ok(Self::DnsOverHttps {
+                url: CLOUDFLARE_DOH_URL.to_string(),
+            }),
+            "google" => Ok(Self::DnsOverHttps {
+                url: GOOGLE_DOH_URL.to_string(),
+            }),
+            "static" => {
+                let records = get_env_var("WAVS_ENV_DKIM_STATIC_RECORDS")?;
+                let records: HashMap<String, Vec<String>> = serde_json::from_str(&records)
+                    .map_err(|_| AppError::InvalidEnv {
+                        key: "WAVS_ENV_DKIM_STATIC_RECORDS",
+                        reason: "Not a JSON map of DNS names to TXT records",
+                    })?;
+
+                Ok(Self::Static(
+                    records
+                        .into_iter()
+                        .map(|(name, txt)| (DkimKey::normalize(&name), txt))
+                        .collect(),
+                ))
+            }
+            url if url.starts_with("https://") || url.starts_with("http://") => {
+                Ok(Self::DnsOverHttps {
+                    url: url.to_string(),
+                })
+            }
+            _ => Err(AppError::InvalidEnv {
+                key: "WAVS_ENV_DKIM_RESOLVER",
+                reason: "Expected 'cloudflare', 'google', 'static' or a DoH url",
+            }),
+        }
+    }
+
+    async fn lookup_txt(&self, name: &str) -> AppResult<Vec<String>> {
+        match self {
+            Self::Static(records) => Ok(records
+                .get(&DkimKey::normalize(name))
+                .cloned()
+                .unwrap_or_default()),
+            Self::DnsOverHttps { url } => lookup_txt_doh(url, name).await,
+        }
+    }
+}
+
+/// The JSON flavor of DNS-over-HTTPS, which both Cloudflare and Google serve
+async fn lookup_txt_doh(url: &str, name: &str) -> AppResult<Vec<String>> {
+    #[derive(Deserialize)]
+    struct DnsResponse {
+        #[serde(rename = "Status")]
+        status: u16,
+        #[serde(rename = "Answer", default)]
+        answer: Vec<DnsAnswer>,
+    }
+
+    #[derive(Deserialize)]
+    struct DnsAnswer {
+        #[serde(rename = "type")]
+        record_type: u16,
+        data: String,
+    }
+
+    const NOERROR: u16 = 0;
+    const NXDOMAIN: u16 = 3;
+    const TXT: u16 = 16;
+
+    let query = serde_urlencoded::to_string([("name", name), ("type", "TXT")])
+        .map_err(|e| AppError::Resolver(e.into()))?;
+
+    let http_client = wstd::http::Client::new();
+
+    let request = Request::get(format!("{}?{}", url, query).as_str())
+        .header("Accept", "application/dns-json")
+        .body(Body::empty())
+        .map_err(|e| AppError::Resolver(anyhow::anyhow!("Failed to build DoH request: {}", e)))?;
+
+    let response = http_client
+        .send(request)
+        .await
+        .map_err(|e| AppError::Resolver(anyhow::anyhow!("DoH request to {} failed: {}", url, e)))?;
+
+    if !response.status().is_success() {
+        return Err(AppError::Resolver(anyhow::anyhow!(
+            "DoH request to {} returned error status: {}",
+            url,
+            response.status()
+        )));
+    }
+
+    let mut body = response.into_body();
+    let body = body.contents().await.map_err(AppError::Resolver)?;
+
+    let resp: DnsResponse = serde_json::from_slice(&body).map_err(|e| {
+        AppError::Resolver(anyhow::anyhow!("Failed to parse DoH response JSON: {}", e))
+    })?;
+
+    match resp.status {
+        NOERROR => {}
+        NXDOMAIN => return Ok(Vec::new()),
+        status => {
+            return Err(AppError::Resolver(anyhow::anyhow!(
+                "DoH lookup of {} at {} failed with rcode {}",
+                name,
+                url,
+                status
+            )))
+        }
+    }
+
+    Ok(resp
+        .answer
+        .into_iter()
+        .filter(|answer| answer.record_type == TXT)
+        .map(|answer| join_character_strings(&answer.data))
+        .collect())
+}
+
+/// Records already resolved by a `Resolver`
+pub struct ResolvedLookup(HashMap<String, Vec<String>>);
+
+impl Lookup for ResolvedLookup {
+    fn lookup_txt<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Vec<String>, DKIMError>> {
+        Box::pin(async move {
+            match self.0.get(&DkimKey::normalize(name)) {
+                Some(txt) if !txt.is_empty() => Ok(txt.clone()),
+                _ => Err(DKIMError::NoKeyForSignature),
+            }
+        })
+    }
+}
+
+/// Long TXT records come split into quoted character-strings, e.g. `"v=DKIM1; k=rsa; " "p=MIIB..."`
+fn join_character_strings(data: &str) -> String {
+    if data.contains('"') {
+        data.split('"').skip(1).step_by(2).collect()
+    } else {
+        data.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::policy::DomainPolicy;
+
+    const NAME: &str = "sel._domainkey.example.com";
+
+    fn answering(txt: &[&str]) -> Backend {
+        Backend::Static(HashMap::from([(
+            NAME.to_string(),
+            txt.iter().map(|record| record.to_string()).collect(),
+        )]))
+    }
+
+    fn resolver(backends: Vec<Backend>, quorum: usize) -> Resolver {
+        Resolver { backends, quorum }
+    }
+
+    #[tokio::test]
+    async fn test_majority_answer_wins() {
+        let resolver = resolver(
+            vec![
+                answering(&["p=good"]),
+                answering(&["p=spoofed"]),
+                answering(&["p=good"]),
+            ],
+            2,
+        );
+
+        assert_eq!(resolver.lookup_txt(NAME).await.unwrap(), ["p=good"]);
+    }
+
+    #[tokio::test]
+    async fn test_no_quorum_is_an_error() {
+        let resolver = resolver(
+            vec![
+                answering(&["p=good"]),
+                answering(&["p=spoofed"]),
+                answering(&[]),
+            ],
+            2,
+        );
+
+        assert!(matches!(
+            resolver.lookup_txt(NAME).await,
+            Err(AppError::Resolver(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_record_order_does_not_split_the_vote() {
+        let resolver = resolver(
+            vec![answering(&["p=a", "p=b"]), answering(&["p=b", "p=a"])],
+            2,
+        );
+
+        assert_eq!(resolver.lookup_txt(NAME).await.unwrap(), ["p=a", "p=b"]);
+    }
+
+    #[tokio::test]
+    async fn test_agreeing_on_no_record() {
+        let resolver = resolver(vec![answering(&[]), answering(&[])], 2);
+
+        assert!(resolver.lookup_txt(NAME).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_static_names_are_normalized() {
+        let resolver = resolver(vec![answering(&["p=good"])], 1);
+
+        assert_eq!(
+            resolver
+                .lookup_txt("Sel._DomainKey.Example.com.")
+                .await
+                .unwrap(),
+            ["p=good"]
+        );
+    }
+
+    fn signature(selector: &str) -> DkimSignature {
+        DkimSignature::parse(&format!(
+            "v=1; a=rsa-sha256; d=example.com; s={selector}; h=from:subject; bh=AAAA; b=BBBB"
+        ))
+        .unwrap()
+    }
+
+    fn records(records: &[(&str, &str)]) -> Backend {
+        Backend::Static(
+            records
+                .iter()
+                .map(|(name, txt)| (name.to_string(), vec![txt.to_string()]))
+                .collect(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_no_quorum_skips_only_that_signature() {
+        let resolver = resolver(
+            vec![
+                records(&[
+                    ("good._domainkey.example.com", "p=good"),
+                    ("split._domainkey.example.com", "p=good"),
+                ]),
+                records(&[
+                    ("good._domainkey.example.com", "p=good"),
+                    ("split._domainkey.example.com", "p=spoofed"),
+                ]),
+            ],
+            2,
+        );
+        let policy = DomainPolicy::default().for_sender("example.com").unwrap();
+
+        let lookup = resolver
+            .resolve(&[signature("split"), signature("good")], &policy)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            lookup
+                .lookup_txt("good._domainkey.example.com")
+                .await
+                .unwrap(),
+            ["p=good"]
+        );
+        assert!(matches!(
+            lookup.lookup_txt("split._domainkey.example.com").await,
+            Err(DKIMError::NoKeyForSignature)
+        ));
+
+        // with nothing else to verify through, the quorum failure is the error
+        assert!(matches!(
+            resolver.resolve(&[signature("split")], &policy).await,
+            Err(AppError::Resolver(_))
+        ));
+    }
+
+    #[test]
+    fn test_join_character_strings() {
+        assert_eq!(
+            join_character_strings(r#""v=DKIM1; k=rsa; " "p=MIIB" "AQAB""#),
+            "v=DKIM1; k=rsa; p=MIIBAQAB"
+        );
+        assert_eq!(
+            join_character_strings(r#""v=DKIM1; p=abc""#),
+            "v=DKIM1; p=abc"
+        );
+        // some resolvers give the record unquoted
+        assert_eq!(join_character_strings("v=DKIM1; p=abc"), "v=DKIM1; p=abc");
+        assert_eq!(join_character_strings(r#""""#), "");
+    }
+}
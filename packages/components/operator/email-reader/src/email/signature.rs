@@ -0,0 +1,423 @@
+use app_contract_api::service_handler::msg::MAX_CLOCK_SKEW_SECONDS;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::{
+    email::parser::EmailMessage,
+    error::{AppError, AppResult},
+};
+
+/// Headers a signature has to cover for us to act on the email
+///
+/// The Subject carries the command, and the From decides whose account it runs under.
+/// A signature that leaves either out would still pass with them rewritten.
+pub const REQUIRED_SIGNED_HEADERS: &[&str] = &["From", "Subject"];
+
+/// The tags of a `DKIM-Signature` header that decide what it actually vouches for
+#[derive(Debug, Clone)]
+pub struct DkimSignature {
+    pub domain: String,
+    pub selector: String,
+    /// `h=`
+    pub signed_headers: Vec<String>,
+    /// `l=`, only this many bytes of the canonicalized body are signed
+    pub body_length: Option<usize>,
+    pub body_canonicalization: Canonicalization,
+    /// `t=`, unix seconds
+    pub signed_at: Option<u64>,
+    /// `x=`, unix seconds
+    pub expires_at: Option<u64>,
+    /// `b=`, decoded
+    pub signature: Vec<u8>,
+    /// `bh=`, decoded
+    pub body_hash: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Canonicalization {
+    Simple,
+    Relaxed,
+}
+
+impl DkimSignature {
+    /// `None` if the header is missing tags the verifier needs anyway
+    pub fn parse(header: &str) -> Option<Self> {
+        let mut domain = None;
+        let mut selector = None;
+        let mut signed_headers = None;
+        let mut body_length = None;
+        let mut body_canonicalization = Canonicalization::Simple;
+        let mut signed_at = None;
+        let mut expires_at = None;
+        let mut signature = None;
+        let mut body_hash = None;
+
+        for tag in header.split(';') {
+            let Some((name, value)) = tag.split_once('=') else {
+                continue;
+            };
+
+            let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+
+            match name.trim() {
+                "d" => domain = Some(value),
+                "s" => selector = Some(value),
+                "h" => {
+                    signed_headers = Some(
+                        value
+                            .split(':')
+                            .filter(|name| !name.is_empty())
+                            .map(str::to_string)
+                            .collect(),
+                    )
+                }
+                "l" => body_length = Some(value.parse().ok()?),
+                "t" => signed_at = Some(value.parse().ok()?),
+                "x" => expires_at = Some(value.parse().ok()?),
+                "b" => signature = Some(STANDARD.decode(&value).ok()?),
+                "bh" => body_hash = Some(STANDARD.decode(&value).ok()?),
+                // c=header/body, the body half defaults to simple
+                "c" => {
+                    body_canonicalization = match value.split_once('/') {
+                        Some((_, "relaxed")) => Canonicalization::Relaxed,
+                        _ => Canonicalization::Simple,
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            domain: domain?,
+            selector: selector?,
+            signed_headers: signed_headers?,
+            body_length,
+            body_canonicalization,
+            signed_at,
+            expires_at,
+            signature: signature?,
+            body_hash: body_hash?,
+        })
+    }
+
+    /// Why this signature can't vouch for the email at `now`, if it can't
+    fn check(&self, email: &EmailMessage, now: u64) -> Result<(), String> {
+        let missing = REQUIRED_SIGNED_HEADERS
+            .iter()
+            .filter(|required| {
+                !self
+                    .signed_headers
+                    .iter()
+                    .any(|signed| signed.eq_ignore_ascii_case(required))
+            })
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            return Err(format!("does not sign {:?}", missing));
+        }
+
+        if let Some(body_length) = self.body_length {
+            let full_length =
+                canonicalized_body_length(&email.raw_bytes, self.body_canonicalization);
+
+            if body_length < full_length {
+                return Err(format!(
+                    "only signs {} of {} body bytes (l=)",
+                    body_length, full_length
+                ));
+            }
+        }
+
+        if let Some(expires_at) = self.expires_at {
+            if expires_at < now {
+                return Err(format!("expired at {} (x=)", expires_at));
+            }
+        }
+
+        if let Some(signed_at) = self.signed_at {
+            if signed_at > now + MAX_CLOCK_SKEW_SECONDS {
+                return Err(format!("is signed in the future at {} (t=)", signed_at));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The signatures on an email that cover everything we act on
+///
+/// Only these get keys looked up, so the email can only pass verification through one of them.
+pub fn strict_signatures(email: &EmailMessage, now: u64) -> AppResult<Vec<DkimSignature>> {
+    let mut strict = Vec::new();
+    let mut rejected = Vec::new();
+
+    for header in &email.dkim_signatures {
+        let Some(signature) = DkimSignature::parse(header) else {
+            rejected.push("malformed signature".to_string());
+            continue;
+        };
+
+        match signature.check(email, now) {
+            Ok(()) => strict.push(signature),
+            Err(reason) => rejected.push(format!(
+                "signature by {} (s={}) {}",
+                signature.domain, signature.selector, reason
+            )),
+        }
+    }
+
+    if strict.is_empty() {
+        return Err(AppError::DkimCoverage(if rejected.is_empty() {
+            "Email is not signed".to_string()
+        } else {
+            rejected.join(", ")
+        }));
+    }
+
+    Ok(strict)
+}
+
+/// The raw email with every `DKIM-Signature` header but `keep`'s taken out
+pub fn with_only_signature(raw: &[u8], keep: &DkimSignature) -> Vec<u8> {
+    let (headers, body) = split_message(raw);
+    let separator = &raw[headers.len()..raw.len() - body.len()];
+
+    let mut fields: Vec<&[u8]> = Vec::new();
+    let mut start = 0;
+
+    for (pos, byte) in headers.iter().enumerate() {
+        if *byte != b'\n' {
+            continue;
+        }
+
+        // a line starting with whitespace continues the field above it
+        if !matches!(headers.get(pos + 1), Some(b' ' | b'\t')) {
+            fields.push(&headers[start..=pos]);
+            start = pos + 1;
+        }
+    }
+
+    if start < headers.len() {
+        fields.push(&headers[start..]);
+    }
+
+    let mut stripped = Vec::with_capacity(raw.len());
+
+    for field in fields {
+        let text = String::from_utf8_lossy(field);
+
+        let is_other_signature = text.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("DKIM-Signature")
+                && !DkimSignature::parse(value).is_some_and(|signature| {
+                    signature.signature == keep.signature && signature.body_hash == keep.body_hash
+                })
+        });
+
+        if !is_other_signature {
+            stripped.extend_from_slice(field);
+        }
+    }
+
+    stripped.extend_from_slice(separator);
+    stripped.extend_from_slice(body);
+
+    stripped
+}
+
+/// The header section, each line with its line ending, and the body after the empty line
+fn split_message(raw: &[u8]) -> (&[u8], &[u8]) {
+    raw.windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .map(|pos| (&raw[..pos + 2], &raw[pos + 4..]))
+        .or_else(|| {
+            raw.windows(2)
+                .position(|window| window == b"\n\n")
+                .map(|pos| (&raw[..pos + 1], &raw[pos + 2..]))
+        })
+        .unwrap_or((raw, &[]))
+}
+
+/// Length of the body as RFC 6376 section 3.4 canonicalizes it, which is what `l=` counts
+fn canonicalized_body_length(raw: &[u8], canonicalization: Canonicalization) -> usize {
+    let (_, body) = split_message(raw);
+
+    let mut lines = body
+        .split(|byte| *byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .map(|line| match canonicalization {
+            Canonicalization::Simple => line.len(),
+            Canonicalization::Relaxed => relaxed_line_length(line),
+        })
+        .collect::<Vec<_>>();
+
+    // trailing empty lines are ignored
+    while lines.last() == Some(&0) {
+        lines.pop();
+    }
+
+    match (lines.is_empty(), canonicalization) {
+        // an empty body is a single CRLF in simple, and nothing in relaxed
+        (true, Canonicalization::Simple) => 2,
+        (true, Canonicalization::Relaxed) => 0,
+        // every line ends in CRLF
+        (false, _) => lines.iter().map(|len| len + 2).sum(),
+    }
+}
+
+/// Whitespace runs count as one space, trailing whitespace not at all
+fn relaxed_line_length(line: &[u8]) -> usize {
+    let is_wsp = |byte: &u8| *byte == b' ' || *byte == b'\t';
+
+    let mut len = 0;
+    let mut in_wsp = false;
+
+    for byte in line {
+        if is_wsp(byte) {
+            in_wsp = true;
+        } else {
+            if in_wsp && len > 0 {
+                len += 1;
+            }
+            in_wsp = false;
+            len += 1;
+        }
+    }
+
+    // leading whitespace is kept, reduced to one space
+    if line.first().is_some_and(is_wsp) && len > 0 {
+        len += 1;
+    }
+
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn signature_header(tags: &str) -> String {
+        format!("DKIM-Signature: v=1; a=rsa-sha256; d=example.com; s=sel; bh=AAAA; b=BBBB; {tags}")
+    }
+
+    fn message(signatures: &[&str], body: &str) -> EmailMessage {
+        let mut raw = String::new();
+        for tags in signatures {
+            raw.push_str(&signature_header(tags));
+            raw.push_str("\r\n");
+        }
+        raw.push_str("From: alice@example.com\r\nSubject: withdraw 10\r\n\r\n");
+        raw.push_str(body);
+
+        EmailMessage::parse_rest_api(raw.as_bytes()).unwrap()
+    }
+
+    fn check(tags: &str, body: &str) -> Result<(), String> {
+        let email = message(&[tags], body);
+        let signature = DkimSignature::parse(&email.dkim_signatures[0]).unwrap();
+
+        signature.check(&email, NOW)
+    }
+
+    #[test]
+    fn requires_from_and_subject_signed() {
+        assert!(check("h=from:subject:date", "hi\r\n").is_ok());
+        assert!(check("h=From:Subject", "hi\r\n").is_ok());
+        assert!(check("h=from:date", "hi\r\n")
+            .unwrap_err()
+            .contains("Subject"));
+        assert!(check("h=subject", "hi\r\n").unwrap_err().contains("From"));
+        assert!(check("h=", "hi\r\n").is_err());
+    }
+
+    #[test]
+    fn requires_body_length_to_cover_body() {
+        // "hello\r\nworld\r\n" is 14 bytes either way
+        assert!(check("h=from:subject; l=14", "hello\r\nworld\r\n").is_ok());
+        assert!(check("h=from:subject; l=100", "hello\r\nworld\r\n").is_ok());
+        assert!(check("h=from:subject; l=7", "hello\r\nworld\r\n")
+            .unwrap_err()
+            .contains("l="));
+
+        // relaxed squeezes "a  b \r\n" down to "a b\r\n"
+        assert!(check("h=from:subject; c=relaxed/relaxed; l=5", "a  b \r\n").is_ok());
+        assert!(check("h=from:subject; c=relaxed/simple; l=5", "a  b \r\n").is_err());
+    }
+
+    #[test]
+    fn canonicalizes_body_length() {
+        let length = |body: &str, canonicalization| {
+            canonicalized_body_length(
+                format!("Subject: x\r\n\r\n{body}").as_bytes(),
+                canonicalization,
+            )
+        };
+
+        assert_eq!(length("hi\r\n\r\n\r\n", Canonicalization::Simple), 4);
+        assert_eq!(length("hi", Canonicalization::Simple), 4);
+        assert_eq!(length("", Canonicalization::Simple), 2);
+        assert_eq!(length("", Canonicalization::Relaxed), 0);
+        assert_eq!(length(" \t\r\n", Canonicalization::Relaxed), 0);
+        assert_eq!(length("  a\t\tb  \r\n", Canonicalization::Relaxed), 6);
+        assert_eq!(length("  a\t\tb  \r\n", Canonicalization::Simple), 10);
+    }
+
+    #[test]
+    fn rejects_expired_and_future_signatures() {
+        let check_at = |tags: String| check(&format!("h=from:subject; {tags}"), "hi\r\n");
+
+        assert!(check_at(format!("x={}", NOW)).is_ok());
+        assert!(check_at(format!("x={}", NOW - 1))
+            .unwrap_err()
+            .contains("x="));
+
+        assert!(check_at(format!("t={}", NOW - 3600)).is_ok());
+        assert!(check_at(format!("t={}", NOW + MAX_CLOCK_SKEW_SECONDS)).is_ok());
+        assert!(check_at(format!("t={}", NOW + MAX_CLOCK_SKEW_SECONDS + 1))
+            .unwrap_err()
+            .contains("t="));
+    }
+
+    #[test]
+    fn keeps_only_strict_signatures() {
+        let email = message(&["h=from; bh=CCCC", "h=from:subject; t=1"], "hi\r\n");
+        let strict = strict_signatures(&email, NOW).unwrap();
+
+        assert_eq!(strict.len(), 1);
+        assert_eq!(strict[0].signed_at, Some(1));
+
+        let email = message(&["h=from", "h=subject"], "hi\r\n");
+        assert!(strict_signatures(&email, NOW).is_err());
+    }
+
+    #[test]
+    fn strips_other_signatures() {
+        let raw = format!(
+            "{}\r\n{}\r\n\tt=2\r\nFrom: alice@example.com\r\n\r\nbody\r\n",
+            signature_header("h=from:subject; t=1"),
+            signature_header("h=from; bh=CCCC;"),
+        );
+
+        let keep = DkimSignature::parse(&signature_header("h=from:subject")).unwrap();
+        let other = DkimSignature::parse("d=example.com; s=sel; h=from; bh=CCCC; b=BBBB").unwrap();
+
+        let stripped = String::from_utf8(with_only_signature(raw.as_bytes(), &keep)).unwrap();
+        assert_eq!(
+            stripped,
+            format!(
+                "{}\r\nFrom: alice@example.com\r\n\r\nbody\r\n",
+                signature_header("h=from:subject; t=1")
+            )
+        );
+
+        // the folded continuation goes with it
+        let stripped = String::from_utf8(with_only_signature(raw.as_bytes(), &other)).unwrap();
+        assert_eq!(
+            stripped,
+            format!(
+                "{}\r\n\tt=2\r\nFrom: alice@example.com\r\n\r\nbody\r\n",
+                signature_header("h=from; bh=CCCC;")
+            )
+        );
+    }
+}
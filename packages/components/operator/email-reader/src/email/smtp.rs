@@ -0,0 +1,391 @@
+use std::io::{BufRead, BufReader, Read, Write};
+
+use base64::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::{SmtpConfig, SmtpCredentials},
+    email::imap::connection::ImapConnection,
+    error::{AppError, AppResult},
+    oauth::{fetch_gmail_access_token, fetch_gmail_email_address},
+    wasi::clocks::wall_clock,
+};
+
+pub struct OutgoingEmail<'a> {
+    /// Bare address, e.g. "alice@example.com"
+    pub to: &'a str,
+    pub subject: &'a str,
+    pub body: &'a str,
+    /// Message-ID of the email we're replying to, so clients thread the reply
+    pub in_reply_to: Option<&'a str>,
+}
+
+pub async fn send_email(config: &SmtpConfig, email: OutgoingEmail<'_>) -> AppResult<()> {
+    let connection = ImapConnection::new(config).await?;
+    println!("Successfully connected to {config}");
+
+    let mut client = SmtpClient {
+        stream: BufReader::new(connection),
+    };
+
+    client.read_reply(220)?;
+    client.command(&format!("EHLO {}", helo_domain(&config.from)), 250)?;
+    authenticate(&mut client, &config.credentials, config.tls).await?;
+    client.command(&format!("MAIL FROM:<{}>", config.from), 250)?;
+    client.command(&format!("RCPT TO:<{}>", email.to), 250)?;
+    client.command("DATA", 354)?;
+    client.send_data(&format_message(&config.from, &email, wall_clock::now()))?;
+    client.read_reply(250)?;
+    client.command("QUIT", 221)?;
+
+    Ok(())
+}
+
+/// Credentials only ever go out over TLS, there's no STARTTLS to upgrade a plain connection
+async fn authenticate<S: Read + Write>(
+    client: &mut SmtpClient<S>,
+    credentials: &SmtpCredentials,
+    tls: bool,
+) -> AppResult<()> {
+    if !tls && !matches!(credentials, SmtpCredentials::None) {
+        return Err(AppError::Auth(anyhow::anyhow!(
+            "refusing to send SMTP credentials without TLS"
+        )));
+    }
+
+    match credentials {
+        SmtpCredentials::None => {}
+        SmtpCredentials::Plain { username, password } => {
+            let token = BASE64_STANDARD.encode(format!("\0{username}\0{password}"));
+            client
+                .command(&format!("AUTH PLAIN {token}"), 235)
+                .map_err(|e| AppError::Auth(anyhow::anyhow!("{e}")))?;
+        }
+        SmtpCredentials::Gmail {
+            client_id,
+            client_secret,
+            refresh_token,
+        } => {
+            let access_token =
+                fetch_gmail_access_token(client_id, client_secret, refresh_token).await?;
+            let username = fetch_gmail_email_address(&access_token).await?;
+
+            let token = BASE64_STANDARD.encode(format!(
+                "user={username}\x01auth=Bearer {access_token}\x01\x01"
+            ));
+            client
+                .command(&format!("AUTH XOAUTH2 {token}"), 235)
+                .map_err(|e| AppError::Auth(anyhow::anyhow!("{e}")))?;
+        }
+    }
+
+    Ok(())
+}
+
+struct SmtpClient<S> {
+    stream: BufReader<S>,
+}
+
+impl<S: Read + Write> SmtpClient<S> {
+    fn command(&mut self, line: &str, expected: u16) -> AppResult<String> {
+        let stream = self.stream.get_mut();
+        stream.write_all(line.as_bytes())?;
+        stream.write_all(b"\r\n")?;
+        stream.flush()?;
+
+        self.read_reply(expected)
+    }
+
+    fn send_data(&mut self, message: &str) -> AppResult<()> {
+        let stream = self.stream.get_mut();
+
+        for line in message.lines() {
+            // dot-stuffing, RFC 5321 section 4.5.2
+            if line.starts_with('.') {
+                stream.write_all(b".")?;
+            }
+            stream.write_all(line.as_bytes())?;
+            stream.write_all(b"\r\n")?;
+        }
+        stream.write_all(b".\r\n")?;
+        stream.flush()?;
+
+        Ok(())
+    }
+
+    /// Reads a (possibly multi-line) reply, e.g. "250-first\r\n250 last\r\n"
+    fn read_reply(&mut self, expected: u16) -> AppResult<String> {
+        let mut reply = String::new();
+
+        loop {
+            let mut line = String::new();
+            if self.stream.read_line(&mut line)? == 0 {
+                return Err(AppError::Smtp("connection closed".to_string()));
+            }
+
+            let line = line.trim_end_matches(['\r', '\n']);
+            reply.push_str(line);
+            reply.push('\n');
+
+            let code: u16 = line
+                .get(..3)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| AppError::Smtp(format!("malformed reply: {line}")))?;
+
+            // a space (or nothing) after the code marks the last line
+            if line.as_bytes().get(3) != Some(&b'-') {
+                if code != expected {
+                    return Err(AppError::Smtp(format!(
+                        "expected {expected}, got: {}",
+                        reply.trim_end()
+                    )));
+                }
+                return Ok(reply);
+            }
+        }
+    }
+}
+
+fn format_message(from: &str, email: &OutgoingEmail, now: wall_clock::Datetime) -> String {
+    let mut headers = vec![
+        format!("From: <{from}>"),
+        format!("To: <{}>", single_line(email.to)),
+        format!("Subject: {}", single_line(email.subject)),
+        format!("Date: {}", rfc5322_date(now.seconds)),
+        format!("Message-ID: {}", message_id(from, email, now.nanoseconds)),
+    ];
+
+    if let Some(in_reply_to) = email.in_reply_to {
+        let in_reply_to = single_line(in_reply_to);
+        headers.push(format!("In-Reply-To: {in_reply_to}"));
+        headers.push(format!("References: {in_reply_to}"));
+    }
+
+    headers.push("MIME-Version: 1.0".to_string());
+    headers.push("Content-Type: text/plain; charset=utf-8".to_string());
+
+    format!("{}\r\n\r\n{}", headers.join("\r\n"), email.body)
+}
+
+fn message_id(from: &str, email: &OutgoingEmail, nanos: u32) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(email.to);
+    hasher.update(email.subject);
+    hasher.update(email.in_reply_to.unwrap_or_default());
+    hasher.update(nanos.to_be_bytes());
+
+    format!(
+        "<{}@{}>",
+        const_hex::encode(&hasher.finalize()[..16]),
+        helo_domain(from)
+    )
+}
+
+/// e.g. "Thu, 01 Jan 1970 00:00:00 +0000"
+fn rfc5322_date(unix_seconds: u64) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+
+    let days = unix_seconds / 86_400;
+    let secs = unix_seconds % 86_400;
+
+    // civil_from_days, http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} +0000",
+        WEEKDAYS[(days % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60,
+    )
+}
+
+// header values must never smuggle in extra headers
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+fn helo_domain(from: &str) -> &str {
+    from.rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .unwrap_or("localhost")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Replays canned server replies and keeps whatever the client writes
+    struct ScriptedServer {
+        replies: Cursor<Vec<u8>>,
+        written: Vec<u8>,
+    }
+
+    impl Read for ScriptedServer {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for ScriptedServer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn scripted_client(replies: &str) -> SmtpClient<ScriptedServer> {
+        SmtpClient {
+            stream: BufReader::new(ScriptedServer {
+                replies: Cursor::new(replies.as_bytes().to_vec()),
+                written: Vec::new(),
+            }),
+        }
+    }
+
+    fn written(client: &SmtpClient<ScriptedServer>) -> String {
+        String::from_utf8(client.stream.get_ref().written.clone()).unwrap()
+    }
+
+    fn plain() -> SmtpCredentials {
+        SmtpCredentials::Plain {
+            username: "alice".to_string(),
+            password: "secret".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rfc5322_date() {
+        assert_eq!(rfc5322_date(0), "Thu, 01 Jan 1970 00:00:00 +0000");
+        // a leap day
+        assert_eq!(rfc5322_date(951_782_400), "Tue, 29 Feb 2000 00:00:00 +0000");
+        assert_eq!(
+            rfc5322_date(1_700_000_000),
+            "Tue, 14 Nov 2023 22:13:20 +0000"
+        );
+        assert_eq!(
+            rfc5322_date(4_102_444_799),
+            "Thu, 31 Dec 2099 23:59:59 +0000"
+        );
+    }
+
+    #[test]
+    fn test_read_reply() {
+        let reply = scripted_client("250-first\r\n250 last\r\n")
+            .read_reply(250)
+            .unwrap();
+        assert_eq!(reply, "250-first\n250 last\n");
+
+        // a bare code ends the reply too
+        scripted_client("221\r\n").read_reply(221).unwrap();
+
+        scripted_client("550 no such user\r\n")
+            .read_reply(250)
+            .unwrap_err();
+        scripted_client("250-first\r\n")
+            .read_reply(250)
+            .unwrap_err();
+        scripted_client("hello\r\n").read_reply(250).unwrap_err();
+        scripted_client("").read_reply(250).unwrap_err();
+    }
+
+    #[test]
+    fn test_send_data_stuffs_dots() {
+        let mut client = scripted_client("");
+
+        client.send_data("Hi\r\n.hidden\r\n.").unwrap();
+
+        assert_eq!(written(&client), "Hi\r\n..hidden\r\n..\r\n.\r\n");
+    }
+
+    #[test]
+    fn test_format_message() {
+        let email = OutgoingEmail {
+            to: "bob@example.com",
+            subject: "Re: deposit\r\nBcc: eve@example.com",
+            body: "Done.\r\n",
+            in_reply_to: Some("<original@example.com>"),
+        };
+        let now = wall_clock::Datetime {
+            seconds: 0,
+            nanoseconds: 0,
+        };
+
+        let message = format_message("hello@example.com", &email, now);
+        let (headers, body) = message.split_once("\r\n\r\n").unwrap();
+        let headers = headers.split("\r\n").collect::<Vec<_>>();
+
+        assert_eq!(body, "Done.\r\n");
+        assert!(headers.contains(&"From: <hello@example.com>"));
+        assert!(headers.contains(&"To: <bob@example.com>"));
+        // the subject can't smuggle in another header
+        assert!(headers.contains(&"Subject: Re: deposit  Bcc: eve@example.com"));
+        assert!(headers.contains(&"Date: Thu, 01 Jan 1970 00:00:00 +0000"));
+        assert!(headers.contains(&"In-Reply-To: <original@example.com>"));
+        assert!(headers.contains(&"References: <original@example.com>"));
+        assert!(
+            headers
+                .iter()
+                .any(|header| header.starts_with("Message-ID: <")
+                    && header.ends_with("@example.com>"))
+        );
+
+        let email = OutgoingEmail {
+            in_reply_to: None,
+            ..email
+        };
+        let message = format_message("hello@example.com", &email, now);
+
+        assert!(!message.contains("In-Reply-To"));
+        assert!(!message.contains("References"));
+    }
+
+    #[tokio::test]
+    async fn test_auth_plain() {
+        let mut client = scripted_client("235 ok\r\n");
+
+        authenticate(&mut client, &plain(), true).await.unwrap();
+
+        // base64 of "\0alice\0secret"
+        assert_eq!(written(&client), "AUTH PLAIN AGFsaWNlAHNlY3JldA==\r\n");
+
+        let mut client = scripted_client("535 bad credentials\r\n");
+        authenticate(&mut client, &plain(), true).await.unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn test_auth_requires_tls() {
+        let mut client = scripted_client("235 ok\r\n");
+
+        authenticate(&mut client, &plain(), false)
+            .await
+            .unwrap_err();
+        assert_eq!(written(&client), "");
+
+        // nothing to protect without credentials
+        authenticate(&mut client, &SmtpCredentials::None, false)
+            .await
+            .unwrap();
+        assert_eq!(written(&client), "");
+    }
+}
@@ -3,26 +3,117 @@ use std::sync::Arc;
 use sloggers::{null::NullLoggerBuilder, Build};
 
 use crate::{
-    email::parser::EmailMessage,
+    email::{
+        age::{check_email_age, now},
+        auth_results::ReceiverVerdict,
+        parser::EmailMessage,
+        pinned_keys::{DkimRegistry, PinnedKeyLookup, PinnedKeys},
+        policy::{DomainPolicy, SenderPolicy},
+        resolver::Resolver,
+        signature::{strict_signatures, with_only_signature, DkimSignature},
+    },
     error::{AppError, AppResult},
 };
 
-pub async fn verify_email(email: &EmailMessage) -> AppResult<()> {
-    let resolver = cfdkim::dns::wasi::WasiCloudflareLookup {};
+/// What verifying an email established
+pub struct VerifiedEmail {
+    /// The signature the email verified through
+    pub signature: DkimSignature,
+    /// When the email was signed, if it says, see `check_email_age`
+    pub signed_at: Option<u64>,
+}
 
+pub async fn verify_email(email: &EmailMessage) -> AppResult<VerifiedEmail> {
     let from_domain = email
         .original_sender
-        .split('@')
-        .nth(1)
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
         .ok_or_else(|| AppError::CannotExtractDomain(email.original_sender.clone()))?;
 
-    cfdkim::verify_email_with_resolver(
-        &NullLoggerBuilder.build()?,
-        from_domain,
-        &email.get_parsed()?,
-        Arc::new(resolver),
-    )
-    .await?;
+    let policy = DomainPolicy::new()?.for_sender(from_domain)?;
+    let now = now()?;
+    let signatures = strict_signatures(email, now)?;
+
+    // cheaper than our own verification, so it goes first
+    if let Some(verdict) = ReceiverVerdict::new()? {
+        verdict.check(email, &signatures)?;
+    }
+
+    let signature = verify_signatures(email, from_domain, &signatures, &policy).await?;
+    let signed_at = check_email_age(email, signature, now)?;
+
+    Ok(VerifiedEmail {
+        signature: signature.clone(),
+        signed_at,
+    })
+}
+
+/// The signature the email verified through
+///
+/// Each one is verified with the email's other signatures taken out, so the verifier can't
+/// pass the email through a signature we rejected, or one other than the one we return.
+async fn verify_signatures<'a>(
+    email: &EmailMessage,
+    from_domain: &str,
+    signatures: &'a [DkimSignature],
+    policy: &SenderPolicy,
+) -> AppResult<&'a DkimSignature> {
+    let logger = NullLoggerBuilder.build()?;
+
+    let (resolver, keys) = match DkimRegistry::new()? {
+        Some(registry) => (
+            None,
+            Some(Arc::new(
+                PinnedKeys::fetch(&registry, signatures, policy).await?,
+            )),
+        ),
+        None => (
+            Some(Arc::new(
+                Resolver::new()?.resolve(signatures, policy).await?,
+            )),
+            None,
+        ),
+    };
+
+    // nothing pinned for any of the signatures
+    let mut result = Err(AppError::Dkim(cfdkim::DKIMError::NoKeyForSignature));
+
+    for signature in signatures {
+        let raw = with_only_signature(&email.raw_bytes, signature);
+        let parsed = mailparse::parse_mail(&raw)?;
+
+        if let Some(resolver) = &resolver {
+            result =
+                cfdkim::verify_email_with_resolver(&logger, from_domain, &parsed, resolver.clone())
+                    .await
+                    .map(|_| signature)
+                    .map_err(AppError::from);
+        }
+
+        if let Some(keys) = &keys {
+            for attempt in 0..keys.max_attempts() {
+                let resolver = PinnedKeyLookup::new(keys.clone(), attempt);
+
+                result = cfdkim::verify_email_with_resolver(
+                    &logger,
+                    from_domain,
+                    &parsed,
+                    Arc::new(resolver),
+                )
+                .await
+                .map(|_| signature)
+                .map_err(AppError::from);
+
+                if result.is_ok() {
+                    break;
+                }
+            }
+        }
+
+        if result.is_ok() {
+            break;
+        }
+    }
 
-    Ok(())
+    result
 }
@@ -39,9 +39,36 @@ pub enum AppError {
     #[error("DKIM Result: {0}")]
     DkimResult(String),
 
+    #[error("DKIM signature coverage: {0}")]
+    DkimCoverage(String),
+
+    #[error("Email age: {0}")]
+    EmailAge(String),
+
+    #[error("Receiver verdict: {0}")]
+    ReceiverVerdict(String),
+
+    #[error("Domain policy: {0:?}")]
+    Policy(anyhow::Error),
+
+    #[error("DNS resolver: {0:?}")]
+    Resolver(anyhow::Error),
+
+    #[error("DKIM registry: {0:?}")]
+    DkimRegistry(anyhow::Error),
+
     #[error("Cannot extract domain: {0}")]
     CannotExtractDomain(String),
 
+    #[error("DKIM proof: {0:?}")]
+    Proof(anyhow::Error),
+
+    #[error("SMTP: {0}")]
+    Smtp(String),
+
+    #[error("{0:?}")]
+    Io(#[from] std::io::Error),
+
     #[error("slog: {0:?}")]
     Slog(#[from] sloggers::Error),
 }
@@ -1,4 +1,5 @@
 #![allow(warnings)]
+mod chain;
 mod config;
 mod email;
 mod error;
@@ -6,13 +7,27 @@ mod oauth;
 
 use anyhow::bail;
 use app_contract_api::{
+    dkim_proof::DkimProof,
     proxy::ProxyExecuteMsg,
-    service_handler::msg::{CustomExecuteMsg, UserIdEmail},
+    service_handler::{
+        evm,
+        msg::{CustomExecuteMsg, PendingAction, PrivateEmail, UserIdEmail},
+    },
     user_registry::msg::UserId,
 };
 use cfdkim::verify_email_with_resolver;
 
-use crate::{email::verify::verify_email, wavs::operator::input::TriggerData};
+use crate::{
+    config::{confirm_nonce_secret, max_emails_per_trigger, user_id_salt, SmtpConfig},
+    email::{
+        notify::{notify_failed, notify_submitted, report_outcomes, ReplyTarget},
+        parser::EmailMessage,
+        proof::prove_email,
+        smtp::{send_email, OutgoingEmail},
+        verify::verify_email,
+    },
+    wavs::operator::input::TriggerData,
+};
 
 // this is needed just to make the ide/compiler happy... we're _always_ compiling to wasm32-wasi
 wit_bindgen::generate!({
@@ -44,36 +59,46 @@ impl Guest for Component {
 async fn inner(trigger_action: TriggerAction) -> anyhow::Result<Vec<WasmResponse>> {
     match trigger_action.data {
         TriggerData::Cron(_) => {
-            // TODO - read a batch instead of just one email
-            let email = match email::read_next_email().await? {
-                Some(email) => email,
-                None => {
-                    return Ok(Vec::new());
-                }
-            };
+            // what was submitted on earlier triggers has had time to land
+            report_outcomes().await;
 
-            verify_email(&email).await?;
+            let max_emails = max_emails_per_trigger()?;
 
-            let event_id_salt = email.event_id_salt()?;
+            let mut emails = Vec::new();
+            while emails.len() < max_emails {
+                match email::read_next_email().await? {
+                    Some(email) => emails.push(email),
+                    None => break,
+                }
+            }
 
-            let user_id = UserId::new_email_address(&email.original_sender);
+            if emails.is_empty() {
+                return Ok(Vec::new());
+            }
 
-            let email = UserIdEmail {
-                from: user_id,
-                subject: email.subject.unwrap_or_default(),
-            };
+            let read_several = emails.len() > 1;
+            let user_id_salt = user_id_salt()?;
+            let nonce_secret = confirm_nonce_secret()?;
+            let mut responses = Vec::new();
 
-            println!("Got email: {:#?}", email);
-            println!("Proxy execute msg: {:#?}", email.proxy_execute_msg());
-            println!("Event ID salt: {}", const_hex::encode(&event_id_salt));
+            // each email is its own envelope, under an event id derived from the email alone,
+            // so the operators agree on it however their reads were split across triggers
+            for email in emails {
+                let email = match accept_email(email).await {
+                    Ok(email) => email,
+                    // one bad email shouldn't hold up the others read with it
+                    Err(e) if read_several => {
+                        eprintln!("Skipping email: {e:?}");
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                };
+
+                responses
+                    .push(email_response(email, &user_id_salt, nonce_secret.as_deref()).await?);
+            }
 
-            return Ok(vec![WasmResponse {
-                payload: CustomExecuteMsg::Email(email)
-                    .encode()
-                    .map_err(|e| anyhow::anyhow!("{e:?}"))?,
-                ordering: None,
-                event_id_salt: Some(event_id_salt),
-            }]);
+            return Ok(responses);
         }
         TriggerData::Raw(data) => {
             let data = std::str::from_utf8(&data)?;
@@ -95,9 +120,28 @@ async fn inner(trigger_action: TriggerAction) -> anyhow::Result<Vec<WasmResponse
                         Err(e) => println!("Email verification failed: {:?}", e),
                     }
                 }
-                _ => {
-                    bail!("Unknown command: {}", data);
-                }
+                _ => match data.strip_prefix("send-mail ") {
+                    Some(to) => {
+                        let config = SmtpConfig::new()?
+                            .ok_or_else(|| anyhow::anyhow!("SMTP is not configured"))?;
+
+                        send_email(
+                            &config,
+                            OutgoingEmail {
+                                to: to.trim(),
+                                subject: "Test email",
+                                body: "Sent from the email-reader component.\r\n",
+                                in_reply_to: None,
+                            },
+                        )
+                        .await?;
+
+                        println!("Sent test email to {}", to.trim());
+                    }
+                    None => {
+                        bail!("Unknown command: {}", data);
+                    }
+                },
             }
         }
         _ => {
@@ -107,4 +151,153 @@ async fn inner(trigger_action: TriggerAction) -> anyhow::Result<Vec<WasmResponse
     Ok(Vec::new())
 }
 
+/// An email that passed verification, ready to be turned into a command
+struct AcceptedEmail {
+    email: EmailMessage,
+    proof: Option<DkimProof>,
+    signed_at: Option<u64>,
+    event_id_salt: Vec<u8>,
+    reply_target: Option<ReplyTarget>,
+}
+
+async fn accept_email(email: EmailMessage) -> anyhow::Result<AcceptedEmail> {
+    let verified = verify_email(&email).await?;
+
+    // the sender is authenticated from here on, so it's safe to reply
+    let reply_target = ReplyTarget::new(&email);
+
+    let event_id_salt = match email.event_id_salt(&verified.signature) {
+        Ok(salt) => salt,
+        Err(e) => {
+            if let Some(reply_target) = &reply_target {
+                notify_failed(reply_target, &e).await;
+            }
+            return Err(e);
+        }
+    };
+
+    let proof = if proofs_required() {
+        Some(prove_email(&email).await?)
+    } else {
+        None
+    };
+
+    Ok(AcceptedEmail {
+        email,
+        proof,
+        signed_at: verified.signed_at,
+        event_id_salt,
+        reply_target,
+    })
+}
+
+/// The envelope for one email, the sender hears what's being submitted once it's encoded
+///
+/// With a `nonce_secret`, the command can be held for confirmation under the nonce derived
+/// from it and the event id, which only the sender gets to see.
+async fn email_response(
+    email: AcceptedEmail,
+    user_id_salt: &[u8],
+    nonce_secret: Option<&[u8]>,
+) -> anyhow::Result<WasmResponse> {
+    let event_id = host::get_event_id(Some(email.event_id_salt.clone()));
+    let nonce = nonce_secret.map(|secret| PendingAction::nonce_from_secret(secret, &event_id));
+
+    let msg = email_to_msg(
+        email.email,
+        email.proof,
+        email.signed_at,
+        user_id_salt,
+        &event_id,
+        nonce.as_deref(),
+    );
+
+    println!("Event ID salt: {}", const_hex::encode(&email.event_id_salt));
+
+    let payload = if evm_payload() {
+        evm::encode_payload(&msg)
+    } else {
+        msg.encode().map_err(|e| anyhow::anyhow!("{e:?}"))
+    };
+
+    let payload = match payload {
+        Ok(payload) => payload,
+        Err(e) => {
+            if let Some(reply_target) = &email.reply_target {
+                notify_failed(reply_target, &e).await;
+            }
+            return Err(e);
+        }
+    };
+
+    if let Some(reply_target) = &email.reply_target {
+        notify_submitted(reply_target, &msg, &event_id, nonce.as_deref()).await;
+    }
+
+    Ok(WasmResponse {
+        payload,
+        ordering: None,
+        event_id_salt: Some(email.event_id_salt),
+    })
+}
+
+/// With a `proof`, the contract parses the command itself
+///
+/// The contract may have a max email age, so the message carries `signed_at` when there is one.
+/// `seed` is the event id the contract will run the message under, private emails commit to it.
+/// With a `nonce`, the message carries its hash in case the contract holds it for confirmation.
+fn email_to_msg(
+    email: EmailMessage,
+    proof: Option<DkimProof>,
+    signed_at: Option<u64>,
+    user_id_salt: &[u8],
+    seed: &[u8],
+    nonce: Option<&str>,
+) -> CustomExecuteMsg {
+    let user_id = UserId::new_email_address_with_salt(&email.original_sender, user_id_salt);
+
+    let email = UserIdEmail {
+        from: user_id,
+        subject: email.subject.unwrap_or_default(),
+    };
+
+    println!("Got email: {:#?}", email);
+    println!("Proxy execute msg: {:#?}", email.proxy_execute_msg());
+
+    let msg = match proof {
+        Some(proof) => CustomExecuteMsg::ProvenEmail { email, proof },
+        None => match CustomExecuteMsg::from_email_with_salt(email, user_id_salt) {
+            CustomExecuteMsg::Email(email) if private_emails() => {
+                CustomExecuteMsg::PrivateEmail(PrivateEmail::new(&email, seed))
+            }
+            msg => msg,
+        },
+    };
+
+    let msg = match nonce {
+        Some(nonce) => msg.confirmable(nonce),
+        None => msg,
+    };
+
+    match signed_at {
+        Some(signed_at) => msg.timestamped(signed_at),
+        None => msg,
+    }
+}
+
+/// Set from the service handler's privacy mode when the service is deployed
+fn private_emails() -> bool {
+    host::config_var("PRIVATE_EMAILS").is_some_and(|value| value == "true")
+}
+
+/// Set for workflows that submit to the EVM service handler, which takes ABI encoded commands
+fn evm_payload() -> bool {
+    host::config_var("PAYLOAD_ENCODING").is_some_and(|value| value == "abi")
+}
+
+/// Set when the service handler was deployed with a proof verifier
+fn proofs_required() -> bool {
+    host::config_var("DKIM_PROOFS").is_some_and(|value| value == "true")
+}
+
 export!(Component);
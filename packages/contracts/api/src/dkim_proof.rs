@@ -0,0 +1,63 @@
+//! Succinct proofs that a DKIM-signed email exists, in the style of zk-email.
+//! The circuit and prover live outside this repo, this is the format they produce
+//! and the interface verifier contracts implement.
+
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::Binary;
+use sha2::{Digest, Sha256};
+
+use crate::user_registry::msg::UserId;
+
+#[cw_serde]
+pub struct DkimProof {
+    /// Proving system and circuit, e.g. "groth16-bn254/email-subject-v1"
+    /// Verifiers reject schemes they don't know
+    pub scheme: String,
+    /// Serialized proof, as produced by the prover
+    pub proof: Binary,
+    pub public_inputs: DkimPublicInputs,
+}
+
+/// What the proof attests to, without revealing the rest of the email
+#[cw_serde]
+pub struct DkimPublicInputs {
+    /// `UserId` of the From address, derived in-circuit
+    pub from_hash: UserId,
+    /// Domain of the same From address, must be the signing domain
+    pub from_domain: String,
+    /// `UserId` of the address a "link" or "unlink" subject names, derived in-circuit with
+    /// the same salt as `from_hash`. The contract can't hash it, it doesn't know the salt
+    #[serde(default)]
+    pub target_hash: Option<UserId>,
+    /// Hex sha256 of the decoded Subject
+    pub command_hash: String,
+    /// The signing domain (DKIM `d=` tag)
+    pub domain: String,
+    /// The selector (DKIM `s=` tag), to find the key in the DKIM registry
+    pub selector: String,
+    /// Hex sha256 of the DKIM public key the signature verifies under
+    pub public_key_hash: String,
+}
+
+impl DkimPublicInputs {
+    pub fn command_hash(subject: &str) -> String {
+        const_hex::encode(Sha256::digest(subject.as_bytes()))
+    }
+
+    pub fn public_key_hash(public_key: &[u8]) -> String {
+        const_hex::encode(Sha256::digest(public_key))
+    }
+}
+
+/// The query every pluggable verifier contract must answer
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum VerifierQueryMsg {
+    #[returns(VerifyDkimProofResponse)]
+    VerifyDkimProof { proof: DkimProof },
+}
+
+#[cw_serde]
+pub struct VerifyDkimProofResponse {
+    pub valid: bool,
+}
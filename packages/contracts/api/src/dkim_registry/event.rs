@@ -0,0 +1,189 @@
+use cosmwasm_schema::cw_serde;
+
+#[cw_serde]
+pub struct DkimKeyRecordedEvent {
+    pub domain: String,
+    pub selector: String,
+    pub key_hash: String,
+    pub first_seen_height: u64,
+    pub last_seen_height: u64,
+}
+
+impl DkimKeyRecordedEvent {
+    pub const EVENT_TYPE: &'static str = "dkim-key-recorded";
+    pub const EVENT_ATTR_KEY_DOMAIN: &'static str = "domain";
+    pub const EVENT_ATTR_KEY_SELECTOR: &'static str = "selector";
+    pub const EVENT_ATTR_KEY_KEY_HASH: &'static str = "key-hash";
+    pub const EVENT_ATTR_KEY_FIRST_SEEN_HEIGHT: &'static str = "first-seen-height";
+    pub const EVENT_ATTR_KEY_LAST_SEEN_HEIGHT: &'static str = "last-seen-height";
+}
+
+impl From<DkimKeyRecordedEvent> for cosmwasm_std::Event {
+    fn from(src: DkimKeyRecordedEvent) -> Self {
+        cosmwasm_std::Event::new(DkimKeyRecordedEvent::EVENT_TYPE)
+            .add_attribute(DkimKeyRecordedEvent::EVENT_ATTR_KEY_DOMAIN, src.domain)
+            .add_attribute(DkimKeyRecordedEvent::EVENT_ATTR_KEY_SELECTOR, src.selector)
+            .add_attribute(DkimKeyRecordedEvent::EVENT_ATTR_KEY_KEY_HASH, src.key_hash)
+            .add_attribute(
+                DkimKeyRecordedEvent::EVENT_ATTR_KEY_FIRST_SEEN_HEIGHT,
+                src.first_seen_height.to_string(),
+            )
+            .add_attribute(
+                DkimKeyRecordedEvent::EVENT_ATTR_KEY_LAST_SEEN_HEIGHT,
+                src.last_seen_height.to_string(),
+            )
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for DkimKeyRecordedEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let mut domain = None;
+        let mut selector = None;
+        let mut key_hash = None;
+        let mut first_seen_height = None;
+        let mut last_seen_height = None;
+
+        for attr in event.attributes.iter() {
+            match attr.key.as_str() {
+                Self::EVENT_ATTR_KEY_DOMAIN => domain = Some(attr.value.to_string()),
+                Self::EVENT_ATTR_KEY_SELECTOR => selector = Some(attr.value.to_string()),
+                Self::EVENT_ATTR_KEY_KEY_HASH => key_hash = Some(attr.value.to_string()),
+                Self::EVENT_ATTR_KEY_FIRST_SEEN_HEIGHT => {
+                    first_seen_height = Some(attr.value.parse::<u64>()?)
+                }
+                Self::EVENT_ATTR_KEY_LAST_SEEN_HEIGHT => {
+                    last_seen_height = Some(attr.value.parse::<u64>()?)
+                }
+                _ => {}
+            }
+        }
+
+        match (
+            domain,
+            selector,
+            key_hash,
+            first_seen_height,
+            last_seen_height,
+        ) {
+            (
+                Some(domain),
+                Some(selector),
+                Some(key_hash),
+                Some(first_seen_height),
+                Some(last_seen_height),
+            ) => Ok(Self {
+                domain,
+                selector,
+                key_hash,
+                first_seen_height,
+                last_seen_height,
+            }),
+            (domain, selector, key_hash, first_seen_height, last_seen_height) => {
+                let mut missing_attrs = Vec::new();
+                if domain.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_DOMAIN);
+                }
+                if selector.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_SELECTOR);
+                }
+                if key_hash.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_KEY_HASH);
+                }
+                if first_seen_height.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_FIRST_SEEN_HEIGHT);
+                }
+                if last_seen_height.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_LAST_SEEN_HEIGHT);
+                }
+                Err(anyhow::anyhow!(
+                    "Missing required attributes in DkimKeyRecordedEvent: {:?}",
+                    missing_attrs
+                ))
+            }
+        }
+    }
+}
+
+#[cw_serde]
+pub struct DkimKeyRevokedEvent {
+    pub domain: String,
+    pub selector: String,
+    pub key_hash: String,
+}
+
+impl DkimKeyRevokedEvent {
+    pub const EVENT_TYPE: &'static str = "dkim-key-revoked";
+    pub const EVENT_ATTR_KEY_DOMAIN: &'static str = "domain";
+    pub const EVENT_ATTR_KEY_SELECTOR: &'static str = "selector";
+    pub const EVENT_ATTR_KEY_KEY_HASH: &'static str = "key-hash";
+}
+
+impl From<DkimKeyRevokedEvent> for cosmwasm_std::Event {
+    fn from(src: DkimKeyRevokedEvent) -> Self {
+        cosmwasm_std::Event::new(DkimKeyRevokedEvent::EVENT_TYPE)
+            .add_attribute(DkimKeyRevokedEvent::EVENT_ATTR_KEY_DOMAIN, src.domain)
+            .add_attribute(DkimKeyRevokedEvent::EVENT_ATTR_KEY_SELECTOR, src.selector)
+            .add_attribute(DkimKeyRevokedEvent::EVENT_ATTR_KEY_KEY_HASH, src.key_hash)
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for DkimKeyRevokedEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let mut domain = None;
+        let mut selector = None;
+        let mut key_hash = None;
+
+        for attr in event.attributes.iter() {
+            match attr.key.as_str() {
+                Self::EVENT_ATTR_KEY_DOMAIN => domain = Some(attr.value.to_string()),
+                Self::EVENT_ATTR_KEY_SELECTOR => selector = Some(attr.value.to_string()),
+                Self::EVENT_ATTR_KEY_KEY_HASH => key_hash = Some(attr.value.to_string()),
+                _ => {}
+            }
+        }
+
+        match (domain, selector, key_hash) {
+            (Some(domain), Some(selector), Some(key_hash)) => Ok(Self {
+                domain,
+                selector,
+                key_hash,
+            }),
+            (domain, selector, key_hash) => {
+                let mut missing_attrs = Vec::new();
+                if domain.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_DOMAIN);
+                }
+                if selector.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_SELECTOR);
+                }
+                if key_hash.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_KEY_HASH);
+                }
+                Err(anyhow::anyhow!(
+                    "Missing required attributes in DkimKeyRevokedEvent: {:?}",
+                    missing_attrs
+                ))
+            }
+        }
+    }
+}
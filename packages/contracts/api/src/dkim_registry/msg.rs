@@ -0,0 +1,211 @@
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Addr, Binary};
+
+use crate::dkim_proof::DkimPublicInputs;
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    pub admins: Vec<String>,
+}
+
+#[cw_serde]
+pub enum ExecuteMsg {
+    /// Admin only. Pins the key a selector currently publishes
+    ///
+    /// Recording a key that is already pinned only moves its `last_seen_height`,
+    /// so it's safe to re-run on every observation
+    RecordKey {
+        domain: String,
+        selector: String,
+        #[serde(default)]
+        key_type: DkimKeyType,
+        /// The decoded `p=` tag
+        public_key: Binary,
+    },
+    /// Admin only. Stops serving a pinned key, e.g. once it's known to be compromised
+    RevokeKey {
+        domain: String,
+        selector: String,
+        key_hash: String,
+    },
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum QueryMsg {
+    /// Every key pinned for a selector, most recently seen first
+    #[returns(KeysResponse)]
+    Keys { domain: String, selector: String },
+
+    #[returns(KeyResponse)]
+    Key {
+        domain: String,
+        selector: String,
+        key_hash: String,
+    },
+
+    #[returns(AdminsResponse)]
+    Admins {},
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
+
+#[cw_serde]
+pub struct KeysResponse {
+    pub keys: Vec<DkimKey>,
+}
+
+#[cw_serde]
+pub struct KeyResponse {
+    pub key: Option<DkimKey>,
+}
+
+#[cw_serde]
+pub struct AdminsResponse {
+    pub admins: Vec<Addr>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum DkimKeyType {
+    #[default]
+    Rsa,
+    Ed25519,
+}
+
+impl std::fmt::Display for DkimKeyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rsa => write!(f, "rsa"),
+            Self::Ed25519 => write!(f, "ed25519"),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct DkimKey {
+    pub domain: String,
+    pub selector: String,
+    pub key_type: DkimKeyType,
+    pub public_key: Binary,
+    /// Same hash a `DkimProof` commits to, see `DkimPublicInputs::public_key_hash`
+    pub key_hash: String,
+    pub first_seen_height: u64,
+    pub last_seen_height: u64,
+    pub revoked: bool,
+}
+
+impl DkimKey {
+    pub fn key_hash(public_key: &[u8]) -> String {
+        DkimPublicInputs::public_key_hash(public_key)
+    }
+
+    /// Domains and selectors are case-insensitive, and DNS names may be fully qualified
+    pub fn normalize(name: &str) -> String {
+        name.trim().trim_end_matches('.').to_ascii_lowercase()
+    }
+
+    /// Where the key is published, e.g. "20230601._domainkey.gmail.com"
+    pub fn dns_name(selector: &str, domain: &str) -> String {
+        format!(
+            "{}._domainkey.{}",
+            Self::normalize(selector),
+            Self::normalize(domain)
+        )
+    }
+
+    /// The record as it would be published over DNS
+    pub fn txt_record(&self) -> String {
+        format!(
+            "v=DKIM1; k={}; p={}",
+            self.key_type,
+            self.public_key.to_base64()
+        )
+    }
+
+    /// Reads the key type and decoded `p=` tag out of a published record
+    ///
+    /// `None` for anything that isn't a usable key, including revoked ones (empty `p=`)
+    pub fn parse_txt_record(record: &str) -> Option<(DkimKeyType, Binary)> {
+        let mut key_type = DkimKeyType::Rsa;
+        let mut public_key = None;
+
+        for tag in record.split(';') {
+            let Some((name, value)) = tag.split_once('=') else {
+                continue;
+            };
+
+            // base64 may be broken up by whitespace
+            let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+
+            match name.trim() {
+                "k" => {
+                    key_type = match value.as_str() {
+                        "rsa" => DkimKeyType::Rsa,
+                        "ed25519" => DkimKeyType::Ed25519,
+                        _ => return None,
+                    }
+                }
+                "p" if !value.is_empty() => public_key = Binary::from_base64(&value).ok(),
+                _ => {}
+            }
+        }
+
+        public_key.map(|public_key| (key_type, public_key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key_type: DkimKeyType) -> DkimKey {
+        let public_key = Binary::from(b"public key".to_vec());
+
+        DkimKey {
+            domain: "example.com".to_string(),
+            selector: "s1".to_string(),
+            key_type,
+            key_hash: DkimKey::key_hash(&public_key),
+            public_key,
+            first_seen_height: 1,
+            last_seen_height: 1,
+            revoked: false,
+        }
+    }
+
+    #[test]
+    fn test_txt_record_roundtrip() {
+        for key_type in [DkimKeyType::Rsa, DkimKeyType::Ed25519] {
+            let key = key(key_type.clone());
+
+            assert_eq!(
+                DkimKey::parse_txt_record(&key.txt_record()),
+                Some((key_type, key.public_key))
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_txt_record_defaults() {
+        let (key_type, public_key) =
+            DkimKey::parse_txt_record("v=DKIM1; p=cHVibGlj IGtleQ==").unwrap();
+
+        assert_eq!(key_type, DkimKeyType::Rsa);
+        assert_eq!(public_key.as_slice(), b"public key");
+    }
+
+    #[test]
+    fn test_parse_txt_record_revoked() {
+        assert_eq!(DkimKey::parse_txt_record("v=DKIM1; k=rsa; p="), None);
+    }
+
+    #[test]
+    fn test_dns_name() {
+        assert_eq!(
+            DkimKey::dns_name("S1", "Example.COM."),
+            "s1._domainkey.example.com"
+        );
+    }
+}
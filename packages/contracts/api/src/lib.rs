@@ -1,4 +1,6 @@
 pub mod control_center;
+pub mod dkim_proof;
+pub mod dkim_registry;
 pub mod proxy;
 pub mod service_handler;
 pub mod user_registry;
@@ -1,4 +1,5 @@
 use cosmwasm_schema::cw_serde;
+use cosmwasm_std::Timestamp;
 
 use crate::{service_handler::msg::UserIdEmail, user_registry::msg::UserId};
 
@@ -83,3 +84,369 @@ impl TryFrom<&cosmwasm_std::Event> for EmailEvent {
         }
     }
 }
+
+#[cw_serde]
+pub struct PrivateEmailEvent {
+    pub from: UserId,
+    pub commitment: String,
+    pub pagination_id: u64,
+}
+
+impl PrivateEmailEvent {
+    pub const EVENT_TYPE: &'static str = "private-email";
+    pub const EVENT_ATTR_KEY_EMAIL_FROM: &'static str = "email-from";
+    pub const EVENT_ATTR_KEY_COMMITMENT: &'static str = "commitment";
+    pub const EVENT_ATTR_KEY_PAGINATION_ID: &'static str = "pagination-id";
+}
+
+impl From<PrivateEmailEvent> for cosmwasm_std::Event {
+    fn from(src: PrivateEmailEvent) -> Self {
+        cosmwasm_std::Event::new(PrivateEmailEvent::EVENT_TYPE)
+            .add_attribute(
+                PrivateEmailEvent::EVENT_ATTR_KEY_EMAIL_FROM,
+                src.from.to_string(),
+            )
+            .add_attribute(PrivateEmailEvent::EVENT_ATTR_KEY_COMMITMENT, src.commitment)
+            .add_attribute(
+                PrivateEmailEvent::EVENT_ATTR_KEY_PAGINATION_ID,
+                src.pagination_id.to_string(),
+            )
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for PrivateEmailEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let mut from = None;
+        let mut commitment = None;
+        let mut pagination_id = None;
+
+        for attr in event.attributes.iter() {
+            match attr.key.as_str() {
+                Self::EVENT_ATTR_KEY_EMAIL_FROM => {
+                    from = Some(UserId::new_raw(attr.value.to_string()))
+                }
+                Self::EVENT_ATTR_KEY_COMMITMENT => commitment = Some(attr.value.to_string()),
+                Self::EVENT_ATTR_KEY_PAGINATION_ID => {
+                    pagination_id = Some(attr.value.parse::<u64>()?)
+                }
+                _ => {}
+            }
+        }
+
+        match (from, commitment, pagination_id) {
+            (Some(from), Some(commitment), Some(pagination_id)) => Ok(Self {
+                from,
+                commitment,
+                pagination_id,
+            }),
+            (from, commitment, pagination_id) => {
+                let mut missing_attrs = Vec::new();
+                if from.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_EMAIL_FROM);
+                }
+                if commitment.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_COMMITMENT);
+                }
+                if pagination_id.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_PAGINATION_ID);
+                }
+                Err(anyhow::anyhow!(
+                    "Missing required attributes in PrivateEmailEvent: {:?}",
+                    missing_attrs
+                ))
+            }
+        }
+    }
+}
+
+/// A command is held until `user_id` replies with the nonce, only its hash is public
+#[cw_serde]
+pub struct ConfirmationRequestedEvent {
+    pub user_id: UserId,
+    pub nonce_hash: String,
+    pub expires_at: Timestamp,
+}
+
+impl ConfirmationRequestedEvent {
+    pub const EVENT_TYPE: &'static str = "confirmation-requested";
+    pub const EVENT_ATTR_KEY_USER_ID: &'static str = "user-id";
+    pub const EVENT_ATTR_KEY_NONCE_HASH: &'static str = "nonce-hash";
+    pub const EVENT_ATTR_KEY_EXPIRES_AT: &'static str = "expires-at";
+}
+
+impl From<ConfirmationRequestedEvent> for cosmwasm_std::Event {
+    fn from(src: ConfirmationRequestedEvent) -> Self {
+        cosmwasm_std::Event::new(ConfirmationRequestedEvent::EVENT_TYPE)
+            .add_attribute(
+                ConfirmationRequestedEvent::EVENT_ATTR_KEY_USER_ID,
+                src.user_id.to_string(),
+            )
+            .add_attribute(
+                ConfirmationRequestedEvent::EVENT_ATTR_KEY_NONCE_HASH,
+                src.nonce_hash,
+            )
+            .add_attribute(
+                ConfirmationRequestedEvent::EVENT_ATTR_KEY_EXPIRES_AT,
+                src.expires_at.nanos().to_string(),
+            )
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for ConfirmationRequestedEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let mut user_id = None;
+        let mut nonce_hash = None;
+        let mut expires_at = None;
+
+        for attr in event.attributes.iter() {
+            match attr.key.as_str() {
+                Self::EVENT_ATTR_KEY_USER_ID => {
+                    user_id = Some(UserId::new_raw(attr.value.to_string()))
+                }
+                Self::EVENT_ATTR_KEY_NONCE_HASH => nonce_hash = Some(attr.value.to_string()),
+                Self::EVENT_ATTR_KEY_EXPIRES_AT => {
+                    expires_at = Some(Timestamp::from_nanos(attr.value.parse::<u64>()?))
+                }
+                _ => {}
+            }
+        }
+
+        match (user_id, nonce_hash, expires_at) {
+            (Some(user_id), Some(nonce_hash), Some(expires_at)) => Ok(Self {
+                user_id,
+                nonce_hash,
+                expires_at,
+            }),
+            (user_id, nonce_hash, expires_at) => {
+                let mut missing_attrs = Vec::new();
+                if user_id.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_USER_ID);
+                }
+                if nonce_hash.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_NONCE_HASH);
+                }
+                if expires_at.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_EXPIRES_AT);
+                }
+                Err(anyhow::anyhow!(
+                    "Missing required attributes in ConfirmationRequestedEvent: {:?}",
+                    missing_attrs
+                ))
+            }
+        }
+    }
+}
+
+#[cw_serde]
+pub struct ActionConfirmedEvent {
+    pub user_id: UserId,
+    pub nonce_hash: String,
+}
+
+impl ActionConfirmedEvent {
+    pub const EVENT_TYPE: &'static str = "action-confirmed";
+    pub const EVENT_ATTR_KEY_USER_ID: &'static str = "user-id";
+    pub const EVENT_ATTR_KEY_NONCE_HASH: &'static str = "nonce-hash";
+}
+
+impl From<ActionConfirmedEvent> for cosmwasm_std::Event {
+    fn from(src: ActionConfirmedEvent) -> Self {
+        cosmwasm_std::Event::new(ActionConfirmedEvent::EVENT_TYPE)
+            .add_attribute(
+                ActionConfirmedEvent::EVENT_ATTR_KEY_USER_ID,
+                src.user_id.to_string(),
+            )
+            .add_attribute(
+                ActionConfirmedEvent::EVENT_ATTR_KEY_NONCE_HASH,
+                src.nonce_hash,
+            )
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for ActionConfirmedEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let mut user_id = None;
+        let mut nonce_hash = None;
+
+        for attr in event.attributes.iter() {
+            match attr.key.as_str() {
+                Self::EVENT_ATTR_KEY_USER_ID => {
+                    user_id = Some(UserId::new_raw(attr.value.to_string()))
+                }
+                Self::EVENT_ATTR_KEY_NONCE_HASH => nonce_hash = Some(attr.value.to_string()),
+                _ => {}
+            }
+        }
+
+        match (user_id, nonce_hash) {
+            (Some(user_id), Some(nonce_hash)) => Ok(Self {
+                user_id,
+                nonce_hash,
+            }),
+            (user_id, nonce_hash) => {
+                let mut missing_attrs = Vec::new();
+                if user_id.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_USER_ID);
+                }
+                if nonce_hash.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_NONCE_HASH);
+                }
+                Err(anyhow::anyhow!(
+                    "Missing required attributes in ActionConfirmedEvent: {:?}",
+                    missing_attrs
+                ))
+            }
+        }
+    }
+}
+
+/// A confirmation came in too late, the pending action is dropped
+#[cw_serde]
+pub struct ActionExpiredEvent {
+    pub user_id: UserId,
+    pub nonce_hash: String,
+}
+
+impl ActionExpiredEvent {
+    pub const EVENT_TYPE: &'static str = "action-expired";
+    pub const EVENT_ATTR_KEY_USER_ID: &'static str = "user-id";
+    pub const EVENT_ATTR_KEY_NONCE_HASH: &'static str = "nonce-hash";
+}
+
+impl From<ActionExpiredEvent> for cosmwasm_std::Event {
+    fn from(src: ActionExpiredEvent) -> Self {
+        cosmwasm_std::Event::new(ActionExpiredEvent::EVENT_TYPE)
+            .add_attribute(
+                ActionExpiredEvent::EVENT_ATTR_KEY_USER_ID,
+                src.user_id.to_string(),
+            )
+            .add_attribute(
+                ActionExpiredEvent::EVENT_ATTR_KEY_NONCE_HASH,
+                src.nonce_hash,
+            )
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for ActionExpiredEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let mut user_id = None;
+        let mut nonce_hash = None;
+
+        for attr in event.attributes.iter() {
+            match attr.key.as_str() {
+                Self::EVENT_ATTR_KEY_USER_ID => {
+                    user_id = Some(UserId::new_raw(attr.value.to_string()))
+                }
+                Self::EVENT_ATTR_KEY_NONCE_HASH => nonce_hash = Some(attr.value.to_string()),
+                _ => {}
+            }
+        }
+
+        match (user_id, nonce_hash) {
+            (Some(user_id), Some(nonce_hash)) => Ok(Self {
+                user_id,
+                nonce_hash,
+            }),
+            (user_id, nonce_hash) => {
+                let mut missing_attrs = Vec::new();
+                if user_id.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_USER_ID);
+                }
+                if nonce_hash.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_NONCE_HASH);
+                }
+                Err(anyhow::anyhow!(
+                    "Missing required attributes in ActionExpiredEvent: {:?}",
+                    missing_attrs
+                ))
+            }
+        }
+    }
+}
+
+/// An envelope whose event id already ran, so it was skipped
+#[cw_serde]
+pub struct AlreadyHandledEvent {
+    /// Hex encoded
+    pub event_id: String,
+}
+
+impl AlreadyHandledEvent {
+    pub const EVENT_TYPE: &'static str = "already-handled";
+    pub const EVENT_ATTR_KEY_EVENT_ID: &'static str = "event-id";
+}
+
+impl From<AlreadyHandledEvent> for cosmwasm_std::Event {
+    fn from(src: AlreadyHandledEvent) -> Self {
+        cosmwasm_std::Event::new(AlreadyHandledEvent::EVENT_TYPE)
+            .add_attribute(AlreadyHandledEvent::EVENT_ATTR_KEY_EVENT_ID, src.event_id)
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for AlreadyHandledEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let event_id = event
+            .attributes
+            .iter()
+            .find(|attr| attr.key == Self::EVENT_ATTR_KEY_EVENT_ID)
+            .map(|attr| attr.value.to_string())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Missing required attributes in AlreadyHandledEvent: {:?}",
+                    [Self::EVENT_ATTR_KEY_EVENT_ID]
+                )
+            })?;
+
+        Ok(Self { event_id })
+    }
+}
@@ -0,0 +1,228 @@
+//! The EVM service handler's payload, see `packages/contracts/evm`
+//!
+//! An envelope carries `abi.encode(Command)`, the types are generated from
+//! `IEmailServiceHandler.sol`.
+
+use alloy_primitives::{Address, FixedBytes, U256};
+use alloy_sol_types::{sol, SolValue};
+use anyhow::{bail, Context, Result};
+
+use crate::{proxy::ProxyExecuteMsg, service_handler::msg::CustomExecuteMsg};
+
+// generated from the contract's own interface, so the payload can't drift from what it decodes
+sol!("../evm/src/IEmailServiceHandler.sol");
+
+pub use IEmailServiceHandler::{ActionKind, Command, CommandKind, ProxyAction};
+
+/// The envelope payload for `msg`
+pub fn encode_payload(msg: &CustomExecuteMsg) -> Result<Vec<u8>> {
+    Ok(command(msg)?.abi_encode())
+}
+
+/// Decodes an envelope payload, as the EVM service handler does
+pub fn decode_payload(payload: &[u8]) -> Result<Command> {
+    Command::abi_decode(payload).context("Invalid EVM service handler payload")
+}
+
+fn command(msg: &CustomExecuteMsg) -> Result<Command> {
+    let empty = Command {
+        kind: CommandKind::Email,
+        from: String::new(),
+        subject: String::new(),
+        userId: String::new(),
+        nonce: String::new(),
+        nonceHash: FixedBytes::ZERO,
+        commitment: FixedBytes::ZERO,
+        action: ProxyAction {
+            kind: ActionKind::ForwardToInflow,
+            recipient: Address::ZERO,
+            token: Address::ZERO,
+            amount: U256::ZERO,
+        },
+        signedAt: 0,
+    };
+
+    Ok(match msg {
+        CustomExecuteMsg::Email(email) => Command {
+            kind: CommandKind::Email,
+            from: email.from.to_string(),
+            subject: email.subject.clone(),
+            action: proxy_action(&email.proxy_execute_msg())?,
+            ..empty
+        },
+        CustomExecuteMsg::PrivateEmail(email) => Command {
+            kind: CommandKind::PrivateEmail,
+            from: email.from.to_string(),
+            commitment: email
+                .commitment
+                .parse()
+                .context("Private email commitment is not 32 hex bytes")?,
+            action: proxy_action(&email.action)?,
+            ..empty
+        },
+        CustomExecuteMsg::Confirm { from, nonce } => Command {
+            kind: CommandKind::Confirm,
+            from: from.to_string(),
+            nonce: nonce.clone(),
+            ..empty
+        },
+        CustomExecuteMsg::Link { from, user_id } => Command {
+            kind: CommandKind::Link,
+            from: from.to_string(),
+            userId: user_id.to_string(),
+            ..empty
+        },
+        CustomExecuteMsg::Unlink { from, user_id } => Command {
+            kind: CommandKind::Unlink,
+            from: from.to_string(),
+            userId: user_id.to_string(),
+            ..empty
+        },
+        CustomExecuteMsg::Timestamped { signed_at, msg } => {
+            let command = command(msg)?;
+
+            if command.signedAt != 0 {
+                bail!("Timestamped messages can't be nested")
+            }
+
+            Command {
+                signedAt: signed_at.seconds(),
+                ..command
+            }
+        }
+        CustomExecuteMsg::Confirmable { nonce_hash, msg } => {
+            let command = command(msg)?;
+
+            if command.nonceHash != FixedBytes::ZERO || command.signedAt != 0 {
+                bail!("Confirmable messages must be inside Timestamped and can't be nested")
+            }
+
+            Command {
+                nonceHash: nonce_hash
+                    .parse()
+                    .context("Nonce hash is not 32 hex bytes")?,
+                ..command
+            }
+        }
+        CustomExecuteMsg::ProvenEmail { .. } => {
+            bail!("DKIM proofs can't be verified by the EVM service handler")
+        }
+    })
+}
+
+fn proxy_action(msg: &ProxyExecuteMsg) -> Result<ProxyAction> {
+    Ok(match msg {
+        ProxyExecuteMsg::ForwardToInflow {} => ProxyAction {
+            kind: ActionKind::ForwardToInflow,
+            recipient: Address::ZERO,
+            token: Address::ZERO,
+            amount: U256::ZERO,
+        },
+        ProxyExecuteMsg::WithdrawReceiptTokens { address, coin }
+        | ProxyExecuteMsg::WithdrawFunds { address, coin } => ProxyAction {
+            kind: match msg {
+                ProxyExecuteMsg::WithdrawReceiptTokens { .. } => ActionKind::WithdrawReceiptTokens,
+                _ => ActionKind::WithdrawFunds,
+            },
+            recipient: address
+                .parse()
+                .with_context(|| format!("{address} is not an EVM address"))?,
+            token: coin
+                .denom
+                .parse()
+                .with_context(|| format!("{} is not a token address", coin.denom))?,
+            amount: U256::from_be_bytes(coin.amount.to_be_bytes()),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        service_handler::msg::{PendingAction, UserIdEmail},
+        user_registry::msg::UserId,
+    };
+    use cosmwasm_std::Timestamp;
+    use sha2::{Digest, Sha256};
+
+    const RECIPIENT: &str = "0x000000000000000000000000000000000000dead";
+    const TOKEN: &str = "0x0000000000000000000000000000000000000bee";
+
+    fn email(subject: &str) -> CustomExecuteMsg {
+        CustomExecuteMsg::Email(UserIdEmail {
+            from: UserId::new_email_address("alice@example.com"),
+            subject: subject.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_withdraw_roundtrip() {
+        let msg = email(&format!("withdraw {RECIPIENT} {TOKEN} 500000")).timestamped(1_700_000_000);
+
+        let command = decode_payload(&encode_payload(&msg).unwrap()).unwrap();
+
+        assert_eq!(command.kind, CommandKind::Email);
+        assert_eq!(command.signedAt, 1_700_000_000);
+        assert_eq!(command.action.kind, ActionKind::WithdrawFunds);
+        assert_eq!(
+            command.action.recipient,
+            RECIPIENT.parse::<Address>().unwrap()
+        );
+        assert_eq!(command.action.token, TOKEN.parse::<Address>().unwrap());
+        assert_eq!(command.action.amount, U256::from(500_000u64));
+    }
+
+    #[test]
+    fn test_link_roundtrip() {
+        let msg = CustomExecuteMsg::Link {
+            from: UserId::new_email_address("alice@example.com"),
+            user_id: UserId::new_email_address("bob@example.com"),
+        };
+
+        let command = decode_payload(&encode_payload(&msg).unwrap()).unwrap();
+
+        assert_eq!(command.kind, CommandKind::Link);
+        assert_eq!(
+            command.userId,
+            UserId::new_email_address("bob@example.com").to_string()
+        );
+        assert_eq!(command.action.kind, ActionKind::ForwardToInflow);
+    }
+
+    #[test]
+    fn test_confirmable_roundtrip() {
+        let nonce = PendingAction::nonce_from_secret(b"operator-secret", b"event-id");
+        let msg = email(&format!("withdraw {RECIPIENT} {TOKEN} 500000"))
+            .confirmable(&nonce)
+            .timestamped(1_700_000_000);
+
+        let command = decode_payload(&encode_payload(&msg).unwrap()).unwrap();
+
+        // what the contract's sha256(bytes(nonce)) gives
+        assert_eq!(
+            command.nonceHash,
+            FixedBytes::<32>::from_slice(&Sha256::digest(nonce.as_bytes()))
+        );
+        assert_eq!(command.nonce, "");
+        assert_eq!(command.signedAt, 1_700_000_000);
+        assert_eq!(command.action.amount, U256::from(500_000u64));
+    }
+
+    #[test]
+    fn test_cosmos_addresses_are_rejected() {
+        let msg = email("withdraw neutron1abc untrn 100");
+
+        encode_payload(&msg).unwrap_err();
+    }
+
+    #[test]
+    fn test_nested_timestamps_are_rejected() {
+        let msg = CustomExecuteMsg::Timestamped {
+            signed_at: Timestamp::from_seconds(1),
+            msg: Box::new(email("deposit").timestamped(2)),
+        };
+
+        encode_payload(&msg).unwrap_err();
+    }
+}
@@ -1,2 +1,4 @@
 pub mod event;
+#[cfg(feature = "evm")]
+pub mod evm;
 pub mod msg;
@@ -1,16 +1,57 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, HexBinary, Timestamp, Uint256};
+use sha2::{Digest, Sha256};
 use wavs_types::contracts::cosmwasm::service_handler::{
     ServiceHandlerExecuteMessages, ServiceHandlerQueryMessages,
 };
 
-use crate::{proxy::ProxyExecuteMsg, user_registry::msg::UserId};
+use crate::{dkim_proof::DkimProof, proxy::ProxyExecuteMsg, user_registry::msg::UserId};
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub auth: Auth,
     /// The UserRegistry contract address
     pub user_registry: String,
+    /// If set, high-value commands are held until the user confirms them by email
+    pub confirmation: Option<ConfirmationConfig>,
+    /// If set, only commitments of emails are accepted and stored, never their subjects
+    #[serde(default)]
+    pub private_emails: bool,
+    /// If set, every email must come with a DKIM proof this verifier contract accepts,
+    /// rather than resting on the operators' word alone
+    pub proof_verifier: Option<String>,
+    /// The DkimRegistry contract, required with `proof_verifier`. A proof only counts if
+    /// its key is pinned there, and not revoked
+    pub dkim_registry: Option<String>,
+    /// If set, emails signed longer ago than this are rejected,
+    /// which needs the operators to send them `Timestamped`
+    pub max_email_age_seconds: Option<u64>,
+}
+
+/// How long a link can be confirmed for, when there's no confirmation config to say
+pub const LINK_EXPIRES_AFTER_SECONDS: u64 = 24 * 60 * 60;
+
+/// How far ahead of our clock an email's timestamp may be, senders' clocks aren't exact
+pub const MAX_CLOCK_SKEW_SECONDS: u64 = 300;
+
+#[cw_serde]
+pub struct ConfirmationConfig {
+    /// Withdrawals of at least this amount (in any denom) must be confirmed
+    pub withdraw_threshold: Uint256,
+    /// How long a pending action can be confirmed for
+    pub expires_after_seconds: u64,
+}
+
+impl ConfirmationConfig {
+    pub fn requires_confirmation(&self, msg: &ProxyExecuteMsg) -> bool {
+        match msg {
+            ProxyExecuteMsg::ForwardToInflow {} => false,
+            ProxyExecuteMsg::WithdrawFunds { coin, .. }
+            | ProxyExecuteMsg::WithdrawReceiptTokens { coin, .. } => {
+                coin.amount >= self.withdraw_threshold
+            }
+        }
+    }
 }
 
 #[cw_serde]
@@ -62,6 +103,33 @@ pub enum CustomQueryMsg {
 
     #[returns(UserRegistryResponse)]
     UserRegistry {},
+
+    #[returns(ConfirmationResponse)]
+    Confirmation {},
+
+    #[returns(PendingActionResponse)]
+    PendingAction { nonce_hash: String },
+
+    #[returns(PrivacyResponse)]
+    Privacy {},
+
+    #[returns(ProofVerifierResponse)]
+    ProofVerifier {},
+
+    #[returns(MaxEmailAgeResponse)]
+    MaxEmailAge {},
+
+    #[returns(PrivateEmailsResponse)]
+    PrivateEmails {
+        /// Max number of emails to return
+        limit: Option<u32>,
+        /// Optional exclusive start of the range (last key from previous page)
+        start_after: Option<u64>,
+    },
+
+    /// Whether a WAVS event id ran
+    #[returns(HandledEventResponse)]
+    HandledEvent { event_id: HexBinary },
 }
 
 #[cw_serde]
@@ -82,6 +150,12 @@ pub struct EmailsResponse {
     pub emails: Vec<(UserIdEmail, u64)>,
 }
 
+#[cw_serde]
+pub struct PrivateEmailsResponse {
+    /// List of (email, pagination key)
+    pub emails: Vec<(PrivateEmail, u64)>,
+}
+
 #[cw_serde]
 pub struct EmailMessageOnly {
     pub subject: String,
@@ -107,6 +181,43 @@ impl UserIdEmail {
     }
 }
 
+/// What the chain keeps of an email in privacy mode
+///
+/// The subject itself stays off-chain. Anyone holding the email (and the event id)
+/// can check it against the commitment.
+#[cw_serde]
+pub struct PrivateEmail {
+    pub from: UserId,
+    /// Hex sha256 over the subject hash and the WAVS event id
+    pub commitment: String,
+    /// The command decoded from the subject
+    pub action: ProxyExecuteMsg,
+}
+
+impl PrivateEmail {
+    const COMMITMENT_DOMAIN: &'static [u8] = b"hydro-email/email-commitment";
+
+    pub fn new(email: &UserIdEmail, event_id: &[u8]) -> Self {
+        Self {
+            from: email.from.clone(),
+            commitment: Self::commitment(&email.subject, event_id),
+            action: email.proxy_execute_msg(),
+        }
+    }
+
+    /// The event id is mixed in so that common subjects can't be matched by hash alone
+    pub fn commitment(subject: &str, event_id: &[u8]) -> String {
+        let subject_hash = Sha256::digest(subject.as_bytes());
+
+        let mut hasher = Sha256::new();
+        hasher.update(Self::COMMITMENT_DOMAIN);
+        hasher.update(subject_hash);
+        hasher.update(event_id);
+
+        const_hex::encode(hasher.finalize())
+    }
+}
+
 #[cw_serde]
 #[schemaifier(mute_warnings)]
 #[serde(untagged)]
@@ -119,9 +230,104 @@ pub enum ExecuteMsg {
 pub enum CustomExecuteMsg {
     /// Got an email
     Email(UserIdEmail),
+    /// Got a "confirm <nonce>" reply for a pending action
+    Confirm { from: UserId, nonce: String },
+    /// Got a "link <email>" request, to add another address to the sender's account
+    ///
+    /// Held until `user_id` confirms it from its own address, like a high-value command.
+    Link { from: UserId, user_id: UserId },
+    /// Got an "unlink <email>" request, to remove an address from the sender's account
+    Unlink { from: UserId, user_id: UserId },
+    /// Got an email, in privacy mode
+    PrivateEmail(PrivateEmail),
+    /// Got an email, with a proof that it was DKIM-signed
+    ///
+    /// The contract parses the command itself, see `from_proven_email`.
+    ProvenEmail {
+        email: UserIdEmail,
+        proof: DkimProof,
+    },
+    /// Any of the above, with when the email was signed (the DKIM `t=` tag, or its Date)
+    ///
+    /// Required once the contract has a max email age, and can't be nested.
+    Timestamped {
+        signed_at: Timestamp,
+        msg: Box<CustomExecuteMsg>,
+    },
+    /// Any of the above, with the hash of the nonce to hold it under if it needs confirmation
+    ///
+    /// The operators derive the nonce from a secret and only email it to the user, so it's
+    /// only ever on-chain once it's used. Ignored unless the command ends up held, and
+    /// required if it does. Goes inside `Timestamped`, and can't be nested.
+    Confirmable {
+        nonce_hash: String,
+        msg: Box<CustomExecuteMsg>,
+    },
 }
 
 impl CustomExecuteMsg {
+    /// Figure out which command an email carries, based on its subject
+    pub fn from_email(email: UserIdEmail) -> Self {
+        Self::from_email_with_salt(email, &UserId::SALT)
+    }
+
+    /// Like `from_email`, for an email that came with a `DkimProof`
+    ///
+    /// The link or unlink target is the proof's `target_hash`, hashed under the same salt as
+    /// the sender. `None` if the proof has a target for any other command, or is missing one.
+    pub fn from_proven_email(email: UserIdEmail, target_hash: Option<UserId>) -> Option<Self> {
+        match (Self::from_email(email), target_hash) {
+            (Self::Link { from, .. }, Some(user_id)) => Some(Self::Link { from, user_id }),
+            (Self::Unlink { from, .. }, Some(user_id)) => Some(Self::Unlink { from, user_id }),
+            (Self::Link { .. } | Self::Unlink { .. }, None) | (_, Some(_)) => None,
+            (msg, None) => Some(msg),
+        }
+    }
+
+    /// Like `from_email`, with addresses in the subject hashed under `salt`
+    ///
+    /// Must be the salt the sender's own id was derived with.
+    pub fn from_email_with_salt(email: UserIdEmail, salt: &[u8]) -> Self {
+        if let Some(nonce) = PendingAction::parse_confirm_subject(&email.subject) {
+            return Self::Confirm {
+                from: email.from,
+                nonce,
+            };
+        }
+
+        let mut words = email.subject.split_whitespace();
+        let command = words.next().map(|word| word.to_lowercase());
+
+        // the address is hashed as written, so keep its case
+        match (command.as_deref(), words.next(), words.next()) {
+            (Some("link"), Some(address), None) => Self::Link {
+                from: email.from,
+                user_id: UserId::new_email_address_with_salt(address, salt),
+            },
+            (Some("unlink"), Some(address), None) => Self::Unlink {
+                from: email.from,
+                user_id: UserId::new_email_address_with_salt(address, salt),
+            },
+            _ => Self::Email(email),
+        }
+    }
+
+    /// Wraps the message with when its email was signed, in unix seconds
+    pub fn timestamped(self, signed_at_seconds: u64) -> Self {
+        Self::Timestamped {
+            signed_at: Timestamp::from_seconds(signed_at_seconds),
+            msg: Box::new(self),
+        }
+    }
+
+    /// Wraps the message with the hash of the nonce its confirmation request carries
+    pub fn confirmable(self, nonce: &str) -> Self {
+        Self::Confirmable {
+            nonce_hash: PendingAction::nonce_hash(nonce),
+            msg: Box::new(self),
+        }
+    }
+
     pub fn encode(&self) -> cosmwasm_std::StdResult<Vec<u8>> {
         cosmwasm_std::to_json_vec(self)
     }
@@ -141,5 +347,309 @@ pub struct UserRegistryResponse {
     pub address: Addr,
 }
 
+#[cw_serde]
+pub struct ConfirmationResponse {
+    pub config: Option<ConfirmationConfig>,
+}
+
+#[cw_serde]
+pub struct ProofVerifierResponse {
+    pub address: Option<Addr>,
+    pub dkim_registry: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct MaxEmailAgeResponse {
+    pub max_email_age_seconds: Option<u64>,
+}
+
+#[cw_serde]
+pub struct HandledEventResponse {
+    /// The height it ran at, `None` if it hasn't (yet), e.g. because its transaction failed
+    pub height: Option<u64>,
+}
+
+#[cw_serde]
+pub struct PrivacyResponse {
+    pub private_emails: bool,
+}
+
+#[cw_serde]
+pub struct PendingActionResponse {
+    pub action: Option<PendingAction>,
+}
+
+/// A command waiting for a "confirm <nonce>" reply
+#[cw_serde]
+pub struct PendingAction {
+    /// Who has to confirm it
+    pub user_id: UserId,
+    /// See [`PendingAction::nonce_hash`], the nonce itself is only known to the operators and the user
+    pub nonce_hash: String,
+    pub action: PendingCommand,
+    /// Block time after which the action can no longer be confirmed
+    pub expires_at: Timestamp,
+}
+
+/// What a pending action does once it's confirmed
+#[cw_serde]
+pub enum PendingCommand {
+    /// A high-value command for the confirming user's proxy
+    Proxy(ProxyExecuteMsg),
+    /// Adds the confirming address to `account`, which asked for it with a "link <email>"
+    Link { account: UserId },
+}
+
+impl PendingAction {
+    const NONCE_DOMAIN: &'static [u8] = b"hydro-email/confirm-nonce/v2";
+    const NONCE_LEN: usize = 8;
+
+    /// Derive the nonce from the operators' secret and a unique seed (the WAVS event id)
+    ///
+    /// Every operator derives the same one, so they agree on the payload carrying its hash,
+    /// and nobody without the secret can tell what it is.
+    pub fn nonce_from_secret(secret: &[u8], seed: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(Self::NONCE_DOMAIN);
+        hasher.update((secret.len() as u64).to_be_bytes());
+        hasher.update(secret);
+        hasher.update(seed);
+
+        const_hex::encode(&hasher.finalize()[..Self::NONCE_LEN])
+    }
+
+    /// What pending actions are stored and looked up by, the hex sha256 of the nonce
+    ///
+    /// The EVM service handler keys them by the same hash, as bytes32.
+    pub fn nonce_hash(nonce: &str) -> String {
+        const_hex::encode(Sha256::digest(nonce.as_bytes()))
+    }
+
+    /// The subject a user should reply with to confirm this action
+    pub fn confirm_subject(nonce: &str) -> String {
+        format!("confirm {nonce}")
+    }
+
+    /// Parse "confirm <nonce>", tolerating any number of reply prefixes
+    pub fn parse_confirm_subject(subject: &str) -> Option<String> {
+        let mut text = subject.trim().to_lowercase();
+
+        while let Some(rest) = text.strip_prefix("re:") {
+            text = rest.trim_start().to_string();
+        }
+
+        let nonce = text.strip_prefix("confirm ")?.trim();
+
+        if nonce.len() == Self::NONCE_LEN * 2 && nonce.chars().all(|c| c.is_ascii_hexdigit()) {
+            Some(nonce.to_string())
+        } else {
+            None
+        }
+    }
+}
+
 #[cw_serde]
 pub struct MigrateMsg {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::Coin;
+
+    fn email(subject: &str) -> UserIdEmail {
+        UserIdEmail {
+            from: UserId::new_email_address("alice@example.com"),
+            subject: subject.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_confirm_subject_roundtrip() {
+        let nonce = PendingAction::nonce_from_secret(b"operator-secret", b"event-id");
+        let subject = PendingAction::confirm_subject(&nonce);
+
+        assert_eq!(PendingAction::parse_confirm_subject(&subject), Some(nonce));
+    }
+
+    #[test]
+    fn test_confirm_subject_reply_prefixes() {
+        let nonce = PendingAction::nonce_from_secret(b"operator-secret", b"event-id");
+
+        assert_eq!(
+            PendingAction::parse_confirm_subject(&format!("Re: RE: Confirm {nonce}")),
+            Some(nonce)
+        );
+    }
+
+    #[test]
+    fn test_nonce_from_secret() {
+        let nonce = PendingAction::nonce_from_secret(b"operator-secret", b"event-id");
+
+        assert_eq!(nonce.len(), 16);
+        assert_ne!(
+            nonce,
+            PendingAction::nonce_from_secret(b"other-secret", b"event-id")
+        );
+        assert_ne!(
+            nonce,
+            PendingAction::nonce_from_secret(b"operator-secret", b"other-event-id")
+        );
+        // the secret and seed can't be traded for one another
+        assert_ne!(
+            PendingAction::nonce_from_secret(b"ab", b"c"),
+            PendingAction::nonce_from_secret(b"a", b"bc")
+        );
+    }
+
+    #[test]
+    fn test_confirmable() {
+        let nonce = PendingAction::nonce_from_secret(b"operator-secret", b"event-id");
+
+        match CustomExecuteMsg::from_email(email("deposit")).confirmable(&nonce) {
+            CustomExecuteMsg::Confirmable { nonce_hash, msg } => {
+                assert_eq!(nonce_hash, PendingAction::nonce_hash(&nonce));
+                assert_ne!(nonce_hash, nonce);
+                assert_eq!(*msg, CustomExecuteMsg::Email(email("deposit")));
+            }
+            _ => panic!("expected Confirmable"),
+        }
+    }
+
+    #[test]
+    fn test_confirm_subject_invalid_nonce() {
+        assert_eq!(PendingAction::parse_confirm_subject("confirm"), None);
+        assert_eq!(PendingAction::parse_confirm_subject("confirm xyz"), None);
+        assert_eq!(
+            PendingAction::parse_confirm_subject("confirm 0123456789abcdef00"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_from_email() {
+        let nonce = PendingAction::nonce_from_secret(b"operator-secret", b"event-id");
+
+        match CustomExecuteMsg::from_email(email(&PendingAction::confirm_subject(&nonce))) {
+            CustomExecuteMsg::Confirm {
+                from,
+                nonce: parsed,
+            } => {
+                assert_eq!(from, UserId::new_email_address("alice@example.com"));
+                assert_eq!(parsed, nonce);
+            }
+            _ => panic!("expected Confirm"),
+        }
+
+        assert!(matches!(
+            CustomExecuteMsg::from_email(email("deposit")),
+            CustomExecuteMsg::Email(_)
+        ));
+    }
+
+    #[test]
+    fn test_from_email_link() {
+        match CustomExecuteMsg::from_email(email("Link bob@Work.example.com")) {
+            CustomExecuteMsg::Link { from, user_id } => {
+                assert_eq!(from, UserId::new_email_address("alice@example.com"));
+                assert_eq!(user_id, UserId::new_email_address("bob@Work.example.com"));
+            }
+            _ => panic!("expected Link"),
+        }
+
+        assert!(matches!(
+            CustomExecuteMsg::from_email(email("unlink bob@example.com")),
+            CustomExecuteMsg::Unlink { .. }
+        ));
+
+        // anything else is a proxy command
+        assert!(matches!(
+            CustomExecuteMsg::from_email(email("link bob@example.com carol@example.com")),
+            CustomExecuteMsg::Email(_)
+        ));
+    }
+
+    #[test]
+    fn test_from_email_with_salt() {
+        let salt = b"operator-secret";
+
+        match CustomExecuteMsg::from_email_with_salt(email("link bob@example.com"), salt) {
+            CustomExecuteMsg::Link { user_id, .. } => {
+                assert_eq!(
+                    user_id,
+                    UserId::new_email_address_with_salt("bob@example.com", salt)
+                );
+                assert_ne!(user_id, UserId::new_email_address("bob@example.com"));
+            }
+            _ => panic!("expected Link"),
+        }
+    }
+
+    #[test]
+    fn test_from_proven_email() {
+        let target = UserId::new_email_address_with_salt("bob@example.com", b"operator-secret");
+
+        match CustomExecuteMsg::from_proven_email(
+            email("link bob@example.com"),
+            Some(target.clone()),
+        ) {
+            Some(CustomExecuteMsg::Link { user_id, .. }) => assert_eq!(user_id, target),
+            _ => panic!("expected Link"),
+        }
+
+        // the target comes from the proof, or not at all
+        assert_eq!(
+            CustomExecuteMsg::from_proven_email(email("unlink bob@example.com"), None),
+            None
+        );
+        assert_eq!(
+            CustomExecuteMsg::from_proven_email(email("deposit"), Some(target)),
+            None
+        );
+        assert_eq!(
+            CustomExecuteMsg::from_proven_email(email("deposit"), None),
+            Some(CustomExecuteMsg::Email(email("deposit")))
+        );
+    }
+
+    #[test]
+    fn test_private_email_commitment() {
+        let private = PrivateEmail::new(&email("withdraw neutron1abc uatom 5"), b"event-id");
+
+        assert_eq!(
+            private.commitment,
+            PrivateEmail::commitment("withdraw neutron1abc uatom 5", b"event-id")
+        );
+        assert_ne!(
+            private.commitment,
+            PrivateEmail::commitment("withdraw neutron1abc uatom 5", b"other-event-id")
+        );
+        assert_ne!(
+            private.commitment,
+            PrivateEmail::commitment("withdraw neutron1abc uatom 6", b"event-id")
+        );
+        assert!(matches!(
+            private.action,
+            ProxyExecuteMsg::WithdrawFunds { .. }
+        ));
+    }
+
+    #[test]
+    fn test_requires_confirmation() {
+        let config = ConfirmationConfig {
+            withdraw_threshold: Uint256::from(1000u128),
+            expires_after_seconds: 3600,
+        };
+
+        let withdraw = |amount: u128| ProxyExecuteMsg::WithdrawFunds {
+            address: "neutron1abc".to_string(),
+            coin: Coin {
+                denom: "uatom".to_string(),
+                amount: Uint256::from(amount),
+            },
+        };
+
+        assert!(!config.requires_confirmation(&ProxyExecuteMsg::ForwardToInflow {}));
+        assert!(!config.requires_confirmation(&withdraw(999)));
+        assert!(config.requires_confirmation(&withdraw(1000)));
+    }
+}
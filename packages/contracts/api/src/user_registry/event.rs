@@ -77,3 +77,421 @@ impl TryFrom<&cosmwasm_std::Event> for UserRegisteredEvent {
         }
     }
 }
+
+#[cw_serde]
+pub struct UserIdLinkedEvent {
+    pub account_id: u64,
+    pub user_id: UserId,
+    pub proxy_address: Addr,
+}
+
+impl UserIdLinkedEvent {
+    pub const EVENT_TYPE: &'static str = "user-id-linked";
+    pub const EVENT_ATTR_KEY_ACCOUNT_ID: &'static str = "account-id";
+    pub const EVENT_ATTR_KEY_USER_ID: &'static str = "user-id";
+    pub const EVENT_ATTR_KEY_PROXY_ADDRESS: &'static str = "proxy-address";
+}
+
+impl From<UserIdLinkedEvent> for cosmwasm_std::Event {
+    fn from(src: UserIdLinkedEvent) -> Self {
+        cosmwasm_std::Event::new(UserIdLinkedEvent::EVENT_TYPE)
+            .add_attribute(
+                UserIdLinkedEvent::EVENT_ATTR_KEY_ACCOUNT_ID,
+                src.account_id.to_string(),
+            )
+            .add_attribute(
+                UserIdLinkedEvent::EVENT_ATTR_KEY_USER_ID,
+                src.user_id.to_string(),
+            )
+            .add_attribute(
+                UserIdLinkedEvent::EVENT_ATTR_KEY_PROXY_ADDRESS,
+                src.proxy_address.to_string(),
+            )
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for UserIdLinkedEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let mut account_id = None;
+        let mut user_id = None;
+        let mut proxy_address = None;
+
+        for attr in event.attributes.iter() {
+            match attr.key.as_str() {
+                Self::EVENT_ATTR_KEY_ACCOUNT_ID => account_id = Some(attr.value.parse::<u64>()?),
+                Self::EVENT_ATTR_KEY_USER_ID => {
+                    user_id = Some(UserId::new_raw(attr.value.to_string()))
+                }
+                Self::EVENT_ATTR_KEY_PROXY_ADDRESS => {
+                    proxy_address = Some(Addr::unchecked(attr.value.to_string()))
+                }
+                _ => {}
+            }
+        }
+
+        match (account_id, user_id, proxy_address) {
+            (Some(account_id), Some(user_id), Some(proxy_address)) => Ok(Self {
+                account_id,
+                user_id,
+                proxy_address,
+            }),
+            (account_id, user_id, proxy_address) => {
+                let mut missing_attrs = Vec::new();
+                if account_id.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_ACCOUNT_ID);
+                }
+                if user_id.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_USER_ID);
+                }
+                if proxy_address.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_PROXY_ADDRESS);
+                }
+                Err(anyhow::anyhow!(
+                    "Missing required attributes in UserIdLinkedEvent: {:?}",
+                    missing_attrs
+                ))
+            }
+        }
+    }
+}
+
+#[cw_serde]
+pub struct UserIdUnlinkedEvent {
+    pub account_id: u64,
+    pub user_id: UserId,
+}
+
+impl UserIdUnlinkedEvent {
+    pub const EVENT_TYPE: &'static str = "user-id-unlinked";
+    pub const EVENT_ATTR_KEY_ACCOUNT_ID: &'static str = "account-id";
+    pub const EVENT_ATTR_KEY_USER_ID: &'static str = "user-id";
+}
+
+impl From<UserIdUnlinkedEvent> for cosmwasm_std::Event {
+    fn from(src: UserIdUnlinkedEvent) -> Self {
+        cosmwasm_std::Event::new(UserIdUnlinkedEvent::EVENT_TYPE)
+            .add_attribute(
+                UserIdUnlinkedEvent::EVENT_ATTR_KEY_ACCOUNT_ID,
+                src.account_id.to_string(),
+            )
+            .add_attribute(
+                UserIdUnlinkedEvent::EVENT_ATTR_KEY_USER_ID,
+                src.user_id.to_string(),
+            )
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for UserIdUnlinkedEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let mut account_id = None;
+        let mut user_id = None;
+
+        for attr in event.attributes.iter() {
+            match attr.key.as_str() {
+                Self::EVENT_ATTR_KEY_ACCOUNT_ID => account_id = Some(attr.value.parse::<u64>()?),
+                Self::EVENT_ATTR_KEY_USER_ID => {
+                    user_id = Some(UserId::new_raw(attr.value.to_string()))
+                }
+                _ => {}
+            }
+        }
+
+        match (account_id, user_id) {
+            (Some(account_id), Some(user_id)) => Ok(Self {
+                account_id,
+                user_id,
+            }),
+            (account_id, user_id) => {
+                let mut missing_attrs = Vec::new();
+                if account_id.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_ACCOUNT_ID);
+                }
+                if user_id.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_USER_ID);
+                }
+                Err(anyhow::anyhow!(
+                    "Missing required attributes in UserIdUnlinkedEvent: {:?}",
+                    missing_attrs
+                ))
+            }
+        }
+    }
+}
+
+#[cw_serde]
+pub struct AdminsUpdatedEvent {
+    pub added: Vec<Addr>,
+    pub removed: Vec<Addr>,
+}
+
+impl AdminsUpdatedEvent {
+    pub const EVENT_TYPE: &'static str = "admins-updated";
+    /// Comma-separated
+    pub const EVENT_ATTR_KEY_ADDED: &'static str = "added";
+    /// Comma-separated
+    pub const EVENT_ATTR_KEY_REMOVED: &'static str = "removed";
+}
+
+impl From<AdminsUpdatedEvent> for cosmwasm_std::Event {
+    fn from(src: AdminsUpdatedEvent) -> Self {
+        cosmwasm_std::Event::new(AdminsUpdatedEvent::EVENT_TYPE)
+            .add_attribute(AdminsUpdatedEvent::EVENT_ATTR_KEY_ADDED, join(&src.added))
+            .add_attribute(
+                AdminsUpdatedEvent::EVENT_ATTR_KEY_REMOVED,
+                join(&src.removed),
+            )
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for AdminsUpdatedEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let mut added = None;
+        let mut removed = None;
+
+        for attr in event.attributes.iter() {
+            match attr.key.as_str() {
+                Self::EVENT_ATTR_KEY_ADDED => added = Some(split(&attr.value, Addr::unchecked)),
+                Self::EVENT_ATTR_KEY_REMOVED => removed = Some(split(&attr.value, Addr::unchecked)),
+                _ => {}
+            }
+        }
+
+        match (added, removed) {
+            (Some(added), Some(removed)) => Ok(Self { added, removed }),
+            (added, removed) => {
+                let mut missing_attrs = Vec::new();
+                if added.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_ADDED);
+                }
+                if removed.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_REMOVED);
+                }
+                Err(anyhow::anyhow!(
+                    "Missing required attributes in AdminsUpdatedEvent: {:?}",
+                    missing_attrs
+                ))
+            }
+        }
+    }
+}
+
+#[cw_serde]
+pub struct UserProxyUpdatedEvent {
+    pub account_id: u64,
+    pub old_proxy_address: Addr,
+    pub proxy_address: Addr,
+}
+
+impl UserProxyUpdatedEvent {
+    pub const EVENT_TYPE: &'static str = "user-proxy-updated";
+    pub const EVENT_ATTR_KEY_ACCOUNT_ID: &'static str = "account-id";
+    pub const EVENT_ATTR_KEY_OLD_PROXY_ADDRESS: &'static str = "old-proxy-address";
+    pub const EVENT_ATTR_KEY_PROXY_ADDRESS: &'static str = "proxy-address";
+}
+
+impl From<UserProxyUpdatedEvent> for cosmwasm_std::Event {
+    fn from(src: UserProxyUpdatedEvent) -> Self {
+        cosmwasm_std::Event::new(UserProxyUpdatedEvent::EVENT_TYPE)
+            .add_attribute(
+                UserProxyUpdatedEvent::EVENT_ATTR_KEY_ACCOUNT_ID,
+                src.account_id.to_string(),
+            )
+            .add_attribute(
+                UserProxyUpdatedEvent::EVENT_ATTR_KEY_OLD_PROXY_ADDRESS,
+                src.old_proxy_address.to_string(),
+            )
+            .add_attribute(
+                UserProxyUpdatedEvent::EVENT_ATTR_KEY_PROXY_ADDRESS,
+                src.proxy_address.to_string(),
+            )
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for UserProxyUpdatedEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let mut account_id = None;
+        let mut old_proxy_address = None;
+        let mut proxy_address = None;
+
+        for attr in event.attributes.iter() {
+            match attr.key.as_str() {
+                Self::EVENT_ATTR_KEY_ACCOUNT_ID => account_id = Some(attr.value.parse::<u64>()?),
+                Self::EVENT_ATTR_KEY_OLD_PROXY_ADDRESS => {
+                    old_proxy_address = Some(Addr::unchecked(attr.value.to_string()))
+                }
+                Self::EVENT_ATTR_KEY_PROXY_ADDRESS => {
+                    proxy_address = Some(Addr::unchecked(attr.value.to_string()))
+                }
+                _ => {}
+            }
+        }
+
+        match (account_id, old_proxy_address, proxy_address) {
+            (Some(account_id), Some(old_proxy_address), Some(proxy_address)) => Ok(Self {
+                account_id,
+                old_proxy_address,
+                proxy_address,
+            }),
+            (account_id, old_proxy_address, proxy_address) => {
+                let mut missing_attrs = Vec::new();
+                if account_id.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_ACCOUNT_ID);
+                }
+                if old_proxy_address.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_OLD_PROXY_ADDRESS);
+                }
+                if proxy_address.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_PROXY_ADDRESS);
+                }
+                Err(anyhow::anyhow!(
+                    "Missing required attributes in UserProxyUpdatedEvent: {:?}",
+                    missing_attrs
+                ))
+            }
+        }
+    }
+}
+
+#[cw_serde]
+pub struct UserDeregisteredEvent {
+    pub account_id: u64,
+    pub user_ids: Vec<UserId>,
+    pub proxy_address: Addr,
+}
+
+impl UserDeregisteredEvent {
+    pub const EVENT_TYPE: &'static str = "user-deregistered";
+    pub const EVENT_ATTR_KEY_ACCOUNT_ID: &'static str = "account-id";
+    /// Comma-separated
+    pub const EVENT_ATTR_KEY_USER_IDS: &'static str = "user-ids";
+    pub const EVENT_ATTR_KEY_PROXY_ADDRESS: &'static str = "proxy-address";
+}
+
+impl From<UserDeregisteredEvent> for cosmwasm_std::Event {
+    fn from(src: UserDeregisteredEvent) -> Self {
+        cosmwasm_std::Event::new(UserDeregisteredEvent::EVENT_TYPE)
+            .add_attribute(
+                UserDeregisteredEvent::EVENT_ATTR_KEY_ACCOUNT_ID,
+                src.account_id.to_string(),
+            )
+            .add_attribute(
+                UserDeregisteredEvent::EVENT_ATTR_KEY_USER_IDS,
+                join(&src.user_ids),
+            )
+            .add_attribute(
+                UserDeregisteredEvent::EVENT_ATTR_KEY_PROXY_ADDRESS,
+                src.proxy_address.to_string(),
+            )
+    }
+}
+
+impl TryFrom<&cosmwasm_std::Event> for UserDeregisteredEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(event: &cosmwasm_std::Event) -> Result<Self, Self::Error> {
+        if event.ty != Self::EVENT_TYPE && event.ty != format!("wasm-{}", Self::EVENT_TYPE) {
+            return Err(anyhow::anyhow!(
+                "Expected event type {}, found {}",
+                Self::EVENT_TYPE,
+                event.ty
+            ));
+        }
+
+        let mut account_id = None;
+        let mut user_ids = None;
+        let mut proxy_address = None;
+
+        for attr in event.attributes.iter() {
+            match attr.key.as_str() {
+                Self::EVENT_ATTR_KEY_ACCOUNT_ID => account_id = Some(attr.value.parse::<u64>()?),
+                Self::EVENT_ATTR_KEY_USER_IDS => {
+                    user_ids = Some(split(&attr.value, UserId::new_raw))
+                }
+                Self::EVENT_ATTR_KEY_PROXY_ADDRESS => {
+                    proxy_address = Some(Addr::unchecked(attr.value.to_string()))
+                }
+                _ => {}
+            }
+        }
+
+        match (account_id, user_ids, proxy_address) {
+            (Some(account_id), Some(user_ids), Some(proxy_address)) => Ok(Self {
+                account_id,
+                user_ids,
+                proxy_address,
+            }),
+            (account_id, user_ids, proxy_address) => {
+                let mut missing_attrs = Vec::new();
+                if account_id.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_ACCOUNT_ID);
+                }
+                if user_ids.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_USER_IDS);
+                }
+                if proxy_address.is_none() {
+                    missing_attrs.push(Self::EVENT_ATTR_KEY_PROXY_ADDRESS);
+                }
+                Err(anyhow::anyhow!(
+                    "Missing required attributes in UserDeregisteredEvent: {:?}",
+                    missing_attrs
+                ))
+            }
+        }
+    }
+}
+
+fn join<T: std::fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn split<T>(value: &str, f: impl Fn(String) -> T) -> Vec<T> {
+    value
+        .split(',')
+        .filter(|s| !s.is_empty())
+        .map(|s| f(s.to_string()))
+        .collect()
+}
@@ -10,10 +10,39 @@ pub struct InstantiateMsg {
 
 #[cw_serde]
 pub enum ExecuteMsg {
+    /// Creates a new account, with `user_id` as its only identity
     RegisterUser {
         user_id: UserId,
         proxy_address: String,
     },
+    /// Admin only. The service handler may link and unlink identities on behalf of verified senders
+    SetServiceHandler { address: String },
+    /// Add `new_user_id` to the account `user_id` belongs to
+    ///
+    /// Sender must be an admin, or the service handler acting on a verified email from `user_id`
+    LinkUserId {
+        user_id: UserId,
+        new_user_id: UserId,
+    },
+    /// Remove `unlink_user_id` from the account `user_id` belongs to
+    ///
+    /// Same authorization as `LinkUserId`. An account always keeps at least one identity.
+    UnlinkUserId {
+        user_id: UserId,
+        unlink_user_id: UserId,
+    },
+    /// Admin only. At least one admin must remain
+    UpdateAdmins {
+        add: Vec<String>,
+        remove: Vec<String>,
+    },
+    /// Admin only. Points the whole account `user_id` belongs to at a new proxy
+    UpdateUserProxy {
+        user_id: UserId,
+        proxy_address: String,
+    },
+    /// Admin only. Removes the account `user_id` belongs to, with all of its identities
+    DeregisterUser { user_id: UserId },
 }
 
 #[cw_serde]
@@ -21,13 +50,116 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     #[returns(ProxyAddressResponse)]
     ProxyAddress { user_id: UserId },
+
+    /// The account `user_id` belongs to, with all of its identities
+    #[returns(AccountResponse)]
+    Account { user_id: UserId },
+
+    #[returns(ServiceHandlerResponse)]
+    ServiceHandler {},
+
+    #[returns(AdminsResponse)]
+    Admins {},
+
+    /// Every registered identity and the proxy it resolves to
+    #[returns(RegistrationsResponse)]
+    Registrations {
+        /// Max number of registrations to return
+        limit: Option<u32>,
+        /// Optional exclusive start of the range (last key from previous page)
+        start_after: Option<UserId>,
+    },
+
+    /// The account a proxy belongs to
+    #[returns(ProxyOwnerResponse)]
+    ProxyOwner { proxy_address: String },
+
+    #[returns(UsersResponse)]
+    Users {
+        /// Max number of user ids to return
+        limit: Option<u32>,
+        /// Optional exclusive start of the range (last key from previous page)
+        start_after: Option<UserId>,
+    },
+
+    #[returns(UserCountResponse)]
+    UserCount {},
+
+    /// Derives the `UserId` of a raw email address (e.g. "Alice <alice@example.com>"),
+    /// and the proxy it resolves to if registered
+    ///
+    /// Uses the public salt, so it won't find ids derived with a secret one
+    #[returns(UserByEmailResponse)]
+    UserByEmail { email: String },
 }
 
+#[cw_serde]
+pub struct MigrateMsg {}
+
 #[cw_serde]
 pub struct ProxyAddressResponse {
     pub address: Addr,
 }
 
+#[cw_serde]
+pub struct AccountResponse {
+    pub account_id: u64,
+    pub proxy_address: Addr,
+    pub user_ids: Vec<UserId>,
+}
+
+#[cw_serde]
+pub struct ServiceHandlerResponse {
+    pub address: Option<Addr>,
+}
+
+#[cw_serde]
+pub struct AdminsResponse {
+    pub admins: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct RegistrationsResponse {
+    pub registrations: Vec<Registration>,
+}
+
+#[cw_serde]
+pub struct Registration {
+    pub user_id: UserId,
+    pub account_id: u64,
+    pub proxy_address: Addr,
+}
+
+#[cw_serde]
+pub struct ProxyOwnerResponse {
+    pub account_id: u64,
+    pub user_ids: Vec<UserId>,
+}
+
+#[cw_serde]
+pub struct UsersResponse {
+    pub user_ids: Vec<UserId>,
+}
+
+#[cw_serde]
+pub struct UserCountResponse {
+    /// Registered identities, linked ones included
+    pub user_ids: u64,
+    pub accounts: u64,
+}
+
+#[cw_serde]
+pub struct UserByEmailResponse {
+    pub user_id: UserId,
+    pub proxy_address: Option<Addr>,
+}
+
+/// A set of linked identities sharing one proxy
+#[cw_serde]
+pub struct Account {
+    pub proxy_address: Addr,
+}
+
 #[cw_serde]
 #[derive(NewTypeKey)]
 pub struct UserId(String);
@@ -39,6 +171,8 @@ impl std::fmt::Display for UserId {
 }
 
 impl UserId {
+    /// Public default salt. Anyone can hash a list of known addresses with it,
+    /// so deployments that care should use `new_email_address_with_salt` with a secret
     pub const SALT: [u8; 12] = *b"hydro-email!";
 
     pub fn new_raw(user_id: String) -> Self {
@@ -46,14 +180,16 @@ impl UserId {
     }
 
     pub fn new_email_address(email: &str) -> Self {
-        let email = match extract_email(email) {
-            Some(e) => e,
-            None => email.to_string(),
-        };
+        Self::new_email_address_with_salt(email, &Self::SALT)
+    }
+
+    /// Hashes the address as [`normalize_email`] writes it, or the input as is if it isn't one
+    pub fn new_email_address_with_salt(email: &str, salt: &[u8]) -> Self {
+        let email = normalize_email(email).unwrap_or_else(|| email.to_string());
 
         let mut hasher = Sha256::new();
         hasher.update(&email);
-        hasher.update(Self::SALT);
+        hasher.update(salt);
 
         Self(const_hex::encode(hasher.finalize()))
     }
@@ -63,16 +199,57 @@ impl UserId {
     }
 }
 
-fn extract_email(input: &str) -> Option<String> {
+/// The one mailbox in `input`, bare and with its domain lowercased, which is case-insensitive
+///
+/// The local part is left alone: it's hashed as the sender's mail client writes it.
+/// Everything that turns an address into a user id goes through here, so the operators
+/// reading a From header and an admin registering a list of addresses agree on it.
+pub fn normalize_email(input: &str) -> Option<String> {
     let parsed = mailparse::addrparse(input).ok()?;
-    // addrparse returns a vector; usually you want the first entry
-    let addr = parsed.first()?;
-
-    match addr {
-        mailparse::MailAddr::Single(single) => Some(single.addr.clone()),
-        mailparse::MailAddr::Group(group) => {
-            // Rare, but groups exist in RFC 5322
-            group.addrs.first().map(|a| a.addr.clone())
+
+    let addr = match parsed.as_slice() {
+        [mailparse::MailAddr::Single(single)] => &single.addr,
+        _ => return None,
+    };
+
+    match addr.rsplit_once('@') {
+        Some((local, domain)) if !local.is_empty() && !domain.is_empty() => {
+            Some(format!("{local}@{}", domain.to_ascii_lowercase()))
         }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_email() {
+        assert_eq!(
+            normalize_email("Alice@Example.COM").as_deref(),
+            Some("Alice@example.com")
+        );
+        assert_eq!(
+            normalize_email("Alice <alice@example.com>").as_deref(),
+            Some("alice@example.com")
+        );
+
+        assert_eq!(normalize_email("alice"), None);
+        assert_eq!(normalize_email("alice@"), None);
+        assert_eq!(normalize_email("alice@example.com, bob@example.com"), None);
+        assert_eq!(normalize_email("friends: alice@example.com;"), None);
+    }
+
+    #[test]
+    fn test_user_id_ignores_domain_case() {
+        assert_eq!(
+            UserId::new_email_address("alice@Example.com"),
+            UserId::new_email_address("Alice <alice@example.com>")
+        );
+        assert_ne!(
+            UserId::new_email_address("Alice@example.com"),
+            UserId::new_email_address("alice@example.com")
+        );
     }
 }
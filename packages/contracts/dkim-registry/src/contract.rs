@@ -0,0 +1,102 @@
+use app_contract_api::dkim_registry::{
+    event::{DkimKeyRecordedEvent, DkimKeyRevokedEvent},
+    msg::{
+        AdminsResponse, ExecuteMsg, InstantiateMsg, KeyResponse, KeysResponse, MigrateMsg, QueryMsg,
+    },
+};
+use cosmwasm_std::{
+    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+};
+
+use crate::{error::ContractError, state};
+
+#[entry_point]
+pub fn instantiate(
+    mut deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> Result<Response, ContractError> {
+    state::init(&mut deps, &msg)?;
+
+    Ok(Response::new().add_attribute("action", "instantiate_dkim_registry"))
+}
+
+#[entry_point]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        ExecuteMsg::RecordKey {
+            domain,
+            selector,
+            key_type,
+            public_key,
+        } => {
+            state::ensure_admin(deps.storage, &info.sender)?;
+
+            let key = state::record_key(
+                deps.storage,
+                &domain,
+                &selector,
+                key_type,
+                public_key,
+                env.block.height,
+            )?;
+
+            Ok(Response::new().add_event(DkimKeyRecordedEvent {
+                domain: key.domain,
+                selector: key.selector,
+                key_hash: key.key_hash,
+                first_seen_height: key.first_seen_height,
+                last_seen_height: key.last_seen_height,
+            }))
+        }
+        ExecuteMsg::RevokeKey {
+            domain,
+            selector,
+            key_hash,
+        } => {
+            state::ensure_admin(deps.storage, &info.sender)?;
+
+            let key = state::revoke_key(deps.storage, &domain, &selector, &key_hash)?;
+
+            Ok(Response::new().add_event(DkimKeyRevokedEvent {
+                domain: key.domain,
+                selector: key.selector,
+                key_hash: key.key_hash,
+            }))
+        }
+    }
+}
+
+#[entry_point]
+pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Keys { domain, selector } => {
+            let keys = state::list_keys(deps.storage, &domain, &selector)?;
+            to_json_binary(&KeysResponse { keys })
+        }
+        QueryMsg::Key {
+            domain,
+            selector,
+            key_hash,
+        } => {
+            let key = state::get_key(deps.storage, &domain, &selector, &key_hash)?;
+            to_json_binary(&KeyResponse { key })
+        }
+        QueryMsg::Admins {} => {
+            let admins = state::get_admins(deps.storage)?;
+            to_json_binary(&AdminsResponse { admins })
+        }
+    }
+}
+
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    state::migrate(deps.storage)?;
+    Ok(Response::default())
+}
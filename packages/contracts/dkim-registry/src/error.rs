@@ -0,0 +1,31 @@
+use cosmwasm_std::StdError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("no admins provided")]
+    NoAdmins {},
+
+    #[error("unauthorized")]
+    Unauthorized {},
+
+    #[error("empty public key")]
+    EmptyPublicKey {},
+
+    #[error("key not found: {key_hash} for {selector}._domainkey.{domain}")]
+    KeyNotFound {
+        domain: String,
+        selector: String,
+        key_hash: String,
+    },
+
+    #[error("key was revoked: {key_hash} for {selector}._domainkey.{domain}")]
+    KeyRevoked {
+        domain: String,
+        selector: String,
+        key_hash: String,
+    },
+}
@@ -0,0 +1,5 @@
+pub mod contract;
+pub mod error;
+pub mod state;
+
+pub use crate::contract::{execute, instantiate, migrate, query};
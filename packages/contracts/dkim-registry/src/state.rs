@@ -0,0 +1,150 @@
+use app_contract_api::dkim_registry::msg::{DkimKey, DkimKeyType, InstantiateMsg};
+use cosmwasm_std::{Addr, Binary, DepsMut, Order, StdResult, Storage};
+use cw2::set_contract_version;
+use cw_storage_plus::{Item, Map};
+
+use crate::error::ContractError;
+
+const CONTRACT_NAME: &str = "crates.io:dkim-registry";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
+const ADMINS: Item<Vec<Addr>> = Item::new("admins");
+/// Keyed by (domain, selector, key hash), so a selector can hold several keys across rotations
+const KEYS: Map<(&str, &str, &str), DkimKey> = Map::new("keys");
+
+pub fn init(deps: &mut DepsMut, msg: &InstantiateMsg) -> Result<(), ContractError> {
+    let admins = msg
+        .admins
+        .iter()
+        .map(|addr| deps.api.addr_validate(addr))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    if admins.is_empty() {
+        return Err(ContractError::NoAdmins {});
+    }
+
+    ADMINS.save(deps.storage, &admins)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(())
+}
+
+pub fn get_admins(store: &dyn Storage) -> StdResult<Vec<Addr>> {
+    ADMINS.load(store)
+}
+
+pub fn ensure_admin(store: &dyn Storage, addr: &Addr) -> Result<(), ContractError> {
+    let admins = ADMINS.load(store)?;
+
+    if !admins.iter().any(|a| a == addr) {
+        Err(ContractError::Unauthorized {})
+    } else {
+        Ok(())
+    }
+}
+
+/// Pins a new key, or moves the `last_seen_height` of one that's already pinned
+pub fn record_key(
+    store: &mut dyn Storage,
+    domain: &str,
+    selector: &str,
+    key_type: DkimKeyType,
+    public_key: Binary,
+    height: u64,
+) -> Result<DkimKey, ContractError> {
+    if public_key.is_empty() {
+        return Err(ContractError::EmptyPublicKey {});
+    }
+
+    let domain = DkimKey::normalize(domain);
+    let selector = DkimKey::normalize(selector);
+    let key_hash = DkimKey::key_hash(&public_key);
+
+    let key = match KEYS.may_load(store, (&domain, &selector, &key_hash))? {
+        Some(key) if key.revoked => {
+            return Err(ContractError::KeyRevoked {
+                domain,
+                selector,
+                key_hash,
+            })
+        }
+        Some(key) => DkimKey {
+            last_seen_height: height.max(key.last_seen_height),
+            ..key
+        },
+        None => DkimKey {
+            domain: domain.clone(),
+            selector: selector.clone(),
+            key_type,
+            public_key,
+            key_hash: key_hash.clone(),
+            first_seen_height: height,
+            last_seen_height: height,
+            revoked: false,
+        },
+    };
+
+    KEYS.save(store, (&domain, &selector, &key_hash), &key)?;
+
+    Ok(key)
+}
+
+pub fn revoke_key(
+    store: &mut dyn Storage,
+    domain: &str,
+    selector: &str,
+    key_hash: &str,
+) -> Result<DkimKey, ContractError> {
+    let domain = DkimKey::normalize(domain);
+    let selector = DkimKey::normalize(selector);
+    let key_hash = key_hash.to_ascii_lowercase();
+
+    let mut key = KEYS
+        .may_load(store, (&domain, &selector, &key_hash))?
+        .ok_or_else(|| ContractError::KeyNotFound {
+            domain: domain.clone(),
+            selector: selector.clone(),
+            key_hash: key_hash.clone(),
+        })?;
+
+    key.revoked = true;
+    KEYS.save(store, (&domain, &selector, &key_hash), &key)?;
+
+    Ok(key)
+}
+
+pub fn get_key(
+    store: &dyn Storage,
+    domain: &str,
+    selector: &str,
+    key_hash: &str,
+) -> StdResult<Option<DkimKey>> {
+    KEYS.may_load(
+        store,
+        (
+            &DkimKey::normalize(domain),
+            &DkimKey::normalize(selector),
+            &key_hash.to_ascii_lowercase(),
+        ),
+    )
+}
+
+/// Most recently seen first, that's the one a signature is most likely to be under
+pub fn list_keys(store: &dyn Storage, domain: &str, selector: &str) -> StdResult<Vec<DkimKey>> {
+    let mut keys = KEYS
+        .prefix((&DkimKey::normalize(domain), &DkimKey::normalize(selector)))
+        .range(store, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, key)| key))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    keys.sort_by(|a, b| {
+        b.last_seen_height
+            .cmp(&a.last_seen_height)
+            .then_with(|| b.first_seen_height.cmp(&a.first_seen_height))
+    });
+
+    Ok(keys)
+}
+
+pub fn migrate(storage: &mut dyn Storage) -> StdResult<()> {
+    set_contract_version(storage, CONTRACT_NAME, CONTRACT_VERSION)
+}
@@ -0,0 +1,217 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+pragma solidity ^0.8.22;
+
+import {IEmailProxy, IEmailServiceHandler} from "./IEmailServiceHandler.sol";
+import {IWavsServiceHandler, IWavsServiceManager, IWavsServiceTypes} from "./IWavsServiceHandler.sol";
+
+/// The EVM counterpart of the CosmWasm service handler
+///
+/// Runs the same email commands, against per-user `IEmailProxy` contracts the admin registers.
+/// DKIM proofs are CosmWasm only for now.
+contract EmailServiceHandler is IEmailServiceHandler, IWavsServiceHandler {
+    /// Same allowance as the CosmWasm handler for signatures dated slightly ahead of the block
+    uint64 public constant MAX_CLOCK_SKEW_SECONDS = 300;
+    /// How long a link can be confirmed for, when there's no confirmation config to say
+    uint64 public constant LINK_EXPIRES_AFTER_SECONDS = 24 * 60 * 60;
+
+    IWavsServiceManager public immutable serviceManager;
+    address public admin;
+    /// 0 for no limit
+    uint64 public maxEmailAgeSeconds;
+    /// Withdrawals of at least this amount must be confirmed, 0 for no confirmations
+    uint256 public withdrawThreshold;
+    /// How long a pending action can be confirmed for, links fall back to a day if it's 0
+    uint64 public confirmExpiresAfterSeconds;
+
+    mapping(bytes20 => bool) public handled;
+    /// keccak256 of the user id => proxy
+    mapping(bytes32 => address) private _proxies;
+    /// By sha256 of the nonce, the nonce itself is only known to the operators and the user
+    mapping(bytes32 => PendingAction) private _pendingActions;
+
+    constructor(IWavsServiceManager serviceManager_, address admin_, uint64 maxEmailAgeSeconds_) {
+        serviceManager = serviceManager_;
+        admin = admin_;
+        maxEmailAgeSeconds = maxEmailAgeSeconds_;
+    }
+
+    modifier onlyAdmin() {
+        if (msg.sender != admin) revert Unauthorized();
+        _;
+    }
+
+    function getServiceManager() external view returns (address) {
+        return address(serviceManager);
+    }
+
+    function proxyOf(string calldata userId) external view returns (address) {
+        return _proxies[keccak256(bytes(userId))];
+    }
+
+    function pendingAction(bytes32 nonceHash) external view returns (PendingAction memory) {
+        return _pendingActions[nonceHash];
+    }
+
+    function registerUser(string calldata userId, address proxy) external onlyAdmin {
+        _proxies[keccak256(bytes(userId))] = proxy;
+    }
+
+    function setAdmin(address admin_) external onlyAdmin {
+        admin = admin_;
+    }
+
+    function setMaxEmailAgeSeconds(uint64 maxEmailAgeSeconds_) external onlyAdmin {
+        maxEmailAgeSeconds = maxEmailAgeSeconds_;
+    }
+
+    /// A `withdrawThreshold_` of 0 turns withdrawal confirmations off, links are confirmed either way
+    function setConfirmation(uint256 withdrawThreshold_, uint64 confirmExpiresAfterSeconds_) external onlyAdmin {
+        withdrawThreshold = withdrawThreshold_;
+        confirmExpiresAfterSeconds = confirmExpiresAfterSeconds_;
+    }
+
+    function handleSignedEnvelope(
+        IWavsServiceTypes.Envelope calldata envelope,
+        IWavsServiceTypes.SignatureData calldata signatureData
+    ) external {
+        serviceManager.validate(envelope, signatureData);
+
+        if (handled[envelope.eventId]) revert AlreadyHandled(envelope.eventId);
+        handled[envelope.eventId] = true;
+
+        Command memory command = abi.decode(envelope.payload, (Command));
+
+        _run(command, envelope.eventId);
+    }
+
+    function _run(Command memory command, bytes20 eventId) internal {
+        _checkEmailAge(command.signedAt);
+
+        if (command.kind == CommandKind.Email) {
+            _proxy(command.from);
+            emit Email(eventId, command.from, command.subject);
+            _act(command);
+        } else if (command.kind == CommandKind.PrivateEmail) {
+            _proxy(command.from);
+            emit PrivateEmail(eventId, command.from, command.commitment);
+            _act(command);
+        } else if (command.kind == CommandKind.Link) {
+            _proxy(command.from);
+
+            if (_proxies[keccak256(bytes(command.userId))] != address(0)) revert UserAlreadyLinked(command.userId);
+
+            // held until the linked address confirms it's theirs
+            uint64 expiresAfter = confirmExpiresAfterSeconds != 0 ? confirmExpiresAfterSeconds : LINK_EXPIRES_AFTER_SECONDS;
+            ProxyAction memory none;
+
+            _hold(command.userId, CommandKind.Link, command.from, none, expiresAfter, command.nonceHash);
+        } else if (command.kind == CommandKind.Unlink) {
+            bytes32 userId = keccak256(bytes(command.userId));
+
+            if (userId == keccak256(bytes(command.from))) revert CannotUnlinkSelf();
+            // only from an address of the same account
+            if (_proxies[userId] == address(0) || _proxies[userId] != address(_proxy(command.from))) {
+                revert UnknownUser(command.userId);
+            }
+
+            delete _proxies[userId];
+            emit UserUnlinked(command.from, command.userId);
+        } else {
+            _confirm(command.from, command.nonce);
+        }
+    }
+
+    /// Runs a proxy action right away, or holds it if it needs confirmation
+    function _act(Command memory command) internal {
+        if (!_requiresConfirmation(command.action)) {
+            _proxy(command.from).execute(command.action);
+            return;
+        }
+
+        _hold(command.from, command.kind, command.from, command.action, confirmExpiresAfterSeconds, command.nonceHash);
+    }
+
+    function _requiresConfirmation(ProxyAction memory action) internal view returns (bool) {
+        return withdrawThreshold != 0 && action.kind != ActionKind.ForwardToInflow && action.amount >= withdrawThreshold;
+    }
+
+    function _hold(
+        string memory confirmer,
+        CommandKind kind,
+        string memory account,
+        ProxyAction memory action,
+        uint64 expiresAfter,
+        bytes32 nonceHash
+    ) internal {
+        if (nonceHash == bytes32(0)) revert NonceHashRequired();
+        if (_pendingActions[nonceHash].expiresAt != 0) revert PendingActionExists(nonceHash);
+
+        uint64 expiresAt = uint64(block.timestamp) + expiresAfter;
+
+        _pendingActions[nonceHash] = PendingAction({
+            confirmer: keccak256(bytes(confirmer)),
+            kind: kind,
+            account: account,
+            action: action,
+            expiresAt: expiresAt
+        });
+
+        emit ConfirmationRequested(confirmer, nonceHash, expiresAt);
+    }
+
+    function _confirm(string memory from, string memory nonce) internal {
+        bytes32 nonceHash = sha256(bytes(nonce));
+        PendingAction memory pending = _pendingActions[nonceHash];
+
+        if (pending.expiresAt == 0) revert PendingActionNotFound(nonceHash);
+        if (pending.confirmer != keccak256(bytes(from))) revert PendingActionUserMismatch(nonceHash);
+
+        // dropped either way, an expired one doesn't revert so the deletion sticks
+        delete _pendingActions[nonceHash];
+
+        if (block.timestamp > pending.expiresAt) {
+            emit ActionExpired(from, nonceHash);
+            return;
+        }
+
+        if (pending.kind == CommandKind.Link) {
+            _link(pending.account, from);
+        } else {
+            _proxy(pending.account).execute(pending.action);
+        }
+
+        emit ActionConfirmed(from, nonceHash);
+    }
+
+    /// Adds `userId` to `account`'s proxy
+    function _link(string memory account, string memory userId) internal {
+        address proxy = address(_proxy(account));
+        bytes32 userIdHash = keccak256(bytes(userId));
+
+        if (_proxies[userIdHash] != address(0)) revert UserAlreadyLinked(userId);
+
+        _proxies[userIdHash] = proxy;
+        emit UserLinked(account, userId, proxy);
+    }
+
+    function _proxy(string memory userId) internal view returns (IEmailProxy) {
+        address proxy = _proxies[keccak256(bytes(userId))];
+
+        if (proxy == address(0)) revert UnknownUser(userId);
+
+        return IEmailProxy(proxy);
+    }
+
+    function _checkEmailAge(uint64 signedAt) internal view {
+        if (signedAt == 0) {
+            if (maxEmailAgeSeconds != 0) revert TimestampRequired();
+            return;
+        }
+
+        if (signedAt > block.timestamp + MAX_CLOCK_SKEW_SECONDS) revert EmailFromFuture(signedAt);
+
+        if (maxEmailAgeSeconds != 0 && block.timestamp > signedAt && block.timestamp - signedAt > maxEmailAgeSeconds) {
+            revert EmailTooOld(signedAt, maxEmailAgeSeconds);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+pragma solidity ^0.8.22;
+
+/// The email service handler's payload, `abi.encode(Command)`
+///
+/// `app_contract_api::service_handler::evm` generates its Rust types from this file,
+/// so it has to stay self-contained, without imports.
+interface IEmailServiceHandler {
+    enum CommandKind {
+        Email,
+        Confirm,
+        Link,
+        Unlink,
+        PrivateEmail
+    }
+
+    enum ActionKind {
+        ForwardToInflow,
+        WithdrawReceiptTokens,
+        WithdrawFunds
+    }
+
+    /// The proxy command, with the recipient and the token (the denom) as addresses
+    struct ProxyAction {
+        ActionKind kind;
+        address recipient;
+        address token;
+        uint256 amount;
+    }
+
+    /// One command from an email, fields that don't apply to its kind are left empty
+    struct Command {
+        CommandKind kind;
+        string from;
+        string subject;
+        /// The address to link or unlink
+        string userId;
+        /// What a Confirm replied with
+        string nonce;
+        /// sha256 of the nonce to hold the command under if it needs confirmation, 0 for none
+        bytes32 nonceHash;
+        /// Of a private email
+        bytes32 commitment;
+        ProxyAction action;
+        /// Unix seconds, 0 if not timestamped
+        uint64 signedAt;
+    }
+
+    /// A command held until `confirmer` confirms it by email
+    struct PendingAction {
+        /// keccak256 of the user id that has to confirm
+        bytes32 confirmer;
+        /// Email or PrivateEmail for a proxy action, or Link
+        CommandKind kind;
+        /// Whose proxy runs the action, or the user id the link adds the confirmer to
+        string account;
+        ProxyAction action;
+        uint64 expiresAt;
+    }
+
+    event Email(bytes20 indexed eventId, string from, string subject);
+    event PrivateEmail(bytes20 indexed eventId, string from, bytes32 commitment);
+    event UserLinked(string from, string userId, address proxy);
+    event UserUnlinked(string from, string userId);
+    event ConfirmationRequested(string userId, bytes32 nonceHash, uint64 expiresAt);
+    event ActionConfirmed(string userId, bytes32 nonceHash);
+    /// A confirmation came in too late, the pending action is dropped
+    event ActionExpired(string userId, bytes32 nonceHash);
+
+    error Unauthorized();
+    error AlreadyHandled(bytes20 eventId);
+    error UnknownUser(string userId);
+    error UserAlreadyLinked(string userId);
+    error CannotUnlinkSelf();
+    error PendingActionExists(bytes32 nonceHash);
+    error PendingActionNotFound(bytes32 nonceHash);
+    error PendingActionUserMismatch(bytes32 nonceHash);
+    error NonceHashRequired();
+    error TimestampRequired();
+    error EmailTooOld(uint64 signedAt, uint64 maxAgeSeconds);
+    error EmailFromFuture(uint64 signedAt);
+}
+
+/// The per-user contract that holds funds and carries out email commands, like the proxy on Cosmos
+interface IEmailProxy {
+    function execute(IEmailServiceHandler.ProxyAction calldata action) external;
+}
@@ -0,0 +1,36 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+pragma solidity ^0.8.22;
+
+/// The parts of the WAVS service manager and handler interfaces the email handler uses
+///
+/// Mirrors the WAVS middleware's, so the aggregator's `SubmitAction::Evm` can call it.
+interface IWavsServiceTypes {
+    struct Envelope {
+        bytes20 eventId;
+        bytes12 ordering;
+        bytes payload;
+    }
+
+    struct SignatureData {
+        address[] signers;
+        bytes[] signatures;
+        uint32 referenceBlock;
+    }
+}
+
+interface IWavsServiceManager {
+    /// Reverts unless enough of the operator set signed the envelope
+    function validate(
+        IWavsServiceTypes.Envelope calldata envelope,
+        IWavsServiceTypes.SignatureData calldata signatureData
+    ) external view;
+}
+
+interface IWavsServiceHandler {
+    function handleSignedEnvelope(
+        IWavsServiceTypes.Envelope calldata envelope,
+        IWavsServiceTypes.SignatureData calldata signatureData
+    ) external;
+
+    function getServiceManager() external view returns (address);
+}
@@ -0,0 +1,220 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+pragma solidity ^0.8.22;
+
+import {Test} from "forge-std/Test.sol";
+
+import {EmailServiceHandler} from "../src/EmailServiceHandler.sol";
+import {IEmailProxy, IEmailServiceHandler} from "../src/IEmailServiceHandler.sol";
+import {IWavsServiceManager, IWavsServiceTypes} from "../src/IWavsServiceHandler.sol";
+
+contract AcceptAllServiceManager is IWavsServiceManager {
+    function validate(IWavsServiceTypes.Envelope calldata, IWavsServiceTypes.SignatureData calldata)
+        external
+        pure
+    {}
+}
+
+contract RecordingProxy is IEmailProxy {
+    uint256 public executed;
+    IEmailServiceHandler.ProxyAction public last;
+
+    function execute(IEmailServiceHandler.ProxyAction calldata action) external {
+        executed++;
+        last = action;
+    }
+}
+
+contract EmailServiceHandlerTest is Test {
+    string constant ALICE = "alice-user-id";
+    string constant BOB = "bob-user-id";
+
+    EmailServiceHandler handler;
+    RecordingProxy proxy;
+    uint160 nextEventId;
+
+    function setUp() public {
+        handler = new EmailServiceHandler(new AcceptAllServiceManager(), address(this), 0);
+        proxy = new RecordingProxy();
+        handler.registerUser(ALICE, address(proxy));
+        vm.warp(1_700_000_000);
+    }
+
+    function test_email_runs_the_action() public {
+        _submit(_withdraw(ALICE, 500_000));
+
+        assertEq(proxy.executed(), 1);
+        (IEmailServiceHandler.ActionKind kind,,, uint256 amount) = proxy.last();
+        assertEq(uint8(kind), uint8(IEmailServiceHandler.ActionKind.WithdrawFunds));
+        assertEq(amount, 500_000);
+    }
+
+    function test_envelopes_are_handled_once() public {
+        IWavsServiceTypes.Envelope memory envelope = _envelope(_withdraw(ALICE, 1));
+        IWavsServiceTypes.SignatureData memory signatureData;
+
+        handler.handleSignedEnvelope(envelope, signatureData);
+
+        vm.expectRevert(abi.encodeWithSelector(IEmailServiceHandler.AlreadyHandled.selector, envelope.eventId));
+        handler.handleSignedEnvelope(envelope, signatureData);
+    }
+
+    function test_unknown_sender_reverts() public {
+        vm.expectRevert(abi.encodeWithSelector(IEmailServiceHandler.UnknownUser.selector, BOB));
+        _submit(_withdraw(BOB, 1));
+    }
+
+    function test_link_and_unlink() public {
+        IEmailServiceHandler.Command memory command = _empty(IEmailServiceHandler.CommandKind.Link, ALICE);
+        command.userId = BOB;
+
+        string memory nonce = _submit(command);
+
+        // not linked until bob confirms
+        assertEq(handler.proxyOf(BOB), address(0));
+
+        _submit(_confirmation(BOB, nonce));
+        assertEq(handler.proxyOf(BOB), address(proxy));
+
+        command.kind = IEmailServiceHandler.CommandKind.Unlink;
+
+        _submit(command);
+        assertEq(handler.proxyOf(BOB), address(0));
+    }
+
+    function test_link_confirmed_by_the_linking_user_reverts() public {
+        IEmailServiceHandler.Command memory command = _empty(IEmailServiceHandler.CommandKind.Link, ALICE);
+        command.userId = BOB;
+
+        string memory nonce = _submit(command);
+
+        vm.expectRevert(
+            abi.encodeWithSelector(IEmailServiceHandler.PendingActionUserMismatch.selector, sha256(bytes(nonce)))
+        );
+        _submit(_confirmation(ALICE, nonce));
+    }
+
+    function test_large_withdrawals_are_confirmed() public {
+        handler.setConfirmation(1000, 600);
+
+        _submit(_withdraw(ALICE, 999));
+        string memory nonce = _submit(_withdraw(ALICE, 1000));
+
+        // only the one below the threshold ran
+        assertEq(proxy.executed(), 1);
+        assertEq(handler.pendingAction(sha256(bytes(nonce))).action.amount, 1000);
+        assertEq(handler.pendingAction(sha256(bytes(nonce))).expiresAt, uint64(block.timestamp) + 600);
+
+        vm.expectRevert(
+            abi.encodeWithSelector(IEmailServiceHandler.PendingActionUserMismatch.selector, sha256(bytes(nonce)))
+        );
+        _submit(_confirmation(BOB, nonce));
+
+        _submit(_confirmation(ALICE, nonce));
+        assertEq(proxy.executed(), 2);
+        (,,, uint256 amount) = proxy.last();
+        assertEq(amount, 1000);
+        assertEq(handler.pendingAction(sha256(bytes(nonce))).expiresAt, 0);
+
+        vm.expectRevert(
+            abi.encodeWithSelector(IEmailServiceHandler.PendingActionNotFound.selector, sha256(bytes(nonce)))
+        );
+        _submit(_confirmation(ALICE, nonce));
+    }
+
+    function test_held_without_a_nonce_hash_reverts() public {
+        handler.setConfirmation(1000, 600);
+
+        IWavsServiceTypes.SignatureData memory signatureData;
+
+        vm.expectRevert(IEmailServiceHandler.NonceHashRequired.selector);
+        handler.handleSignedEnvelope(_envelope(_withdraw(ALICE, 5000)), signatureData);
+    }
+
+    function test_expired_confirmation_drops_the_action() public {
+        handler.setConfirmation(1000, 600);
+
+        string memory nonce = _submit(_withdraw(ALICE, 5000));
+
+        vm.warp(block.timestamp + 601);
+
+        vm.expectEmit();
+        emit IEmailServiceHandler.ActionExpired(ALICE, sha256(bytes(nonce)));
+        _submit(_confirmation(ALICE, nonce));
+
+        assertEq(proxy.executed(), 0);
+        assertEq(handler.pendingAction(sha256(bytes(nonce))).expiresAt, 0);
+    }
+
+    function test_max_email_age() public {
+        handler.setMaxEmailAgeSeconds(3600);
+
+        IEmailServiceHandler.Command memory command = _withdraw(ALICE, 1);
+
+        vm.expectRevert(IEmailServiceHandler.TimestampRequired.selector);
+        _submit(command);
+
+        command.signedAt = uint64(block.timestamp - 7200);
+        vm.expectRevert(
+            abi.encodeWithSelector(IEmailServiceHandler.EmailTooOld.selector, command.signedAt, uint64(3600))
+        );
+        _submit(command);
+
+        command.signedAt = uint64(block.timestamp - 60);
+        _submit(command);
+        assertEq(proxy.executed(), 1);
+    }
+
+    /// Submits the command with the hash of a fresh nonce, like the operators do, and gives the nonce
+    function _submit(IEmailServiceHandler.Command memory command) internal returns (string memory nonce) {
+        nonce = string.concat("nonce-", vm.toString(uint256(nextEventId) + 1));
+        command.nonceHash = sha256(bytes(nonce));
+
+        IWavsServiceTypes.SignatureData memory signatureData;
+
+        handler.handleSignedEnvelope(_envelope(command), signatureData);
+    }
+
+    function _envelope(IEmailServiceHandler.Command memory command)
+        internal
+        returns (IWavsServiceTypes.Envelope memory)
+    {
+        nextEventId++;
+
+        return IWavsServiceTypes.Envelope({
+            eventId: bytes20(nextEventId),
+            ordering: bytes12(0),
+            payload: abi.encode(command)
+        });
+    }
+
+    function _confirmation(string memory from, string memory nonce)
+        internal
+        pure
+        returns (IEmailServiceHandler.Command memory command)
+    {
+        command = _empty(IEmailServiceHandler.CommandKind.Confirm, from);
+        command.nonce = nonce;
+    }
+
+    function _withdraw(string memory from, uint256 amount)
+        internal
+        pure
+        returns (IEmailServiceHandler.Command memory command)
+    {
+        command = _empty(IEmailServiceHandler.CommandKind.Email, from);
+        command.subject = "withdraw";
+        command.action.kind = IEmailServiceHandler.ActionKind.WithdrawFunds;
+        command.action.recipient = address(0xdead);
+        command.action.token = address(0xbee);
+        command.action.amount = amount;
+    }
+
+    function _empty(IEmailServiceHandler.CommandKind kind, string memory from)
+        internal
+        pure
+        returns (IEmailServiceHandler.Command memory command)
+    {
+        command.kind = kind;
+        command.from = from;
+    }
+}
@@ -1,13 +1,26 @@
-use app_contract_api::service_handler::{
-    event::EmailEvent,
-    msg::{
-        AdminResponse, CustomExecuteMsg, CustomQueryMsg, EmailUserIdsResponse, EmailsFromResponse,
-        EmailsResponse, ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, UserRegistryResponse,
+use app_contract_api::{
+    dkim_proof::{DkimProof, DkimPublicInputs, VerifierQueryMsg, VerifyDkimProofResponse},
+    dkim_registry::msg::{DkimKey, KeyResponse, QueryMsg as DkimRegistryQueryMsg},
+    proxy::ProxyExecuteMsg,
+    service_handler::{
+        event::{
+            ActionConfirmedEvent, ActionExpiredEvent, AlreadyHandledEvent,
+            ConfirmationRequestedEvent, EmailEvent, PrivateEmailEvent,
+        },
+        msg::{
+            AdminResponse, ConfirmationResponse, CustomExecuteMsg, CustomQueryMsg,
+            EmailUserIdsResponse, EmailsFromResponse, EmailsResponse, ExecuteMsg,
+            HandledEventResponse, InstantiateMsg, MaxEmailAgeResponse, MigrateMsg, PendingAction,
+            PendingActionResponse, PendingCommand, PrivacyResponse, PrivateEmailsResponse,
+            ProofVerifierResponse, QueryMsg, UserIdEmail, UserRegistryResponse,
+            LINK_EXPIRES_AFTER_SECONDS, MAX_CLOCK_SKEW_SECONDS,
+        },
     },
+    user_registry::msg::{ExecuteMsg as UserRegistryExecuteMsg, UserId},
 };
 use cosmwasm_std::{
-    ensure, entry_point, to_json_binary, Binary, CosmosMsg, Deps, DepsMut, Env, MessageInfo, Reply,
-    Response, StdResult, WasmMsg,
+    ensure, entry_point, to_json_binary, Binary, CosmosMsg, Deps, DepsMut, Env, HexBinary,
+    MessageInfo, Reply, Response, StdResult, Timestamp, WasmMsg,
 };
 use wavs_types::contracts::cosmwasm::{
     service_handler::{ServiceHandlerExecuteMessages, ServiceHandlerQueryMessages},
@@ -34,7 +47,7 @@ pub fn instantiate(
 #[entry_point]
 pub fn execute(
     mut deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
@@ -42,62 +55,339 @@ pub fn execute(
         ExecuteMsg::Custom(msg) => {
             let admin = ADMIN.load(deps.storage)?;
             ensure!(info.sender == admin, ContractError::Unauthorized);
-            handle_custom_message(&mut deps, msg)
+            handle_custom_message(&mut deps, &env, msg)
         }
-        ExecuteMsg::Wavs(msg) => match msg {
-            ServiceHandlerExecuteMessages::WavsHandleSignedEnvelope {
-                envelope,
-                signature_data,
-            } => {
-                let service_manager = SERVICE_MANAGER.load(deps.storage)?;
+        ExecuteMsg::Wavs(msg) => {
+            let (msg, event_id) = open_envelope(deps.as_ref(), msg)?;
 
-                // TODO: only allow eventId to be used once
+            // e.g. a retry of a submission that did land
+            if !state::mark_event_handled(deps.storage, &event_id, env.block.height)? {
+                return Ok(already_handled(&event_id));
+            }
 
-                deps.querier.query_wasm_smart::<WavsValidateResult>(
-                    service_manager,
-                    &ServiceManagerQueryMessages::WavsValidate {
-                        envelope: envelope.clone(),
-                        signature_data: signature_data.clone(),
-                    },
-                )?;
+            handle_custom_message(&mut deps, &env, msg)
+        }
+    }
+}
+
+/// Has the service manager check the envelope's signatures, then gives its message and event id
+fn open_envelope(
+    deps: Deps,
+    msg: ServiceHandlerExecuteMessages,
+) -> Result<(CustomExecuteMsg, Vec<u8>), ContractError> {
+    match msg {
+        ServiceHandlerExecuteMessages::WavsHandleSignedEnvelope {
+            envelope,
+            signature_data,
+        } => {
+            let service_manager = SERVICE_MANAGER.load(deps.storage)?;
 
-                let envelope = envelope
-                    .decode()
-                    .map_err(|e| ContractError::AbiDecode(e.to_string()))?;
+            deps.querier.query_wasm_smart::<WavsValidateResult>(
+                service_manager,
+                &ServiceManagerQueryMessages::WavsValidate {
+                    envelope: envelope.clone(),
+                    signature_data: signature_data.clone(),
+                },
+            )?;
 
-                let msg = CustomExecuteMsg::decode(&envelope.payload)
-                    .map_err(|e| ContractError::PayloadDecode(e.to_string()))?;
+            let envelope = envelope
+                .decode()
+                .map_err(|e| ContractError::AbiDecode(e.to_string()))?;
 
-                handle_custom_message(&mut deps, msg)
-            }
-        },
+            let msg = CustomExecuteMsg::decode(&envelope.payload)
+                .map_err(|e| ContractError::PayloadDecode(e.to_string()))?;
+
+            Ok((msg, envelope.eventId.to_vec()))
+        }
     }
 }
 
+fn already_handled(event_id: &[u8]) -> Response {
+    Response::new().add_event(AlreadyHandledEvent {
+        event_id: HexBinary::from(event_id).to_hex(),
+    })
+}
+
 fn handle_custom_message(
     deps: &mut DepsMut,
+    env: &Env,
     msg: CustomExecuteMsg,
 ) -> Result<Response, ContractError> {
+    let (msg, signed_at) = match msg {
+        CustomExecuteMsg::Timestamped { signed_at, msg } => (*msg, Some(signed_at)),
+        msg => (msg, None),
+    };
+
+    let (msg, nonce_hash) = match msg {
+        CustomExecuteMsg::Confirmable { nonce_hash, msg } => (*msg, Some(nonce_hash)),
+        msg => (msg, None),
+    };
+
+    if let Some(max_age_seconds) = state::max_email_age_seconds(deps.storage)? {
+        check_email_age(env, signed_at, max_age_seconds)?;
+    }
+
+    let msg = match msg {
+        CustomExecuteMsg::ProvenEmail { email, proof } => {
+            // the subject is in the payload either way
+            ensure!(
+                !state::private_emails(deps.storage)?,
+                ContractError::PlaintextEmail
+            );
+
+            verify_dkim_proof(deps.as_ref(), email, proof)?
+        }
+        msg => {
+            ensure!(
+                state::proof_verifier(deps.storage)?.is_none(),
+                ContractError::ProofRequired
+            );
+
+            msg
+        }
+    };
+
     match msg {
         CustomExecuteMsg::Email(email) => {
+            ensure!(
+                !state::private_emails(deps.storage)?,
+                ContractError::PlaintextEmail
+            );
+
             let pagination_id = state::push_email(deps.storage, &email)?;
+            let proxy_execute_msg = email.proxy_execute_msg();
+            let user_id = email.from.clone();
 
-            let proxy_msg = CosmosMsg::Wasm(WasmMsg::Execute {
-                contract_addr: state::proxy_address(deps.as_ref(), email.from.clone())?.to_string(),
-                msg: to_json_binary(&email.proxy_execute_msg())?,
-                funds: vec![],
+            let resp = Response::new().add_event(EmailEvent {
+                email,
+                pagination_id,
             });
 
+            handle_action(deps, env, resp, user_id, proxy_execute_msg, nonce_hash)
+        }
+        CustomExecuteMsg::PrivateEmail(email) => {
+            let pagination_id = state::push_private_email(deps.storage, &email)?;
+
+            let resp = Response::new().add_event(PrivateEmailEvent {
+                from: email.from.clone(),
+                commitment: email.commitment,
+                pagination_id,
+            });
+
+            handle_action(deps, env, resp, email.from, email.action, nonce_hash)
+        }
+        CustomExecuteMsg::Confirm { from, nonce } => {
+            let nonce_hash = PendingAction::nonce_hash(&nonce);
+            let pending = state::take_pending_action(deps.storage, &nonce_hash)?;
+
+            ensure!(
+                pending.user_id == from,
+                ContractError::PendingActionUserMismatch { nonce_hash }
+            );
+
+            // not an error, or reverting would keep it around forever
+            if env.block.time > pending.expires_at {
+                return Ok(Response::new().add_event(ActionExpiredEvent {
+                    user_id: from,
+                    nonce_hash,
+                }));
+            }
+
+            let msg = match pending.action {
+                PendingCommand::Proxy(action) => proxy_msg(deps.as_ref(), from.clone(), &action)?,
+                PendingCommand::Link { account } => user_registry_msg(
+                    deps.as_ref(),
+                    &UserRegistryExecuteMsg::LinkUserId {
+                        user_id: account,
+                        new_user_id: from.clone(),
+                    },
+                )?,
+            };
+
             Ok(Response::new()
-                .add_message(proxy_msg)
-                .add_event(EmailEvent {
-                    email,
-                    pagination_id,
+                .add_message(msg)
+                .add_event(ActionConfirmedEvent {
+                    user_id: from,
+                    nonce_hash,
                 }))
         }
+        CustomExecuteMsg::Link { from, user_id } => {
+            // otherwise anyone could claim an address that isn't registered yet
+            let expires_after_seconds = state::confirmation_config(deps.storage)?
+                .map(|config| config.expires_after_seconds)
+                .unwrap_or(LINK_EXPIRES_AFTER_SECONDS);
+
+            let pending = PendingAction {
+                user_id,
+                nonce_hash: nonce_hash.ok_or(ContractError::NonceHashRequired)?,
+                action: PendingCommand::Link { account: from },
+                expires_at: env.block.time.plus_seconds(expires_after_seconds),
+            };
+
+            state::push_pending_action(deps.storage, &pending)?;
+
+            Ok(Response::new().add_event(ConfirmationRequestedEvent {
+                user_id: pending.user_id,
+                nonce_hash: pending.nonce_hash,
+                expires_at: pending.expires_at,
+            }))
+        }
+        CustomExecuteMsg::Unlink { from, user_id } => {
+            Ok(Response::new().add_message(user_registry_msg(
+                deps.as_ref(),
+                &UserRegistryExecuteMsg::UnlinkUserId {
+                    user_id: from,
+                    unlink_user_id: user_id,
+                },
+            )?))
+        }
+        CustomExecuteMsg::ProvenEmail { .. } => {
+            unreachable!("from_proven_email never returns a ProvenEmail")
+        }
+        CustomExecuteMsg::Timestamped { .. } => Err(ContractError::NestedTimestamp),
+        CustomExecuteMsg::Confirmable { .. } => Err(ContractError::NestedConfirmable),
+    }
+}
+
+fn check_email_age(
+    env: &Env,
+    signed_at: Option<Timestamp>,
+    max_age_seconds: u64,
+) -> Result<(), ContractError> {
+    let signed_at = signed_at.ok_or(ContractError::TimestampRequired)?;
+
+    ensure!(
+        env.block.time.seconds().saturating_sub(signed_at.seconds()) <= max_age_seconds,
+        ContractError::EmailTooOld {
+            signed_at,
+            max_age_seconds
+        }
+    );
+    ensure!(
+        signed_at <= env.block.time.plus_seconds(MAX_CLOCK_SKEW_SECONDS),
+        ContractError::EmailFromFuture { signed_at }
+    );
+
+    Ok(())
+}
+
+/// Checks the proof is for this email, under a key we trust, then has the verifier contract
+/// check the proof itself. Gives the command the email carries
+fn verify_dkim_proof(
+    deps: Deps,
+    email: UserIdEmail,
+    proof: DkimProof,
+) -> Result<CustomExecuteMsg, ContractError> {
+    let verifier =
+        state::proof_verifier(deps.storage)?.ok_or(ContractError::ProofVerifierNotSet)?;
+    let dkim_registry =
+        state::dkim_registry(deps.storage)?.ok_or(ContractError::DkimRegistryNotSet)?;
+
+    let inputs = &proof.public_inputs;
+
+    ensure!(
+        inputs.from_hash == email.from,
+        ContractError::ProofMismatch {
+            input: "from_hash".to_string()
+        }
+    );
+    ensure!(
+        inputs.command_hash == DkimPublicInputs::command_hash(&email.subject),
+        ContractError::ProofMismatch {
+            input: "command_hash".to_string()
+        }
+    );
+    // the circuit ties `from_domain` to `from_hash`, we tie it to the signer
+    ensure!(
+        DkimKey::normalize(&inputs.from_domain) == DkimKey::normalize(&inputs.domain),
+        ContractError::ProofMismatch {
+            input: "domain".to_string()
+        }
+    );
+
+    let key: KeyResponse = deps.querier.query_wasm_smart(
+        dkim_registry,
+        &DkimRegistryQueryMsg::Key {
+            domain: inputs.domain.clone(),
+            selector: inputs.selector.clone(),
+            key_hash: inputs.public_key_hash.clone(),
+        },
+    )?;
+
+    ensure!(
+        key.key.is_some_and(|key| !key.revoked),
+        ContractError::UntrustedDkimKey {
+            domain: inputs.domain.clone(),
+            selector: inputs.selector.clone(),
+            key_hash: inputs.public_key_hash.clone(),
+        }
+    );
+
+    let msg = CustomExecuteMsg::from_proven_email(email, inputs.target_hash.clone()).ok_or(
+        ContractError::ProofMismatch {
+            input: "target_hash".to_string(),
+        },
+    )?;
+
+    let resp: VerifyDkimProofResponse = deps
+        .querier
+        .query_wasm_smart(verifier, &VerifierQueryMsg::VerifyDkimProof { proof })?;
+
+    ensure!(resp.valid, ContractError::InvalidProof);
+
+    Ok(msg)
+}
+
+/// Executes the action right away, or holds it under `nonce_hash` if it needs confirmation
+fn handle_action(
+    deps: &mut DepsMut,
+    env: &Env,
+    resp: Response,
+    user_id: UserId,
+    action: ProxyExecuteMsg,
+    nonce_hash: Option<String>,
+) -> Result<Response, ContractError> {
+    match state::confirmation_config(deps.storage)? {
+        Some(config) if config.requires_confirmation(&action) => {
+            let pending = PendingAction {
+                user_id,
+                nonce_hash: nonce_hash.ok_or(ContractError::NonceHashRequired)?,
+                action: PendingCommand::Proxy(action),
+                expires_at: env.block.time.plus_seconds(config.expires_after_seconds),
+            };
+
+            state::push_pending_action(deps.storage, &pending)?;
+
+            Ok(resp.add_event(ConfirmationRequestedEvent {
+                user_id: pending.user_id,
+                nonce_hash: pending.nonce_hash,
+                expires_at: pending.expires_at,
+            }))
+        }
+        _ => Ok(resp.add_message(proxy_msg(deps.as_ref(), user_id, &action)?)),
     }
 }
 
+fn user_registry_msg(deps: Deps, msg: &UserRegistryExecuteMsg) -> Result<CosmosMsg, ContractError> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: state::user_registry_address(deps.storage)?.to_string(),
+        msg: to_json_binary(msg)?,
+        funds: vec![],
+    }))
+}
+
+fn proxy_msg(
+    deps: Deps,
+    user_id: UserId,
+    msg: &ProxyExecuteMsg,
+) -> Result<CosmosMsg, ContractError> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: state::proxy_address(deps, user_id)?.to_string(),
+        msg: to_json_binary(msg)?,
+        funds: vec![],
+    }))
+}
+
 #[entry_point]
 pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
@@ -127,6 +417,40 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
                 let address = state::user_registry_address(deps.storage)?;
                 to_json_binary(&UserRegistryResponse { address })
             }
+            CustomQueryMsg::Confirmation {} => {
+                let config = state::confirmation_config(deps.storage)?;
+                to_json_binary(&ConfirmationResponse { config })
+            }
+            CustomQueryMsg::PendingAction { nonce_hash } => {
+                let action = state::pending_action(deps.storage, &nonce_hash)?;
+                to_json_binary(&PendingActionResponse { action })
+            }
+            CustomQueryMsg::Privacy {} => {
+                let private_emails = state::private_emails(deps.storage)?;
+                to_json_binary(&PrivacyResponse { private_emails })
+            }
+            CustomQueryMsg::ProofVerifier {} => {
+                let address = state::proof_verifier(deps.storage)?;
+                let dkim_registry = state::dkim_registry(deps.storage)?;
+                to_json_binary(&ProofVerifierResponse {
+                    address,
+                    dkim_registry,
+                })
+            }
+            CustomQueryMsg::MaxEmailAge {} => {
+                let max_email_age_seconds = state::max_email_age_seconds(deps.storage)?;
+                to_json_binary(&MaxEmailAgeResponse {
+                    max_email_age_seconds,
+                })
+            }
+            CustomQueryMsg::PrivateEmails { limit, start_after } => {
+                let emails = state::list_private_emails(deps.storage, start_after, limit)?;
+                to_json_binary(&PrivateEmailsResponse { emails })
+            }
+            CustomQueryMsg::HandledEvent { event_id } => {
+                let height = state::handled_event_height(deps.storage, &event_id)?;
+                to_json_binary(&HandledEventResponse { height })
+            }
         },
         QueryMsg::Wavs(msg) => match msg {
             ServiceHandlerQueryMessages::WavsServiceManager {} => {
@@ -1,4 +1,6 @@
-use cosmwasm_std::{CheckedFromRatioError, DecimalRangeExceeded, OverflowError, StdError};
+use cosmwasm_std::{
+    CheckedFromRatioError, DecimalRangeExceeded, OverflowError, StdError, Timestamp,
+};
 use cw_utils::PaymentError;
 use thiserror::Error;
 
@@ -30,4 +32,62 @@ pub enum ContractError {
 
     #[error("Payload decode: {0}")]
     PayloadDecode(String),
+
+    #[error("Pending action already exists: {nonce_hash}")]
+    PendingActionExists { nonce_hash: String },
+
+    #[error("No pending action for nonce hash: {nonce_hash}")]
+    PendingActionNotFound { nonce_hash: String },
+
+    #[error("Pending action {nonce_hash} belongs to a different user")]
+    PendingActionUserMismatch { nonce_hash: String },
+
+    #[error("Command needs confirmation, but came without a nonce hash to hold it under")]
+    NonceHashRequired,
+
+    #[error("Confirmable messages must be inside Timestamped and can't be nested")]
+    NestedConfirmable,
+
+    #[error("Plaintext emails are not accepted in privacy mode")]
+    PlaintextEmail,
+
+    #[error("Privacy mode can't be used with a proof verifier, proven emails are in plaintext")]
+    PrivateEmailsWithProofs,
+
+    #[error("Emails must come with a DKIM proof")]
+    ProofRequired,
+
+    #[error("No DKIM proof verifier is configured")]
+    ProofVerifierNotSet,
+
+    #[error("A DKIM proof verifier needs a DKIM registry to check keys against")]
+    DkimRegistryNotSet,
+
+    #[error("DKIM proof does not match the email: {input}")]
+    ProofMismatch { input: String },
+
+    #[error("DKIM key {key_hash} is not pinned for {selector}._domainkey.{domain}, or is revoked")]
+    UntrustedDkimKey {
+        domain: String,
+        selector: String,
+        key_hash: String,
+    },
+
+    #[error("Invalid DKIM proof")]
+    InvalidProof,
+
+    #[error("Emails must be timestamped, there is a max email age")]
+    TimestampRequired,
+
+    #[error("Timestamped messages can't be nested")]
+    NestedTimestamp,
+
+    #[error("Email signed at {signed_at} is older than {max_age_seconds} seconds")]
+    EmailTooOld {
+        signed_at: Timestamp,
+        max_age_seconds: u64,
+    },
+
+    #[error("Email signed at {signed_at} is from the future")]
+    EmailFromFuture { signed_at: Timestamp },
 }
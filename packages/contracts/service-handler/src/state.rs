@@ -1,11 +1,16 @@
 use app_contract_api::{
-    service_handler::msg::{Auth, EmailMessageOnly, InstantiateMsg, UserIdEmail},
+    service_handler::msg::{
+        Auth, ConfirmationConfig, EmailMessageOnly, InstantiateMsg, PendingAction, PrivateEmail,
+        UserIdEmail,
+    },
     user_registry::msg::{ProxyAddressResponse, QueryMsg as UserRegistryQueryMsg, UserId},
 };
-use cosmwasm_std::{Addr, Deps, DepsMut, Order, StdResult, Storage};
+use cosmwasm_std::{ensure, Addr, Deps, DepsMut, Order, StdResult, Storage};
 use cw2::set_contract_version;
 use cw_storage_plus::{Bound, Item, Map};
 
+use crate::error::ContractError;
+
 const CONTRACT_NAME: &str = env!("CARGO_PKG_NAME");
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -19,9 +24,32 @@ const USER_REGISTRY_ADDRESS: Item<Addr> = Item::new("user-registry-address");
 const EMAILS_FROM: Map<(&str, u64), EmailMessageOnly> = Map::new("emails-from");
 const EMAILS_IN_ORDER: Map<u64, UserIdEmail> = Map::new("emails-in-order");
 const EMAIL_USER_IDS: Map<&str, ()> = Map::new("email-user-ids");
+/// Shares pagination ids with the plaintext emails
+const PRIVATE_EMAILS_IN_ORDER: Map<u64, PrivateEmail> = Map::new("private-emails-in-order");
+/// Privacy mode, plaintext emails are rejected
+const PRIVATE_EMAILS: Item<bool> = Item::new("private-emails");
+/// Only set if emails must come with a DKIM proof
+const PROOF_VERIFIER: Item<Addr> = Item::new("proof-verifier");
+/// Set along with the proof verifier, proofs must be under keys pinned here
+const DKIM_REGISTRY: Item<Addr> = Item::new("dkim-registry");
 const EMAIL_PAGINATION_ID_COUNT: Item<u64> = Item::new("email-pagination-id-count");
+/// Only set if high-value commands need confirmation
+const CONFIRMATION_CONFIG: Item<ConfirmationConfig> = Item::new("confirmation-config");
+/// Only set if old emails are rejected
+const MAX_EMAIL_AGE_SECONDS: Item<u64> = Item::new("max-email-age-seconds");
+/// Pending actions, keyed by nonce hash
+///
+/// A namespace of its own since they were keyed by the nonce itself, those are left to expire.
+const PENDING_ACTIONS: Map<&str, PendingAction> = Map::new("pending-actions-by-hash");
+/// Event ids that already ran, with the height they ran at
+const HANDLED_EVENTS: Map<&[u8], u64> = Map::new("handled-events");
+
+pub fn initialize(deps: &mut DepsMut, msg: InstantiateMsg) -> Result<(), ContractError> {
+    ensure!(
+        !(msg.private_emails && msg.proof_verifier.is_some()),
+        ContractError::PrivateEmailsWithProofs
+    );
 
-pub fn initialize(deps: &mut DepsMut, msg: InstantiateMsg) -> StdResult<()> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     // Set admin or service manager for later validation
@@ -39,9 +67,99 @@ pub fn initialize(deps: &mut DepsMut, msg: InstantiateMsg) -> StdResult<()> {
     EMAIL_PAGINATION_ID_COUNT.save(deps.storage, &0u64)?;
     USER_REGISTRY_ADDRESS.save(deps.storage, &deps.api.addr_validate(&msg.user_registry)?)?;
 
+    if let Some(confirmation) = msg.confirmation {
+        CONFIRMATION_CONFIG.save(deps.storage, &confirmation)?;
+    }
+
+    PRIVATE_EMAILS.save(deps.storage, &msg.private_emails)?;
+
+    if let Some(proof_verifier) = msg.proof_verifier {
+        let dkim_registry = msg.dkim_registry.ok_or(ContractError::DkimRegistryNotSet)?;
+
+        PROOF_VERIFIER.save(deps.storage, &deps.api.addr_validate(&proof_verifier)?)?;
+        DKIM_REGISTRY.save(deps.storage, &deps.api.addr_validate(&dkim_registry)?)?;
+    }
+
+    if let Some(max_email_age_seconds) = msg.max_email_age_seconds {
+        MAX_EMAIL_AGE_SECONDS.save(deps.storage, &max_email_age_seconds)?;
+    }
+
+    Ok(())
+}
+
+pub fn proof_verifier(store: &dyn Storage) -> StdResult<Option<Addr>> {
+    PROOF_VERIFIER.may_load(store)
+}
+
+pub fn dkim_registry(store: &dyn Storage) -> StdResult<Option<Addr>> {
+    DKIM_REGISTRY.may_load(store)
+}
+
+pub fn max_email_age_seconds(store: &dyn Storage) -> StdResult<Option<u64>> {
+    MAX_EMAIL_AGE_SECONDS.may_load(store)
+}
+
+pub fn private_emails(store: &dyn Storage) -> StdResult<bool> {
+    Ok(PRIVATE_EMAILS.may_load(store)?.unwrap_or_default())
+}
+
+pub fn confirmation_config(store: &dyn Storage) -> StdResult<Option<ConfirmationConfig>> {
+    CONFIRMATION_CONFIG.may_load(store)
+}
+
+/// `false` if it already ran, then it mustn't run again
+pub fn mark_event_handled(
+    store: &mut dyn Storage,
+    event_id: &[u8],
+    height: u64,
+) -> StdResult<bool> {
+    if HANDLED_EVENTS.has(store, event_id) {
+        return Ok(false);
+    }
+
+    HANDLED_EVENTS.save(store, event_id, &height)?;
+
+    Ok(true)
+}
+
+pub fn handled_event_height(store: &dyn Storage, event_id: &[u8]) -> StdResult<Option<u64>> {
+    HANDLED_EVENTS.may_load(store, event_id)
+}
+
+pub fn push_pending_action(
+    store: &mut dyn Storage,
+    action: &PendingAction,
+) -> Result<(), ContractError> {
+    if PENDING_ACTIONS.has(store, &action.nonce_hash) {
+        return Err(ContractError::PendingActionExists {
+            nonce_hash: action.nonce_hash.clone(),
+        });
+    }
+
+    PENDING_ACTIONS.save(store, &action.nonce_hash, action)?;
+
     Ok(())
 }
 
+pub fn pending_action(store: &dyn Storage, nonce_hash: &str) -> StdResult<Option<PendingAction>> {
+    PENDING_ACTIONS.may_load(store, nonce_hash)
+}
+
+pub fn take_pending_action(
+    store: &mut dyn Storage,
+    nonce_hash: &str,
+) -> Result<PendingAction, ContractError> {
+    let action = PENDING_ACTIONS
+        .may_load(store, nonce_hash)?
+        .ok_or_else(|| ContractError::PendingActionNotFound {
+            nonce_hash: nonce_hash.to_string(),
+        })?;
+
+    PENDING_ACTIONS.remove(store, nonce_hash);
+
+    Ok(action)
+}
+
 pub fn user_registry_address(store: &dyn Storage) -> StdResult<Addr> {
     USER_REGISTRY_ADDRESS.load(store)
 }
@@ -72,6 +190,16 @@ pub fn push_email(store: &mut dyn Storage, email: &UserIdEmail) -> StdResult<u64
     Ok(pagination_id)
 }
 
+pub fn push_private_email(store: &mut dyn Storage, email: &PrivateEmail) -> StdResult<u64> {
+    let pagination_id =
+        EMAIL_PAGINATION_ID_COUNT.update(store, |id| -> StdResult<u64> { Ok(id + 1) })?;
+
+    PRIVATE_EMAILS_IN_ORDER.save(store, pagination_id, email)?;
+    EMAIL_USER_IDS.save(store, &email.from.to_string(), &())?;
+
+    Ok(pagination_id)
+}
+
 pub fn list_email_user_ids(
     store: &dyn Storage,
     start_after: Option<&UserId>,
@@ -139,6 +267,33 @@ pub fn list_emails(
     Ok(emails)
 }
 
-pub fn migrate(storage: &mut dyn Storage) -> StdResult<()> {
-    set_contract_version(storage, CONTRACT_NAME, CONTRACT_VERSION)
+pub fn list_private_emails(
+    store: &dyn Storage,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(PrivateEmail, u64)>> {
+    let iter = PRIVATE_EMAILS_IN_ORDER.range(
+        store,
+        start_after.map(Bound::exclusive),
+        None,
+        Order::Ascending,
+    );
+
+    let take_limit = limit.unwrap_or(u32::MAX) as usize;
+
+    iter.take(take_limit)
+        .map(|item| item.map(|(id, email)| (email, id)))
+        .collect()
+}
+
+pub fn migrate(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    // instantiated before the combination was rejected, it can't process any email
+    ensure!(
+        !(private_emails(storage)? && proof_verifier(storage)?.is_some()),
+        ContractError::PrivateEmailsWithProofs
+    );
+
+    set_contract_version(storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(())
 }
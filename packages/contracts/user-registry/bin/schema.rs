@@ -1,4 +1,4 @@
-use app_contract_api::user_registry::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use app_contract_api::user_registry::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg};
 use cosmwasm_schema::write_api;
 
 fn main() {
@@ -6,5 +6,6 @@ fn main() {
         instantiate: InstantiateMsg,
         execute: ExecuteMsg,
         query: QueryMsg,
+        migrate: MigrateMsg,
     };
 }
@@ -1,6 +1,13 @@
 use app_contract_api::user_registry::{
-    event::UserRegisteredEvent,
-    msg::{ExecuteMsg, InstantiateMsg, ProxyAddressResponse, QueryMsg},
+    event::{
+        AdminsUpdatedEvent, UserDeregisteredEvent, UserIdLinkedEvent, UserIdUnlinkedEvent,
+        UserProxyUpdatedEvent, UserRegisteredEvent,
+    },
+    msg::{
+        AccountResponse, AdminsResponse, ExecuteMsg, InstantiateMsg, MigrateMsg,
+        ProxyAddressResponse, ProxyOwnerResponse, QueryMsg, RegistrationsResponse,
+        ServiceHandlerResponse, UserByEmailResponse, UserCountResponse, UserId, UsersResponse,
+    },
 };
 use cosmwasm_std::{
     entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
@@ -42,6 +49,91 @@ pub fn execute(
                 proxy_address,
             }))
         }
+        ExecuteMsg::SetServiceHandler { address } => {
+            state::ensure_admin(deps.storage, &info.sender)?;
+
+            let address = deps.api.addr_validate(&address)?;
+            state::set_service_handler(deps.storage, &address)?;
+
+            Ok(Response::new()
+                .add_attribute("action", "set_service_handler")
+                .add_attribute("address", address))
+        }
+        ExecuteMsg::LinkUserId {
+            user_id,
+            new_user_id,
+        } => {
+            state::ensure_admin_or_service_handler(deps.storage, &info.sender)?;
+
+            let (account_id, account) =
+                state::link_user_id(deps.storage, user_id, new_user_id.clone())?;
+
+            Ok(Response::new().add_event(UserIdLinkedEvent {
+                account_id,
+                user_id: new_user_id,
+                proxy_address: account.proxy_address,
+            }))
+        }
+        ExecuteMsg::UnlinkUserId {
+            user_id,
+            unlink_user_id,
+        } => {
+            state::ensure_admin_or_service_handler(deps.storage, &info.sender)?;
+
+            let account_id = state::unlink_user_id(deps.storage, user_id, unlink_user_id.clone())?;
+
+            Ok(Response::new().add_event(UserIdUnlinkedEvent {
+                account_id,
+                user_id: unlink_user_id,
+            }))
+        }
+        ExecuteMsg::UpdateAdmins { add, remove } => {
+            state::ensure_admin(deps.storage, &info.sender)?;
+
+            let add = add
+                .iter()
+                .map(|addr| deps.api.addr_validate(addr))
+                .collect::<StdResult<Vec<_>>>()?;
+            let remove = remove
+                .iter()
+                .map(|addr| deps.api.addr_validate(addr))
+                .collect::<StdResult<Vec<_>>>()?;
+
+            state::update_admins(deps.storage, add.clone(), &remove)?;
+
+            Ok(Response::new().add_event(AdminsUpdatedEvent {
+                added: add,
+                removed: remove,
+            }))
+        }
+        ExecuteMsg::UpdateUserProxy {
+            user_id,
+            proxy_address,
+        } => {
+            state::ensure_admin(deps.storage, &info.sender)?;
+
+            let proxy_address = deps.api.addr_validate(&proxy_address)?;
+            let (account_id, old_proxy_address) =
+                state::update_user_proxy(deps.storage, user_id, proxy_address.clone())?;
+
+            Ok(Response::new().add_event(UserProxyUpdatedEvent {
+                account_id,
+                old_proxy_address,
+                proxy_address,
+            }))
+        }
+        ExecuteMsg::DeregisterUser { user_id } => {
+            state::ensure_admin(deps.storage, &info.sender)?;
+
+            let (account_id, user_ids, proxy_address) =
+                state::deregister_user(deps.storage, user_id)?;
+
+            Ok(Response::new().add_event(UserDeregisteredEvent {
+                account_id,
+                user_ids,
+                proxy_address,
+            }))
+        }
     }
 }
 
@@ -52,5 +144,57 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             let address = state::get_proxy_address(deps.storage, user_id)?;
             to_json_binary(&ProxyAddressResponse { address })
         }
+        QueryMsg::Account { user_id } => {
+            let (account_id, account) = state::get_account(deps.storage, user_id)?;
+            let user_ids = state::get_account_user_ids(deps.storage, account_id)?;
+            to_json_binary(&AccountResponse {
+                account_id,
+                proxy_address: account.proxy_address,
+                user_ids,
+            })
+        }
+        QueryMsg::ServiceHandler {} => {
+            let address = state::get_service_handler(deps.storage)?;
+            to_json_binary(&ServiceHandlerResponse { address })
+        }
+        QueryMsg::Admins {} => {
+            let admins = state::get_admins(deps.storage)?;
+            to_json_binary(&AdminsResponse { admins })
+        }
+        QueryMsg::Registrations { limit, start_after } => {
+            let registrations = state::list_registrations(deps.storage, start_after, limit)?;
+            to_json_binary(&RegistrationsResponse { registrations })
+        }
+        QueryMsg::ProxyOwner { proxy_address } => {
+            let proxy_address = deps.api.addr_validate(&proxy_address)?;
+            let account_id = state::get_proxy_account(deps.storage, proxy_address)?;
+            let user_ids = state::get_account_user_ids(deps.storage, account_id)?;
+            to_json_binary(&ProxyOwnerResponse {
+                account_id,
+                user_ids,
+            })
+        }
+        QueryMsg::Users { limit, start_after } => {
+            let user_ids = state::list_user_ids(deps.storage, start_after, limit)?;
+            to_json_binary(&UsersResponse { user_ids })
+        }
+        QueryMsg::UserCount {} => {
+            let (user_ids, accounts) = state::get_counts(deps.storage)?;
+            to_json_binary(&UserCountResponse { user_ids, accounts })
+        }
+        QueryMsg::UserByEmail { email } => {
+            let user_id = UserId::new_email_address(&email);
+            let proxy_address = state::may_get_proxy_address(deps.storage, user_id.clone())?;
+            to_json_binary(&UserByEmailResponse {
+                user_id,
+                proxy_address,
+            })
+        }
     }
 }
+
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    state::migrate(deps)?;
+    Ok(Response::default())
+}
@@ -1,5 +1,5 @@
 use app_contract_api::user_registry::msg::UserId;
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Addr, StdError};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,4 +18,16 @@ pub enum ContractError {
 
     #[error("no proxy address for user: {user_id}")]
     ProxyAddressNotFound { user_id: UserId },
+
+    #[error("user id is not part of this account: {user_id}")]
+    UserIdNotInAccount { user_id: UserId },
+
+    #[error("cannot unlink the last user id of an account: {user_id}")]
+    LastUserId { user_id: UserId },
+
+    #[error("proxy already belongs to an account: {proxy_address}")]
+    ProxyAlreadyRegistered { proxy_address: Addr },
+
+    #[error("proxy does not belong to any account: {proxy_address}")]
+    ProxyNotFound { proxy_address: Addr },
 }
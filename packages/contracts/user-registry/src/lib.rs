@@ -2,4 +2,4 @@ pub mod contract;
 pub mod error;
 pub mod state;
 
-pub use crate::contract::{execute, instantiate, query};
+pub use crate::contract::{execute, instantiate, migrate, query};
@@ -1,14 +1,26 @@
-use app_contract_api::user_registry::msg::{InstantiateMsg, UserId};
-use cosmwasm_std::{Addr, DepsMut, StdResult, Storage};
+use app_contract_api::user_registry::msg::{Account, InstantiateMsg, Registration, UserId};
+use cosmwasm_std::{Addr, DepsMut, Order, StdResult, Storage};
 use cw2::set_contract_version;
-use cw_storage_plus::{Item, Map};
+use cw_storage_plus::{Bound, Item, Map};
 
 use crate::error::ContractError;
 
 const CONTRACT_NAME: &str = "crates.io:user-registry";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 const ADMINS: Item<Vec<Addr>> = Item::new("admins");
-const USER_PROXY_ADDRS: Map<UserId, Addr> = Map::new("user-proxy-addrs");
+const SERVICE_HANDLER: Item<Addr> = Item::new("service-handler");
+const ACCOUNTS: Map<u64, Account> = Map::new("accounts");
+const NEXT_ACCOUNT_ID: Item<u64> = Item::new("next-account-id");
+/// Which account each identity belongs to
+const USER_ACCOUNTS: Map<UserId, u64> = Map::new("user-accounts");
+/// All identities of an account
+const ACCOUNT_USER_IDS: Map<(u64, &str), ()> = Map::new("account-user-ids");
+/// Reverse index, proxies belong to exactly one account
+const PROXY_ACCOUNTS: Map<&Addr, u64> = Map::new("proxy-accounts");
+const USER_ID_COUNT: Item<u64> = Item::new("user-id-count");
+const ACCOUNT_COUNT: Item<u64> = Item::new("account-count");
+/// Pre-account storage, one proxy per identity. Only read when migrating
+const LEGACY_USER_PROXY_ADDRS: Map<UserId, Addr> = Map::new("user-proxy-addrs");
 
 pub fn init(deps: &mut DepsMut, msg: &InstantiateMsg) -> Result<(), ContractError> {
     let admins = msg
@@ -22,6 +34,9 @@ pub fn init(deps: &mut DepsMut, msg: &InstantiateMsg) -> Result<(), ContractErro
     }
 
     ADMINS.save(deps.storage, &admins)?;
+    NEXT_ACCOUNT_ID.save(deps.storage, &0)?;
+    USER_ID_COUNT.save(deps.storage, &0)?;
+    ACCOUNT_COUNT.save(deps.storage, &0)?;
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(())
@@ -41,25 +56,315 @@ pub fn ensure_admin(store: &dyn Storage, addr: &Addr) -> Result<(), ContractErro
     }
 }
 
+/// Applies removals after additions, so an address in both lists ends up removed
+pub fn update_admins(
+    store: &mut dyn Storage,
+    add: Vec<Addr>,
+    remove: &[Addr],
+) -> Result<(), ContractError> {
+    let mut admins = ADMINS.load(store)?;
+
+    for addr in add {
+        if !admins.contains(&addr) {
+            admins.push(addr);
+        }
+    }
+
+    admins.retain(|addr| !remove.contains(addr));
+
+    if admins.is_empty() {
+        return Err(ContractError::NoAdmins {});
+    }
+
+    ADMINS.save(store, &admins)?;
+
+    Ok(())
+}
+
+/// Admins, or the service handler (which only calls in for senders it has verified)
+pub fn ensure_admin_or_service_handler(
+    store: &dyn Storage,
+    addr: &Addr,
+) -> Result<(), ContractError> {
+    if SERVICE_HANDLER.may_load(store)?.as_ref() == Some(addr) {
+        return Ok(());
+    }
+
+    ensure_admin(store, addr)
+}
+
+pub fn set_service_handler(store: &mut dyn Storage, addr: &Addr) -> StdResult<()> {
+    SERVICE_HANDLER.save(store, addr)
+}
+
+pub fn get_service_handler(store: &dyn Storage) -> StdResult<Option<Addr>> {
+    SERVICE_HANDLER.may_load(store)
+}
+
+/// Creates a new account for `user_id`, returning its id
 pub fn register_user(
     deps: DepsMut,
     user_id: UserId,
     proxy_address: Addr,
-) -> Result<(), ContractError> {
-    if USER_PROXY_ADDRS
-        .may_load(deps.storage, user_id.clone())?
-        .is_some()
-    {
-        return Err(ContractError::UserAlreadyRegistered { user_id });
+) -> Result<u64, ContractError> {
+    ensure_unregistered(deps.storage, &user_id)?;
+
+    let account_id = create_account(deps.storage, proxy_address)?;
+    add_user_id(deps.storage, account_id, user_id)?;
+
+    Ok(account_id)
+}
+
+/// Adds `new_user_id` to the account of `user_id`, returning the account
+pub fn link_user_id(
+    store: &mut dyn Storage,
+    user_id: UserId,
+    new_user_id: UserId,
+) -> Result<(u64, Account), ContractError> {
+    let (account_id, account) = get_account(store, user_id)?;
+
+    ensure_unregistered(store, &new_user_id)?;
+    add_user_id(store, account_id, new_user_id)?;
+
+    Ok((account_id, account))
+}
+
+/// Removes `unlink_user_id` from the account of `user_id`, returning the account id
+pub fn unlink_user_id(
+    store: &mut dyn Storage,
+    user_id: UserId,
+    unlink_user_id: UserId,
+) -> Result<u64, ContractError> {
+    let (account_id, _) = get_account(store, user_id)?;
+
+    if USER_ACCOUNTS.may_load(store, unlink_user_id.clone())? != Some(account_id) {
+        return Err(ContractError::UserIdNotInAccount {
+            user_id: unlink_user_id,
+        });
+    }
+
+    if get_account_user_ids(store, account_id)?.len() <= 1 {
+        return Err(ContractError::LastUserId {
+            user_id: unlink_user_id,
+        });
+    }
+
+    remove_user_id(store, account_id, &unlink_user_id)?;
+
+    Ok(account_id)
+}
+
+/// Returns the account id and its previous proxy
+pub fn update_user_proxy(
+    store: &mut dyn Storage,
+    user_id: UserId,
+    proxy_address: Addr,
+) -> Result<(u64, Addr), ContractError> {
+    let (account_id, account) = get_account(store, user_id)?;
+
+    ensure_proxy_unused(store, &proxy_address)?;
+
+    PROXY_ACCOUNTS.remove(store, &account.proxy_address);
+    PROXY_ACCOUNTS.save(store, &proxy_address, &account_id)?;
+    ACCOUNTS.save(store, account_id, &Account { proxy_address })?;
+
+    Ok((account_id, account.proxy_address))
+}
+
+/// Removes the whole account, returning its id, identities and proxy
+pub fn deregister_user(
+    store: &mut dyn Storage,
+    user_id: UserId,
+) -> Result<(u64, Vec<UserId>, Addr), ContractError> {
+    let (account_id, account) = get_account(store, user_id)?;
+    let user_ids = get_account_user_ids(store, account_id)?;
+
+    for user_id in &user_ids {
+        remove_user_id(store, account_id, user_id)?;
+    }
+
+    ACCOUNTS.remove(store, account_id);
+    PROXY_ACCOUNTS.remove(store, &account.proxy_address);
+    ACCOUNT_COUNT.update(store, |count| -> StdResult<u64> { Ok(count - 1) })?;
+
+    Ok((account_id, user_ids, account.proxy_address))
+}
+
+pub fn list_registrations(
+    store: &dyn Storage,
+    start_after: Option<UserId>,
+    limit: Option<u32>,
+) -> StdResult<Vec<Registration>> {
+    let iter = USER_ACCOUNTS.range(
+        store,
+        start_after.map(Bound::exclusive),
+        None,
+        Order::Ascending,
+    );
+
+    let take_limit = limit.unwrap_or(u32::MAX) as usize;
+
+    iter.take(take_limit)
+        .map(|item| {
+            let (user_id, account_id) = item?;
+            let account = ACCOUNTS.load(store, account_id)?;
+
+            Ok(Registration {
+                user_id,
+                account_id,
+                proxy_address: account.proxy_address,
+            })
+        })
+        .collect()
+}
+
+/// Moves registrations made before accounts existed into accounts,
+/// linking identities that shared a proxy, and backfills the counters
+pub fn migrate(deps: DepsMut) -> Result<(), ContractError> {
+    if !NEXT_ACCOUNT_ID.exists(deps.storage) {
+        NEXT_ACCOUNT_ID.save(deps.storage, &0)?;
+    }
+
+    if !USER_ID_COUNT.exists(deps.storage) {
+        let count = USER_ACCOUNTS
+            .keys_raw(deps.storage, None, None, Order::Ascending)
+            .count() as u64;
+        USER_ID_COUNT.save(deps.storage, &count)?;
+    }
+
+    if !ACCOUNT_COUNT.exists(deps.storage) {
+        let accounts = ACCOUNTS
+            .range(deps.storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        for (account_id, account) in &accounts {
+            PROXY_ACCOUNTS.save(deps.storage, &account.proxy_address, account_id)?;
+        }
+        ACCOUNT_COUNT.save(deps.storage, &(accounts.len() as u64))?;
+    }
+
+    let legacy = LEGACY_USER_PROXY_ADDRS
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+
+    for (user_id, proxy_address) in legacy {
+        LEGACY_USER_PROXY_ADDRS.remove(deps.storage, user_id.clone());
+
+        let account_id = match PROXY_ACCOUNTS.may_load(deps.storage, &proxy_address)? {
+            Some(account_id) => account_id,
+            None => create_account(deps.storage, proxy_address)?,
+        };
+        add_user_id(deps.storage, account_id, user_id)?;
     }
 
-    USER_PROXY_ADDRS.save(deps.storage, user_id, &proxy_address)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(())
 }
 
 pub fn get_proxy_address(store: &dyn Storage, user_id: UserId) -> Result<Addr, ContractError> {
-    USER_PROXY_ADDRS
+    let (_, account) = get_account(store, user_id)?;
+
+    Ok(account.proxy_address)
+}
+
+pub fn may_get_proxy_address(store: &dyn Storage, user_id: UserId) -> StdResult<Option<Addr>> {
+    match USER_ACCOUNTS.may_load(store, user_id)? {
+        Some(account_id) => Ok(Some(ACCOUNTS.load(store, account_id)?.proxy_address)),
+        None => Ok(None),
+    }
+}
+
+pub fn get_account(store: &dyn Storage, user_id: UserId) -> Result<(u64, Account), ContractError> {
+    let account_id = USER_ACCOUNTS
         .may_load(store, user_id.clone())?
-        .ok_or_else(|| ContractError::ProxyAddressNotFound { user_id })
+        .ok_or_else(|| ContractError::ProxyAddressNotFound { user_id })?;
+
+    Ok((account_id, ACCOUNTS.load(store, account_id)?))
+}
+
+pub fn get_account_user_ids(store: &dyn Storage, account_id: u64) -> StdResult<Vec<UserId>> {
+    ACCOUNT_USER_IDS
+        .prefix(account_id)
+        .keys(store, None, None, Order::Ascending)
+        .map(|key| key.map(UserId::new_raw))
+        .collect()
+}
+
+fn ensure_unregistered(store: &dyn Storage, user_id: &UserId) -> Result<(), ContractError> {
+    if USER_ACCOUNTS.has(store, user_id.clone()) {
+        return Err(ContractError::UserAlreadyRegistered {
+            user_id: user_id.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+fn ensure_proxy_unused(store: &dyn Storage, proxy_address: &Addr) -> Result<(), ContractError> {
+    if PROXY_ACCOUNTS.has(store, proxy_address) {
+        return Err(ContractError::ProxyAlreadyRegistered {
+            proxy_address: proxy_address.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+fn create_account(store: &mut dyn Storage, proxy_address: Addr) -> Result<u64, ContractError> {
+    ensure_proxy_unused(store, &proxy_address)?;
+
+    let account_id = NEXT_ACCOUNT_ID.load(store)?;
+    NEXT_ACCOUNT_ID.save(store, &(account_id + 1))?;
+
+    PROXY_ACCOUNTS.save(store, &proxy_address, &account_id)?;
+    ACCOUNTS.save(store, account_id, &Account { proxy_address })?;
+    ACCOUNT_COUNT.update(store, |count| -> StdResult<u64> { Ok(count + 1) })?;
+
+    Ok(account_id)
+}
+
+fn add_user_id(store: &mut dyn Storage, account_id: u64, user_id: UserId) -> StdResult<()> {
+    ACCOUNT_USER_IDS.save(store, (account_id, user_id.as_str()), &())?;
+    USER_ACCOUNTS.save(store, user_id, &account_id)?;
+    USER_ID_COUNT.update(store, |count| -> StdResult<u64> { Ok(count + 1) })?;
+
+    Ok(())
+}
+
+fn remove_user_id(store: &mut dyn Storage, account_id: u64, user_id: &UserId) -> StdResult<()> {
+    ACCOUNT_USER_IDS.remove(store, (account_id, user_id.as_str()));
+    USER_ACCOUNTS.remove(store, user_id.clone());
+    USER_ID_COUNT.update(store, |count| -> StdResult<u64> { Ok(count - 1) })?;
+
+    Ok(())
+}
+
+pub fn get_proxy_account(store: &dyn Storage, proxy_address: Addr) -> Result<u64, ContractError> {
+    PROXY_ACCOUNTS
+        .may_load(store, &proxy_address)?
+        .ok_or(ContractError::ProxyNotFound { proxy_address })
+}
+
+pub fn list_user_ids(
+    store: &dyn Storage,
+    start_after: Option<UserId>,
+    limit: Option<u32>,
+) -> StdResult<Vec<UserId>> {
+    let take_limit = limit.unwrap_or(u32::MAX) as usize;
+
+    USER_ACCOUNTS
+        .keys(
+            store,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(take_limit)
+        .collect()
+}
+
+/// (identities, accounts)
+pub fn get_counts(store: &dyn Storage) -> StdResult<(u64, u64)> {
+    Ok((USER_ID_COUNT.load(store)?, ACCOUNT_COUNT.load(store)?))
 }
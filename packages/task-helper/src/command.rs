@@ -1,5 +1,6 @@
 use crate::{config::path_builds, output::OutputFormat};
 use clap::{Parser, ValueEnum};
+use cosmwasm_std::Decimal;
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use std::{path::PathBuf, str::FromStr};
@@ -34,6 +35,31 @@ pub enum CliCommand {
         #[arg(long)]
         user_registry_address: String,
 
+        /// Withdrawals of at least this amount must be confirmed by email
+        /// If not set, commands are never held for confirmation
+        #[arg(long)]
+        confirm_withdraw_threshold: Option<u128>,
+
+        /// How long a held command can be confirmed for
+        #[arg(long, default_value_t = 3600)]
+        confirm_expires_after_seconds: u64,
+
+        /// Only accept commitments of emails, never their subjects
+        #[arg(long)]
+        private_emails: bool,
+
+        /// Contract that verifies DKIM proofs. If set, every email must come with one
+        #[arg(long, requires = "dkim_registry_address")]
+        proof_verifier: Option<String>,
+
+        /// DkimRegistry contract, whose pinned keys DKIM proofs must be under
+        #[arg(long)]
+        dkim_registry_address: Option<String>,
+
+        /// Reject emails signed longer ago than this, both on-chain and in the operators
+        #[arg(long)]
+        max_email_age_seconds: Option<u64>,
+
         #[clap(flatten)]
         args: CliArgs,
     },
@@ -45,6 +71,14 @@ pub enum CliCommand {
         #[clap(flatten)]
         args: CliArgs,
     },
+    /// Instantiate the DkimRegistry contract
+    InstantiateDkimRegistry {
+        #[arg(long)]
+        code_id: u64,
+
+        #[clap(flatten)]
+        args: CliArgs,
+    },
     /// Instantiate the Proxy contract
     InstantiateProxy {
         #[arg(long)]
@@ -93,6 +127,34 @@ pub enum CliCommand {
         #[arg(long)]
         component_aggregator_submitter_cid_file: PathBuf,
 
+        /// If set, operators only verify DKIM signatures against keys pinned there
+        #[arg(long)]
+        contract_dkim_registry_instantiation_file: Option<PathBuf>,
+
+        /// JSON sender domain policy: allow and deny lists, and per-domain signature requirements
+        #[arg(long)]
+        domain_policy_file: Option<PathBuf>,
+
+        /// JSON list of service handlers to submit to, and which emails go to which
+        #[arg(long)]
+        submit_targets_file: Option<PathBuf>,
+
+        /// The operator (by its WAVS_ENV_OPERATOR_NAME) that emails senders back, so they get one copy
+        #[arg(long)]
+        notifying_operator: Option<String>,
+
+        /// Fixed gas price for submissions, in the chain's gas denom, instead of the chain config's
+        #[arg(long)]
+        gas_price: Option<Decimal>,
+
+        /// Applied on top of the gas price, e.g. 1.5 to outbid a fee market
+        #[arg(long)]
+        gas_price_multiplier: Option<Decimal>,
+
+        /// Submissions never pay more than this per unit of gas
+        #[arg(long)]
+        max_gas_price: Option<Decimal>,
+
         #[arg(long)]
         trigger_cron_schedule: String,
 
@@ -205,6 +267,11 @@ pub enum CliCommand {
         #[arg(long)]
         email_address: String,
 
+        /// Hex salt the operators derive user ids with (WAVS_ENV_USER_ID_SALT)
+        /// If not set, the public default salt is used
+        #[arg(long)]
+        user_id_salt: Option<HexBytes>,
+
         #[arg(long)]
         user_registry_address: String,
 
@@ -214,10 +281,135 @@ pub enum CliCommand {
         #[clap(flatten)]
         args: CliArgs,
     },
+    /// Instantiate a proxy for every address in a file and register them all
+    ///
+    /// Safe to re-run: addresses already registered are skipped, and proxies instantiated
+    /// by an earlier run (as recorded in the output file) are reused
+    BulkOnboard {
+        /// Either a JSON array of addresses, or a CSV with them in the first column
+        #[arg(long)]
+        emails_file: PathBuf,
+
+        /// Hex salt the operators derive user ids with (WAVS_ENV_USER_ID_SALT)
+        /// If not set, the public default salt is used
+        #[arg(long)]
+        user_id_salt: Option<HexBytes>,
+
+        #[arg(long)]
+        proxy_code_id: u64,
+
+        #[arg(long, required = true, num_args = 1.., value_delimiter = ' ')]
+        admins: Vec<String>,
+
+        #[arg(long, required = true, num_args = 1.., value_delimiter = ' ')]
+        control_centers: Vec<String>,
+
+        #[arg(long)]
+        user_registry_address: String,
+
+        /// How many proxies to instantiate, or users to register, per transaction
+        #[arg(long, default_value_t = 50)]
+        batch_size: usize,
+
+        /// Instantiate proxies again for the users an interrupted run was instantiating them for,
+        /// once you've checked on chain (their label is "Proxy <user id>") that it didn't
+        #[arg(long)]
+        retry_interrupted: bool,
+
+        #[clap(flatten)]
+        args: CliArgs,
+    },
+    /// Let the ServiceHandler link and unlink user ids on behalf of verified senders
+    ContractSetUserRegistryServiceHandler {
+        #[arg(long)]
+        user_registry_address: String,
+
+        #[arg(long)]
+        service_handler_address: String,
+
+        #[clap(flatten)]
+        args: CliArgs,
+    },
+    /// Move a registered user from the public default salt to a secret one
+    ///
+    /// Links the salted user id to the user's account, then unlinks the public one.
+    /// Run it for every registered address before the operators switch salts.
+    ContractMigrateUserIdSalt {
+        #[arg(long)]
+        email_address: String,
+
+        /// Hex salt the operators will derive user ids with (WAVS_ENV_USER_ID_SALT)
+        #[arg(long)]
+        user_id_salt: HexBytes,
+
+        #[arg(long)]
+        user_registry_address: String,
+
+        #[clap(flatten)]
+        args: CliArgs,
+    },
+    /// Pin the key a DKIM selector currently publishes
+    ///
+    /// Re-running it for a key that's already pinned marks it as seen again
+    ContractPinDkimKey {
+        #[arg(long)]
+        domain: String,
+
+        #[arg(long)]
+        selector: String,
+
+        /// The TXT record to pin, e.g. "v=DKIM1; k=rsa; p=..."
+        /// If not set, it's looked up over DNS-over-HTTPS
+        #[arg(long)]
+        record: Option<String>,
+
+        #[arg(long, default_value = "https://cloudflare-dns.com/dns-query")]
+        dns_over_https_url: Url,
+
+        #[arg(long)]
+        dkim_registry_address: String,
+
+        #[clap(flatten)]
+        args: CliArgs,
+    },
+    /// Stop verifying against a pinned DKIM key
+    ContractRevokeDkimKey {
+        #[arg(long)]
+        domain: String,
+
+        #[arg(long)]
+        selector: String,
+
+        #[arg(long)]
+        key_hash: String,
+
+        #[arg(long)]
+        dkim_registry_address: String,
+
+        #[clap(flatten)]
+        args: CliArgs,
+    },
+}
+
+impl CliCommand {
+    /// Whether the command can honor `--dry-run`, the rest would broadcast regardless
+    pub fn supports_dry_run(&self) -> bool {
+        matches!(
+            self,
+            CliCommand::InstantiateServiceHandler { .. }
+                | CliCommand::InstantiateUserRegistry { .. }
+                | CliCommand::InstantiateDkimRegistry { .. }
+                | CliCommand::InstantiateProxy { .. }
+                | CliCommand::ContractRegisterUser { .. }
+                | CliCommand::ContractSetUserRegistryServiceHandler { .. }
+                | CliCommand::ContractMigrateUserIdSalt { .. }
+                | CliCommand::ContractPinDkimKey { .. }
+                | CliCommand::ContractRevokeDkimKey { .. }
+        )
+    }
 }
 
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub struct HexBytes(Vec<u8>);
 
 impl AsRef<[u8]> for HexBytes {
@@ -250,6 +442,10 @@ pub struct CliArgs {
     /// Output format for any generated files
     #[clap(long, value_enum, default_value_t = OutputFormat::Json)]
     pub output_format: OutputFormat,
+
+    /// Simulate the transaction and print its gas and events, instead of broadcasting it
+    #[clap(long)]
+    pub dry_run: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, ValueEnum)]
@@ -259,6 +455,7 @@ pub enum ContractKind {
     ServiceHandler,
     Proxy,
     UserRegistry,
+    DkimRegistry,
 }
 
 impl std::fmt::Display for ContractKind {
@@ -273,6 +470,7 @@ impl ContractKind {
             Self::ServiceHandler => "service-handler",
             Self::Proxy => "proxy",
             Self::UserRegistry => "user-registry",
+            Self::DkimRegistry => "dkim-registry",
         }
     }
     pub async fn wasm_bytes(&self) -> Vec<u8> {
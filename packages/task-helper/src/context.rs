@@ -32,6 +32,7 @@ impl CliContext {
             CliCommand::InstantiateServiceHandler { args, .. } => args,
             CliCommand::InstantiateProxy { args, .. } => args,
             CliCommand::InstantiateUserRegistry { args, .. } => args,
+            CliCommand::InstantiateDkimRegistry { args, .. } => args,
             CliCommand::UploadComponent { args, .. } => args,
             CliCommand::UploadService { args, .. } => args,
             CliCommand::AssertAccountExists { args, .. } => args,
@@ -42,6 +43,11 @@ impl CliContext {
             CliCommand::QueryProxyConfig { args, .. } => args,
             CliCommand::QueryProxyState { args, .. } => args,
             CliCommand::ContractRegisterUser { args, .. } => args,
+            CliCommand::BulkOnboard { args, .. } => args,
+            CliCommand::ContractSetUserRegistryServiceHandler { args, .. } => args,
+            CliCommand::ContractMigrateUserIdSalt { args, .. } => args,
+            CliCommand::ContractPinDkimKey { args, .. } => args,
+            CliCommand::ContractRevokeDkimKey { args, .. } => args,
         }
     }
 
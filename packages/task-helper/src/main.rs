@@ -4,15 +4,24 @@ mod context;
 mod ipfs;
 mod output;
 
-use std::process::exit;
+use std::{collections::HashMap, path::Path, process::exit};
 
-use app_client::contracts::user_registry::UserRegistryContract;
-use app_contract_api::user_registry::msg::UserId;
+use app_client::{
+    contracts::{dkim_registry::DkimRegistryContract, user_registry::UserRegistryContract},
+    executor::{climb_simulate, AnyMsg, Simulation},
+};
+use app_contract_api::{
+    dkim_registry::msg::{DkimKey, ExecuteMsg as DkimRegistryExecuteMsg},
+    user_registry::msg::{normalize_email, ExecuteMsg as UserRegistryExecuteMsg, UserId},
+};
 use app_utils::{faucet, tracing::tracing_init};
 use cosmwasm_std::Uint256;
-use layer_climb::prelude::EvmAddr;
+use layer_climb::{
+    prelude::{proto_into_any, EvmAddr},
+    signing::SigningClient,
+};
 use reqwest::Url;
-use serde::{de::DeserializeOwned, Deserialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use wavs_types::{
     ComponentSource, GetSignerRequest, Service, ServiceManager, SignatureKind, SignerResponse,
     Submit, Trigger, Workflow,
@@ -24,8 +33,8 @@ use crate::{
     context::CliContext,
     ipfs::IpfsFile,
     output::{
-        OutputComponentUpload, OutputContractInstantiate, OutputContractUpload,
-        OutputOperatorSetSigningKey, OutputServiceUpload,
+        OutputBulkOnboard, OutputComponentUpload, OutputContractInstantiate, OutputContractUpload,
+        OutputOnboardedUser, OutputOperatorSetSigningKey, OutputServiceUpload,
     },
 };
 
@@ -38,6 +47,11 @@ async fn main() {
 
     let ctx = CliContext::new().await;
 
+    if ctx.args().dry_run && !ctx.command.supports_dry_run() {
+        eprintln!("--dry-run isn't supported by this command");
+        exit(1);
+    }
+
     match ctx.command.clone() {
         CliCommand::OperatorSetSigningKey {
             service_manager_address,
@@ -170,6 +184,12 @@ async fn main() {
             args,
             code_id,
             user_registry_address,
+            confirm_withdraw_threshold,
+            confirm_expires_after_seconds,
+            private_emails,
+            proof_verifier,
+            dkim_registry_address,
+            max_email_age_seconds,
         } => {
             let client = ctx.signing_client().await.unwrap();
 
@@ -194,8 +214,29 @@ async fn main() {
             let instantiate_msg = app_contract_api::service_handler::msg::InstantiateMsg {
                 auth,
                 user_registry: user_registry_address,
+                confirmation: confirm_withdraw_threshold.map(|threshold| {
+                    app_contract_api::service_handler::msg::ConfirmationConfig {
+                        withdraw_threshold: threshold.into(),
+                        expires_after_seconds: confirm_expires_after_seconds,
+                    }
+                }),
+                private_emails,
+                proof_verifier: match proof_verifier {
+                    Some(addr) => Some(ctx.parse_address(&addr).await.unwrap().to_string()),
+                    None => None,
+                },
+                dkim_registry: match dkim_registry_address {
+                    Some(addr) => Some(ctx.parse_address(&addr).await.unwrap().to_string()),
+                    None => None,
+                },
+                max_email_age_seconds,
             };
 
+            if args.dry_run {
+                dry_run_instantiate(&client, code_id, "Service Handler", &instantiate_msg).await;
+                return;
+            }
+
             let (contract_addr, tx_resp) = client
                 .contract_instantiate(
                     None,
@@ -226,6 +267,11 @@ async fn main() {
                 admins: vec![ctx.wallet_addr().await.unwrap().to_string()],
             };
 
+            if args.dry_run {
+                dry_run_instantiate(&client, code_id, "User Registry", &instantiate_msg).await;
+                return;
+            }
+
             let (contract_addr, tx_resp) = client
                 .contract_instantiate(
                     None,
@@ -249,6 +295,41 @@ async fn main() {
                 .await
                 .unwrap();
         }
+        CliCommand::InstantiateDkimRegistry { args, code_id } => {
+            let client = ctx.signing_client().await.unwrap();
+
+            let instantiate_msg = app_contract_api::dkim_registry::msg::InstantiateMsg {
+                admins: vec![ctx.wallet_addr().await.unwrap().to_string()],
+            };
+
+            if args.dry_run {
+                dry_run_instantiate(&client, code_id, "DKIM Registry", &instantiate_msg).await;
+                return;
+            }
+
+            let (contract_addr, tx_resp) = client
+                .contract_instantiate(
+                    None,
+                    code_id,
+                    "DKIM Registry",
+                    &instantiate_msg,
+                    vec![],
+                    None,
+                )
+                .await
+                .unwrap();
+
+            println!("Instantiated DKIM Registry contract at address: {contract_addr}");
+
+            args.output()
+                .write(OutputContractInstantiate {
+                    kind: ContractKind::DkimRegistry,
+                    address: contract_addr.to_string(),
+                    tx_hash: tx_resp.txhash,
+                })
+                .await
+                .unwrap();
+        }
 
         CliCommand::InstantiateProxy {
             admins,
@@ -263,6 +344,11 @@ async fn main() {
                 control_centers,
             };
 
+            if args.dry_run {
+                dry_run_instantiate(&client, code_id, "Proxy", &instantiate_msg).await;
+                return;
+            }
+
             let (contract_addr, tx_resp) = client
                 .contract_instantiate(None, code_id, "Proxy", &instantiate_msg, vec![], None)
                 .await
@@ -360,6 +446,13 @@ async fn main() {
             contract_service_handler_instantiation_file,
             component_operator_email_reader_cid_file,
             component_aggregator_submitter_cid_file,
+            contract_dkim_registry_instantiation_file,
+            domain_policy_file,
+            submit_targets_file,
+            notifying_operator,
+            gas_price,
+            gas_price_multiplier,
+            max_gas_price,
             trigger_cron_schedule,
             middleware_instantiation_file,
             activate,
@@ -395,6 +488,23 @@ async fn main() {
             let contract_service_handler: OutputContractInstantiate =
                 read_and_decode(contract_service_handler_instantiation_file).await;
 
+            let contract_dkim_registry: Option<OutputContractInstantiate> =
+                match contract_dkim_registry_instantiation_file {
+                    Some(file) => Some(read_and_decode(output_directory.join(file)).await),
+                    None => None,
+                };
+
+            // passed through compacted, the operator parses it
+            let domain_policy: Option<serde_json::Value> = match domain_policy_file {
+                Some(file) => Some(read_and_decode(file).await),
+                None => None,
+            };
+
+            let submit_targets: Option<serde_json::Value> = match submit_targets_file {
+                Some(file) => Some(read_and_decode(file).await),
+                None => None,
+            };
+
             let component_operator_email_reader: OutputComponentUpload =
                 read_and_decode(component_operator_email_reader_cid_file).await;
 
@@ -411,12 +521,59 @@ async fn main() {
             let middleware_instantiation: MiddlewareInstantiation =
                 read_and_decode(middleware_instantiation_file).await;
 
+            let service_handler_querier = ctx
+                .service_handler_querier(
+                    ctx.parse_address(&contract_service_handler.address)
+                        .await
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            // the operator needs to know which commands the contract will hold,
+            // so it can email the confirmation request
+            let confirmation_config = service_handler_querier.confirmation_config().await.unwrap();
+
+            // and whether the contract only takes commitments of emails
+            let private_emails = service_handler_querier
+                .private_emails_enabled()
+                .await
+                .unwrap();
+
+            // and whether it wants a DKIM proof with every email
+            let dkim_proofs = service_handler_querier
+                .proof_verifier()
+                .await
+                .unwrap()
+                .is_some();
+
+            // and how old an email it still takes
+            let max_email_age_seconds = service_handler_querier
+                .max_email_age_seconds()
+                .await
+                .unwrap();
+
             let trigger = Trigger::Cron {
                 schedule: trigger_cron_schedule,
                 start_time: None,
                 end_time: None,
             };
 
+            // queried on the service's own chain: the service handler for whether a
+            // submitted command ran, and the registry for pinned keys
+            let operator_contracts = [
+                (
+                    "SERVICE_HANDLER_CONTRACT_ADDRESS".to_string(),
+                    contract_service_handler.address.clone(),
+                ),
+                ("CHAIN".to_string(), ctx.chain_key().to_string()),
+            ]
+            .into_iter()
+            .chain(
+                contract_dkim_registry
+                    .map(|registry| ("DKIM_REGISTRY_ADDRESS".to_string(), registry.address)),
+            );
+
             let operator_email_reader_component = wavs_types::Component {
                 source: ComponentSource::Download {
                     //uri: component_operator.uri.parse().unwrap(),
@@ -431,7 +588,33 @@ async fn main() {
                 },
                 fuel_limit: None,
                 time_limit_seconds: None,
-                config: Default::default(),
+                config: confirmation_config
+                    .map(|config| {
+                        (
+                            "CONFIRMATION".to_string(),
+                            serde_json::to_string(&config).unwrap(),
+                        )
+                    })
+                    .into_iter()
+                    .chain(
+                        private_emails.then(|| ("PRIVATE_EMAILS".to_string(), "true".to_string())),
+                    )
+                    .chain(dkim_proofs.then(|| ("DKIM_PROOFS".to_string(), "true".to_string())))
+                    .chain(
+                        max_email_age_seconds.map(|seconds| {
+                            ("MAX_EMAIL_AGE_SECONDS".to_string(), seconds.to_string())
+                        }),
+                    )
+                    .chain(operator_contracts)
+                    .chain(
+                        domain_policy
+                            .map(|policy| ("DOMAIN_POLICY".to_string(), policy.to_string())),
+                    )
+                    .chain(
+                        notifying_operator
+                            .map(|operator| ("NOTIFYING_OPERATOR".to_string(), operator)),
+                    )
+                    .collect(),
                 env_keys: [
                     "WAVS_ENV_IMAP_DEBUG_CAPABILITIES",
                     "WAVS_ENV_IMAP_PORT",
@@ -443,6 +626,23 @@ async fn main() {
                     "WAVS_ENV_GMAIL_CLIENT_ID",
                     "WAVS_ENV_GMAIL_CLIENT_SECRET",
                     "WAVS_ENV_GMAIL_TOKEN",
+                    "WAVS_ENV_SMTP_HOST",
+                    "WAVS_ENV_SMTP_PORT",
+                    "WAVS_ENV_SMTP_TLS",
+                    "WAVS_ENV_SMTP_FROM",
+                    "WAVS_ENV_SMTP_CREDENTIAL_KIND",
+                    "WAVS_ENV_SMTP_USERNAME",
+                    "WAVS_ENV_SMTP_PASSWORD",
+                    "WAVS_ENV_SMTP_RECEIPTS",
+                    "WAVS_ENV_OPERATOR_NAME",
+                    "WAVS_ENV_USER_ID_SALT",
+                    "WAVS_ENV_CONFIRM_NONCE_SECRET",
+                    "WAVS_ENV_DKIM_PROVER_URL",
+                    "WAVS_ENV_DKIM_RESOLVER",
+                    "WAVS_ENV_DKIM_RESOLVER_QUORUM",
+                    "WAVS_ENV_DKIM_STATIC_RECORDS",
+                    "WAVS_ENV_TRUSTED_AUTHSERV_ID",
+                    "WAVS_ENV_MAX_EMAILS_PER_TRIGGER",
                 ]
                 .into_iter()
                 .map(|s| s.to_string())
@@ -471,6 +671,19 @@ async fn main() {
                     ("CHAIN".to_string(), ctx.chain_key().to_string()),
                 ]
                 .into_iter()
+                .chain(
+                    [
+                        ("GAS_PRICE", gas_price),
+                        ("GAS_PRICE_MULTIPLIER", gas_price_multiplier),
+                        ("MAX_GAS_PRICE", max_gas_price),
+                    ]
+                    .into_iter()
+                    .filter_map(|(key, value)| Some((key.to_string(), value?.to_string()))),
+                )
+                .chain(
+                    submit_targets
+                        .map(|targets| ("SUBMIT_TARGETS".to_string(), targets.to_string())),
+                )
                 .collect(),
                 env_keys: Default::default(),
             };
@@ -635,9 +848,10 @@ async fn main() {
         }
         CliCommand::ContractRegisterUser {
             email_address,
+            user_id_salt,
             user_registry_address,
             proxy_address,
-            args: _,
+            args,
         } => {
             let client = ctx.signing_client().await.unwrap();
 
@@ -651,7 +865,27 @@ async fn main() {
                 user_registry_address.into(),
             );
 
-            let user_id = UserId::new_email_address(&email_address);
+            let user_id = match user_id_salt {
+                Some(salt) => UserId::new_email_address_with_salt(&email_address, salt.as_ref()),
+                None => UserId::new_email_address(&email_address),
+            };
+
+            if args.dry_run {
+                let simulation = contract
+                    .executor
+                    .simulate(
+                        &UserRegistryExecuteMsg::RegisterUser {
+                            user_id,
+                            proxy_address: proxy_address.to_string(),
+                        },
+                        &[],
+                    )
+                    .await
+                    .unwrap();
+
+                print_simulation(simulation);
+                return;
+            }
 
             let (tx_resp, user_id) = contract
                 .executor
@@ -665,7 +899,570 @@ async fn main() {
             println!("Email address: {}", email_address);
             println!("User ID: {}", user_id);
         }
+        CliCommand::BulkOnboard {
+            emails_file,
+            user_id_salt,
+            proxy_code_id,
+            admins,
+            control_centers,
+            user_registry_address,
+            batch_size,
+            retry_interrupted,
+            args,
+        } => {
+            let client = ctx.signing_client().await.unwrap();
+
+            let user_registry_address = ctx.parse_address(&user_registry_address).await.unwrap();
+
+            let contract = UserRegistryContract::new(
+                client.querier.clone().into(),
+                client.into(),
+                user_registry_address.into(),
+            );
+
+            let output = args.output();
+
+            // pick up where an earlier run left off
+            let mut users = output
+                .read::<OutputBulkOnboard>()
+                .await
+                .unwrap()
+                .unwrap_or_default()
+                .users;
+
+            for email in read_emails(&emails_file) {
+                let user_id = match &user_id_salt {
+                    Some(salt) => UserId::new_email_address_with_salt(&email, salt.as_ref()),
+                    None => UserId::new_email_address(&email),
+                };
+
+                if !users.iter().any(|user| user.user_id == user_id) {
+                    users.push(OutputOnboardedUser {
+                        email,
+                        user_id,
+                        proxy_address: None,
+                        instantiating: false,
+                        registered: false,
+                    });
+                }
+            }
+
+            // the chain has the last word on who's registered, and with which proxy
+            let registrations = contract
+                .querier
+                .all_registrations()
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|registration| (registration.user_id.to_string(), registration.proxy_address))
+                .collect::<HashMap<_, _>>();
+
+            for user in &mut users {
+                if let Some(proxy_address) = registrations.get(user.user_id.as_str()) {
+                    user.proxy_address = Some(proxy_address.to_string());
+                    user.instantiating = false;
+                    user.registered = true;
+                }
+            }
+
+            // their proxies may well exist, we just never heard back
+            let interrupted = users
+                .iter()
+                .filter(|user| user.instantiating && user.proxy_address.is_none())
+                .collect::<Vec<_>>();
+
+            if !interrupted.is_empty() && !retry_interrupted {
+                panic!(
+                    "An earlier run was interrupted while instantiating proxies for {} users \
+                     (e.g. {}). Look for contracts labeled \"Proxy <user id>\" and record their \
+                     addresses in {}, or pass --retry-interrupted if there are none",
+                    interrupted.len(),
+                    interrupted[0].email,
+                    path_deployments().join(&output.file).display()
+                );
+            }
+
+            let instantiate_msg = hydro_proxy::msg::InstantiateMsg {
+                admins,
+                control_centers,
+            };
+
+            let without_proxy = users
+                .iter()
+                .enumerate()
+                .filter(|(_, user)| user.proxy_address.is_none())
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+
+            for batch in without_proxy.chunks(batch_size.max(1)) {
+                let msgs = batch
+                    .iter()
+                    .map(|&index| {
+                        AnyMsg::instantiate(
+                            None,
+                            proxy_code_id,
+                            format!("Proxy {}", users[index].user_id),
+                            &instantiate_msg,
+                            vec![],
+                        )
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+                    .unwrap();
+
+                for &index in batch {
+                    users[index].instantiating = true;
+                }
+
+                // before broadcasting, so an interrupted run is caught by the next one
+                output
+                    .write(OutputBulkOnboard {
+                        users: users.clone(),
+                    })
+                    .await
+                    .unwrap();
+
+                let resp = contract.executor.inner.exec_multi(&msgs).await.unwrap();
+
+                for (msg_index, &index) in batch.iter().enumerate() {
+                    let proxy_address = instantiated_address(&resp.msg_events[msg_index])
+                        .expect("instantiate event should have the contract address");
+
+                    users[index].proxy_address = Some(proxy_address);
+                    users[index].instantiating = false;
+                }
+
+                println!("Instantiated {} proxies", batch.len());
+                println!("TX Hash: {}", resp.tx.unchecked_into_tx_response().txhash);
+
+                output
+                    .write(OutputBulkOnboard {
+                        users: users.clone(),
+                    })
+                    .await
+                    .unwrap();
+            }
+
+            let unregistered = users
+                .iter()
+                .enumerate()
+                .filter(|(_, user)| !user.registered)
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+
+            for batch in unregistered.chunks(batch_size.max(1)) {
+                let msgs = batch
+                    .iter()
+                    .map(|&index| {
+                        AnyMsg::execute(
+                            contract.address.clone(),
+                            &UserRegistryExecuteMsg::RegisterUser {
+                                user_id: users[index].user_id.clone(),
+                                proxy_address: users[index]
+                                    .proxy_address
+                                    .clone()
+                                    .expect("every user has a proxy by now"),
+                            },
+                            vec![],
+                        )
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()
+                    .unwrap();
+
+                let resp = contract.executor.inner.exec_multi(&msgs).await.unwrap();
+
+                for &index in batch {
+                    users[index].registered = true;
+                }
+
+                println!("Registered {} users", batch.len());
+                println!("TX Hash: {}", resp.tx.unchecked_into_tx_response().txhash);
+
+                output
+                    .write(OutputBulkOnboard {
+                        users: users.clone(),
+                    })
+                    .await
+                    .unwrap();
+            }
+
+            output
+                .write(OutputBulkOnboard {
+                    users: users.clone(),
+                })
+                .await
+                .unwrap();
+
+            println!(
+                "Onboarded {} users, {} of them in this run",
+                users.len(),
+                unregistered.len()
+            );
+        }
+        CliCommand::ContractSetUserRegistryServiceHandler {
+            user_registry_address,
+            service_handler_address,
+            args,
+        } => {
+            let client = ctx.signing_client().await.unwrap();
+
+            let user_registry_address = ctx.parse_address(&user_registry_address).await.unwrap();
+
+            let service_handler_address =
+                ctx.parse_address(&service_handler_address).await.unwrap();
+
+            let contract = UserRegistryContract::new(
+                client.querier.clone().into(),
+                client.into(),
+                user_registry_address.into(),
+            );
+
+            if args.dry_run {
+                let simulation = contract
+                    .executor
+                    .simulate(
+                        &UserRegistryExecuteMsg::SetServiceHandler {
+                            address: service_handler_address.to_string(),
+                        },
+                        &[],
+                    )
+                    .await
+                    .unwrap();
+
+                print_simulation(simulation);
+                return;
+            }
+
+            let tx_resp = contract
+                .executor
+                .set_service_handler(service_handler_address.clone().into())
+                .await
+                .unwrap();
+
+            println!("Set user registry service handler");
+            println!("TX Hash: {}", tx_resp.unchecked_into_tx_response().txhash);
+            println!("Service handler address: {}", service_handler_address);
+        }
+        CliCommand::ContractMigrateUserIdSalt {
+            email_address,
+            user_id_salt,
+            user_registry_address,
+            args,
+        } => {
+            let client = ctx.signing_client().await.unwrap();
+
+            let user_registry_address = ctx.parse_address(&user_registry_address).await.unwrap();
+
+            let public_user_id = UserId::new_email_address(&email_address);
+            let user_id =
+                UserId::new_email_address_with_salt(&email_address, user_id_salt.as_ref());
+
+            if args.dry_run {
+                // in one transaction, since the unlink only goes through after the link
+                let messages = [
+                    UserRegistryExecuteMsg::LinkUserId {
+                        user_id: public_user_id.clone(),
+                        new_user_id: user_id.clone(),
+                    },
+                    UserRegistryExecuteMsg::UnlinkUserId {
+                        user_id: user_id.clone(),
+                        unlink_user_id: public_user_id.clone(),
+                    },
+                ]
+                .iter()
+                .map(|msg| {
+                    proto_into_any(&client.contract_execute_msg(
+                        &user_registry_address,
+                        msg,
+                        vec![],
+                    )?)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+                .unwrap();
+
+                print_simulation(climb_simulate(&client, messages).await.unwrap());
+                return;
+            }
+
+            let contract = UserRegistryContract::new(
+                client.querier.clone().into(),
+                client.into(),
+                user_registry_address.into(),
+            );
+
+            let link_tx_resp = contract
+                .executor
+                .link_user_id(public_user_id.clone(), user_id.clone())
+                .await
+                .unwrap();
+
+            let unlink_tx_resp = contract
+                .executor
+                .unlink_user_id(user_id.clone(), public_user_id.clone())
+                .await
+                .unwrap();
+
+            println!("Migrated user to the new salt");
+            println!(
+                "Link TX Hash: {}",
+                link_tx_resp.unchecked_into_tx_response().txhash
+            );
+            println!(
+                "Unlink TX Hash: {}",
+                unlink_tx_resp.unchecked_into_tx_response().txhash
+            );
+            println!("Email address: {}", email_address);
+            println!("Old user ID: {}", public_user_id);
+            println!("User ID: {}", user_id);
+        }
+        CliCommand::ContractPinDkimKey {
+            domain,
+            selector,
+            record,
+            dns_over_https_url,
+            dkim_registry_address,
+            args,
+        } => {
+            let client = ctx.signing_client().await.unwrap();
+
+            let dkim_registry_address = ctx.parse_address(&dkim_registry_address).await.unwrap();
+
+            let contract = DkimRegistryContract::new(
+                client.querier.clone().into(),
+                client.into(),
+                dkim_registry_address.into(),
+            );
+
+            let records = match record {
+                Some(record) => vec![record],
+                None => {
+                    lookup_txt(&dns_over_https_url, &DkimKey::dns_name(&selector, &domain)).await
+                }
+            };
+
+            let keys = records
+                .iter()
+                .filter_map(|record| DkimKey::parse_txt_record(record))
+                .collect::<Vec<_>>();
+
+            if keys.is_empty() {
+                eprintln!("No DKIM key published for {selector}._domainkey.{domain}");
+                exit(1);
+            }
+
+            for (key_type, public_key) in keys {
+                let key_hash = DkimKey::key_hash(&public_key);
+
+                if args.dry_run {
+                    let simulation = contract
+                        .executor
+                        .simulate(
+                            &DkimRegistryExecuteMsg::RecordKey {
+                                domain: domain.clone(),
+                                selector: selector.clone(),
+                                key_type,
+                                public_key,
+                            },
+                            &[],
+                        )
+                        .await
+                        .unwrap();
+
+                    println!("Key hash: {}", key_hash);
+                    print_simulation(simulation);
+                    continue;
+                }
+
+                let tx_resp = contract
+                    .executor
+                    .record_key(&domain, &selector, key_type, public_key)
+                    .await
+                    .unwrap();
+
+                println!("Pinned DKIM key");
+                println!("TX Hash: {}", tx_resp.unchecked_into_tx_response().txhash);
+                println!("Selector: {selector}._domainkey.{domain}");
+                println!("Key hash: {}", key_hash);
+            }
+        }
+        CliCommand::ContractRevokeDkimKey {
+            domain,
+            selector,
+            key_hash,
+            dkim_registry_address,
+            args,
+        } => {
+            let client = ctx.signing_client().await.unwrap();
+
+            let dkim_registry_address = ctx.parse_address(&dkim_registry_address).await.unwrap();
+
+            let contract = DkimRegistryContract::new(
+                client.querier.clone().into(),
+                client.into(),
+                dkim_registry_address.into(),
+            );
+
+            if args.dry_run {
+                let simulation = contract
+                    .executor
+                    .simulate(
+                        &DkimRegistryExecuteMsg::RevokeKey {
+                            domain,
+                            selector,
+                            key_hash,
+                        },
+                        &[],
+                    )
+                    .await
+                    .unwrap();
+
+                print_simulation(simulation);
+                return;
+            }
+
+            let tx_resp = contract
+                .executor
+                .revoke_key(&domain, &selector, &key_hash)
+                .await
+                .unwrap();
+
+            println!("Revoked DKIM key");
+            println!("TX Hash: {}", tx_resp.unchecked_into_tx_response().txhash);
+            println!("Selector: {selector}._domainkey.{domain}");
+            println!("Key hash: {}", key_hash);
+        }
+    }
+}
+
+/// Addresses from a JSON array, or from the first column of a CSV (a header row is skipped),
+/// normalized the way the operators read them
+///
+/// Any row that isn't a single address fails the run, listed by line, so it's fixed rather
+/// than left off the onboarding.
+fn read_emails(path: &Path) -> Vec<String> {
+    let data = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {e}", path.display()));
+
+    let rows: Vec<(usize, String)> = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str::<Vec<String>>(&data)
+            .unwrap_or_else(|e| panic!("{} is not a JSON array of addresses: {e}", path.display()))
+            .into_iter()
+            .enumerate()
+            .collect()
+    } else {
+        data.lines()
+            .enumerate()
+            .filter_map(|(index, line)| {
+                let column = line.split(',').next()?.trim().trim_matches('"');
+                (!column.is_empty()).then(|| (index, column.to_string()))
+            })
+            .enumerate()
+            .filter(|(row, (_, column))| *row > 0 || normalize_email(column).is_some())
+            .map(|(_, row)| row)
+            .collect()
+    };
+
+    let mut emails = Vec::new();
+    let mut malformed = Vec::new();
+
+    for (index, row) in rows {
+        match normalize_email(&row) {
+            Some(email) => emails.push(email),
+            None => malformed.push(format!("  {}: {row}", index + 1)),
+        }
+    }
+
+    if !malformed.is_empty() {
+        panic!(
+            "{} has rows that aren't an email address:\n{}",
+            path.display(),
+            malformed.join("\n")
+        );
+    }
+
+    emails
+}
+
+/// The address of the contract a message instantiated, from its events
+fn instantiated_address(events: &[cosmwasm_std::Event]) -> Option<String> {
+    events
+        .iter()
+        .filter(|event| event.ty == "instantiate")
+        .flat_map(|event| &event.attributes)
+        .find(|attr| attr.key == "_contract_address")
+        .map(|attr| attr.value.clone())
+}
+
+/// Simulates instantiating `code_id` and prints what it would do
+async fn dry_run_instantiate(
+    client: &SigningClient,
+    code_id: u64,
+    label: &str,
+    instantiate_msg: &impl Serialize,
+) {
+    let msg = client
+        .contract_instantiate_msg(None, code_id, label, instantiate_msg, vec![])
+        .unwrap();
+
+    let simulation = climb_simulate(client, vec![proto_into_any(&msg).unwrap()])
+        .await
+        .unwrap();
+
+    print_simulation(simulation);
+}
+
+fn print_simulation(simulation: Simulation) {
+    println!("Dry run, nothing was broadcast");
+
+    if let Some(gas_used) = simulation.gas_used {
+        println!("Gas used: {gas_used}");
+    }
+
+    for event in simulation.events {
+        println!("Event: {}", event.ty);
+
+        for attr in event.attributes {
+            println!("  {}: {}", attr.key, attr.value);
+        }
+    }
+}
+
+/// TXT records at `name`, with the character-strings of each record joined back together
+async fn lookup_txt(dns_over_https_url: &Url, name: &str) -> Vec<String> {
+    #[derive(Debug, Deserialize)]
+    struct DnsResponse {
+        #[serde(rename = "Answer", default)]
+        answer: Vec<DnsAnswer>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct DnsAnswer {
+        #[serde(rename = "type")]
+        record_type: u16,
+        data: String,
     }
+
+    const TXT: u16 = 16;
+
+    let resp: DnsResponse = reqwest::Client::new()
+        .get(dns_over_https_url.clone())
+        .query(&[("name", name), ("type", "TXT")])
+        .header("Accept", "application/dns-json")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+
+    resp.answer
+        .into_iter()
+        .filter(|answer| answer.record_type == TXT)
+        .map(|answer| match answer.data.contains('"') {
+            // e.g. "v=DKIM1; k=rsa; " "p=MIIBIjAN..."
+            true => answer.data.split('"').skip(1).step_by(2).collect(),
+            false => answer.data,
+        })
+        .collect()
 }
 
 fn strip_trailing_slash(url: &Url) -> String {
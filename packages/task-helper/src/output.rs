@@ -1,7 +1,8 @@
 use anyhow::Result;
+use app_contract_api::user_registry::msg::UserId;
 use clap::ValueEnum;
 use layer_climb::prelude::EvmAddr;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use wavs_types::{ComponentDigest, ServiceDigest};
 
 use crate::{
@@ -52,6 +53,21 @@ impl Output {
 
         Ok(())
     }
+
+    /// What an earlier run wrote, if the file exists
+    pub async fn read<T: DeserializeOwned>(&self) -> Result<Option<T>> {
+        let file = path_deployments().join(&self.file);
+
+        if !tokio::fs::try_exists(&file).await? {
+            return Ok(None);
+        }
+
+        let data = tokio::fs::read_to_string(&file).await?;
+
+        match self.format {
+            OutputFormat::Json => Ok(Some(serde_json::from_str(&data)?)),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -111,3 +127,23 @@ pub struct OutputServiceUpload {
     /// The gateway URL for accessing the file via HTTP
     pub gateway_url: String,
 }
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub struct OutputBulkOnboard {
+    pub users: Vec<OutputOnboardedUser>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "snake_case")]
+pub struct OutputOnboardedUser {
+    pub email: String,
+    pub user_id: UserId,
+    /// Not set until its proxy is instantiated
+    pub proxy_address: Option<String>,
+    /// Set while its proxy is being instantiated, so a run that dies before recording the
+    /// address doesn't have the next one instantiate a second proxy
+    #[serde(default)]
+    pub instantiating: bool,
+    pub registered: bool,
+}
@@ -0,0 +1,114 @@
+use app_client::contracts::dkim_registry::DkimRegistryContract;
+use app_contract_api::dkim_registry::{
+    event::{DkimKeyRecordedEvent, DkimKeyRevokedEvent},
+    msg::{DkimKey, DkimKeyType},
+};
+use cosmwasm_std::Binary;
+use layer_climb::events::CosmosTxEvents;
+
+/// The executor must be a registry admin
+pub async fn pin_and_rotate_keys(dkim_registry: impl Into<DkimRegistryContract>) {
+    let DkimRegistryContract {
+        querier, executor, ..
+    } = dkim_registry.into();
+
+    let old_key = Binary::from(b"old public key".to_vec());
+    let new_key = Binary::from(b"new public key".to_vec());
+
+    // domain and selector are normalized
+    let response = executor
+        .record_key("Example.COM.", "S1", DkimKeyType::Rsa, old_key.clone())
+        .await
+        .unwrap();
+
+    let recorded = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(DkimKeyRecordedEvent::EVENT_TYPE)
+            .unwrap();
+        DkimKeyRecordedEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+    assert_eq!(recorded.domain, "example.com");
+    assert_eq!(recorded.selector, "s1");
+    assert_eq!(recorded.key_hash, DkimKey::key_hash(&old_key));
+    assert_eq!(recorded.first_seen_height, recorded.last_seen_height);
+
+    // seeing it again keeps the first sighting
+    let response = executor
+        .record_key("example.com", "s1", DkimKeyType::Rsa, old_key.clone())
+        .await
+        .unwrap();
+
+    let seen_again = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(DkimKeyRecordedEvent::EVENT_TYPE)
+            .unwrap();
+        DkimKeyRecordedEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+    assert_eq!(seen_again.first_seen_height, recorded.first_seen_height);
+    assert!(seen_again.last_seen_height >= recorded.last_seen_height);
+
+    // the domain rotates under the same selector, the old key stays pinned
+    executor
+        .record_key("example.com", "s1", DkimKeyType::Rsa, new_key.clone())
+        .await
+        .unwrap();
+
+    let keys = querier.keys("example.com", "s1").await.unwrap();
+    assert_eq!(keys.len(), 2);
+    assert!(keys.iter().all(|key| !key.revoked));
+    assert!(keys
+        .iter()
+        .any(|key| key.key_hash == DkimKey::key_hash(&old_key)));
+
+    let key = querier
+        .key("example.com", "s1", DkimKey::key_hash(&new_key))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(key.public_key, new_key);
+    assert_eq!(
+        key.txt_record(),
+        format!("v=DKIM1; k=rsa; p={}", new_key.to_base64())
+    );
+
+    assert!(querier.keys("example.com", "s2").await.unwrap().is_empty());
+
+    let response = executor
+        .revoke_key("example.com", "s1", DkimKey::key_hash(&old_key))
+        .await
+        .unwrap();
+
+    let revoked = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(DkimKeyRevokedEvent::EVENT_TYPE)
+            .unwrap();
+        DkimKeyRevokedEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+    assert_eq!(revoked.key_hash, DkimKey::key_hash(&old_key));
+
+    let old = querier
+        .key("example.com", "s1", DkimKey::key_hash(&old_key))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(old.revoked);
+
+    // a revoked key can't be pinned again
+    executor
+        .record_key("example.com", "s1", DkimKeyType::Rsa, old_key)
+        .await
+        .unwrap_err();
+
+    executor
+        .revoke_key("example.com", "s1", "not-a-key-hash")
+        .await
+        .unwrap_err();
+
+    executor
+        .record_key("example.com", "s1", DkimKeyType::Rsa, Binary::default())
+        .await
+        .unwrap_err();
+}
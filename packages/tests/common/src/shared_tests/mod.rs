@@ -1,2 +1,4 @@
+pub mod dkim_registry;
 pub mod integration;
 pub mod service_handler;
+pub mod user_registry;
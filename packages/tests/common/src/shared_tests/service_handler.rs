@@ -1,5 +1,24 @@
-use app_client::contracts::service_handler::{ServiceHandlerContract, ServiceHandlerQuerier};
-use app_contract_api::service_handler::{event::EmailEvent, msg::UserIdEmail};
+use app_client::contracts::{
+    dkim_registry::DkimRegistryContract,
+    proxy::ProxyContract,
+    service_handler::{ServiceHandlerContract, ServiceHandlerQuerier},
+    user_registry::UserRegistryContract,
+};
+use app_contract_api::{
+    dkim_proof::{DkimProof, DkimPublicInputs},
+    dkim_registry::msg::DkimKeyType,
+    service_handler::{
+        event::{
+            ActionConfirmedEvent, ActionExpiredEvent, ConfirmationRequestedEvent, EmailEvent,
+            PrivateEmailEvent,
+        },
+        msg::{
+            CustomExecuteMsg, ExecuteMsg, PendingAction, PrivateEmail, UserIdEmail,
+            MAX_CLOCK_SKEW_SECONDS,
+        },
+    },
+    user_registry::msg::UserId,
+};
 use layer_climb::events::CosmosTxEvents;
 
 pub async fn get_admin(querier: &ServiceHandlerQuerier, expected: &str) {
@@ -34,4 +53,518 @@ pub async fn push_email(service_handler: impl Into<ServiceHandlerContract>, emai
         .expect("Pushed email not found in query results");
 
     assert_eq!(found_email.subject, email.subject);
+
+    // pushed by the admin, so not under any event id
+    assert_eq!(querier.handled_event(&[0; 20]).await.unwrap(), None);
+}
+
+/// Expects the service handler to be configured to hold a withdrawal of `withdraw_subject`,
+/// and the proxy to hold enough funds to execute it once confirmed
+pub async fn confirm_withdrawal(
+    service_handler: impl Into<ServiceHandlerContract>,
+    proxy: impl Into<ProxyContract>,
+    withdraw_subject: String,
+) {
+    let ServiceHandlerContract {
+        querier, executor, ..
+    } = service_handler.into();
+    let proxy = proxy.into();
+
+    let user_registry = UserRegistryContract::new(
+        querier.inner.clone(),
+        executor.inner.clone(),
+        querier.user_registry_address().await.unwrap(),
+    );
+
+    let user_id = UserId::new_email_address("alice@example.com");
+    let other_user_id = UserId::new_email_address("bob@example.com");
+
+    user_registry
+        .executor
+        .register_user_id(user_id.clone(), proxy.address.clone())
+        .await
+        .unwrap();
+
+    let email = CustomExecuteMsg::Email(UserIdEmail {
+        from: user_id.clone(),
+        subject: withdraw_subject,
+    });
+
+    // nothing to hold it under
+    executor
+        .exec(&ExecuteMsg::Custom(email.clone()), &[])
+        .await
+        .unwrap_err();
+
+    let nonce = PendingAction::nonce_from_secret(b"operator-secret", b"withdrawal");
+    let response = executor.push_confirmable(email, &nonce).await.unwrap();
+
+    let requested = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(ConfirmationRequestedEvent::EVENT_TYPE)
+            .unwrap();
+        ConfirmationRequestedEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+
+    assert_eq!(requested.user_id, user_id);
+    // the nonce itself is only emailed
+    assert_eq!(requested.nonce_hash, PendingAction::nonce_hash(&nonce));
+    assert!(querier.pending_action(&nonce).await.unwrap().is_none());
+
+    let pending = querier
+        .pending_action(&requested.nonce_hash)
+        .await
+        .unwrap()
+        .expect("withdrawal should be held for confirmation");
+    assert_eq!(pending.user_id, user_id);
+
+    // only the original sender can confirm, and only with the nonce
+    executor
+        .confirm(other_user_id, nonce.clone())
+        .await
+        .unwrap_err();
+    executor
+        .confirm(user_id.clone(), requested.nonce_hash.clone())
+        .await
+        .unwrap_err();
+
+    let response = executor
+        .confirm(user_id.clone(), nonce.clone())
+        .await
+        .unwrap();
+
+    let confirmed = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(ActionConfirmedEvent::EVENT_TYPE)
+            .unwrap();
+        ActionConfirmedEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+
+    assert_eq!(confirmed.user_id, user_id);
+    assert_eq!(confirmed.nonce_hash, requested.nonce_hash);
+
+    // a nonce can only be used once
+    assert!(querier
+        .pending_action(&requested.nonce_hash)
+        .await
+        .unwrap()
+        .is_none());
+    executor.confirm(user_id, nonce).await.unwrap_err();
+}
+
+/// Like `confirm_withdrawal`, with `expire` moving the chain past the confirmation window
+pub async fn expired_confirmation(
+    service_handler: impl Into<ServiceHandlerContract>,
+    proxy: impl Into<ProxyContract>,
+    withdraw_subject: String,
+    expire: impl FnOnce(),
+) {
+    let ServiceHandlerContract {
+        querier, executor, ..
+    } = service_handler.into();
+    let proxy = proxy.into();
+
+    let user_registry = UserRegistryContract::new(
+        querier.inner.clone(),
+        executor.inner.clone(),
+        querier.user_registry_address().await.unwrap(),
+    );
+
+    let user_id = UserId::new_email_address("alice@example.com");
+
+    user_registry
+        .executor
+        .register_user_id(user_id.clone(), proxy.address.clone())
+        .await
+        .unwrap();
+
+    let nonce = PendingAction::nonce_from_secret(b"operator-secret", b"withdrawal");
+    let response = executor
+        .push_confirmable(
+            CustomExecuteMsg::Email(UserIdEmail {
+                from: user_id.clone(),
+                subject: withdraw_subject,
+            }),
+            &nonce,
+        )
+        .await
+        .unwrap();
+
+    let requested = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(ConfirmationRequestedEvent::EVENT_TYPE)
+            .unwrap();
+        ConfirmationRequestedEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+
+    expire();
+
+    // a late confirmation goes through, but only to drop the action
+    let response = executor.confirm(user_id.clone(), nonce).await.unwrap();
+
+    let events = CosmosTxEvents::from(&response);
+    assert!(events
+        .filter_events_by_type(ActionConfirmedEvent::EVENT_TYPE)
+        .next()
+        .is_none());
+
+    let expired = {
+        let event = events
+            .event_first_by_type(ActionExpiredEvent::EVENT_TYPE)
+            .unwrap();
+        ActionExpiredEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+    assert_eq!(expired.user_id, user_id);
+    assert_eq!(expired.nonce_hash, requested.nonce_hash);
+
+    assert!(querier
+        .pending_action(&requested.nonce_hash)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+/// Expects the service handler to be in privacy mode, and the proxy to hold enough funds
+/// to execute `withdraw_subject`
+pub async fn push_private_email(
+    service_handler: impl Into<ServiceHandlerContract>,
+    proxy: impl Into<ProxyContract>,
+    withdraw_subject: String,
+) {
+    let ServiceHandlerContract {
+        querier, executor, ..
+    } = service_handler.into();
+    let proxy = proxy.into();
+
+    assert!(querier.private_emails_enabled().await.unwrap());
+
+    let user_registry = UserRegistryContract::new(
+        querier.inner.clone(),
+        executor.inner.clone(),
+        querier.user_registry_address().await.unwrap(),
+    );
+
+    // as derived by an operator with a secret salt
+    let user_id = UserId::new_email_address_with_salt("alice@example.com", b"operator-secret");
+
+    user_registry
+        .executor
+        .register_user_id(user_id.clone(), proxy.address.clone())
+        .await
+        .unwrap();
+
+    let email = UserIdEmail {
+        from: user_id.clone(),
+        subject: withdraw_subject,
+    };
+
+    // the subject never makes it on-chain
+    executor.push_email(email.clone()).await.unwrap_err();
+
+    let private = PrivateEmail::new(&email, b"event-id");
+
+    let response = executor.push_private_email(private.clone()).await.unwrap();
+
+    let event = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(PrivateEmailEvent::EVENT_TYPE)
+            .unwrap();
+        PrivateEmailEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+
+    assert_eq!(event.from, user_id);
+    assert_eq!(
+        event.commitment,
+        PrivateEmail::commitment(&email.subject, b"event-id")
+    );
+
+    let emails = querier.all_private_emails().await.unwrap();
+    assert_eq!(emails, vec![(private, event.pagination_id)]);
+
+    assert!(querier.all_emails().await.unwrap().is_empty());
+}
+
+/// Expects the service handler to require proofs from a verifier that accepts only `valid_proof`,
+/// and the proxy to hold enough funds to execute `withdraw_subject`
+pub async fn push_proven_email(
+    service_handler: impl Into<ServiceHandlerContract>,
+    proxy: impl Into<ProxyContract>,
+    withdraw_subject: String,
+    valid_proof: cosmwasm_std::Binary,
+) {
+    let ServiceHandlerContract {
+        querier, executor, ..
+    } = service_handler.into();
+    let proxy = proxy.into();
+
+    assert!(querier.proof_verifier().await.unwrap().is_some());
+
+    let user_registry = UserRegistryContract::new(
+        querier.inner.clone(),
+        executor.inner.clone(),
+        querier.user_registry_address().await.unwrap(),
+    );
+
+    let dkim_registry = DkimRegistryContract::new(
+        querier.inner.clone(),
+        executor.inner.clone(),
+        querier.dkim_registry().await.unwrap().unwrap(),
+    );
+
+    let user_id = UserId::new_email_address("alice@example.com");
+
+    user_registry
+        .executor
+        .register_user_id(user_id.clone(), proxy.address.clone())
+        .await
+        .unwrap();
+
+    let email = UserIdEmail {
+        from: user_id.clone(),
+        subject: withdraw_subject,
+    };
+
+    let public_key = cosmwasm_std::Binary::from(b"public-key".to_vec());
+
+    let public_inputs = |from_hash: UserId, subject: &str| DkimPublicInputs {
+        from_hash,
+        from_domain: "example.com".to_string(),
+        target_hash: None,
+        command_hash: DkimPublicInputs::command_hash(subject),
+        domain: "example.com".to_string(),
+        selector: "sel".to_string(),
+        public_key_hash: DkimPublicInputs::public_key_hash(&public_key),
+    };
+
+    let proof = |public_inputs: DkimPublicInputs, proof: cosmwasm_std::Binary| DkimProof {
+        scheme: "test".to_string(),
+        proof,
+        public_inputs,
+    };
+
+    let push = |public_inputs: DkimPublicInputs, valid_proof: cosmwasm_std::Binary| {
+        executor.push_proven_email(email.clone(), proof(public_inputs, valid_proof))
+    };
+
+    // the operators' word alone isn't enough
+    executor.push_email(email.clone()).await.unwrap_err();
+
+    // nor is a proof under a key that isn't pinned
+    push(
+        public_inputs(user_id.clone(), &email.subject),
+        valid_proof.clone(),
+    )
+    .await
+    .unwrap_err();
+
+    dkim_registry
+        .executor
+        .record_key("example.com", "sel", DkimKeyType::Rsa, public_key.clone())
+        .await
+        .unwrap();
+
+    // proofs must be for this sender and this command
+    push(
+        public_inputs(
+            UserId::new_email_address("mallory@example.com"),
+            &email.subject,
+        ),
+        valid_proof.clone(),
+    )
+    .await
+    .unwrap_err();
+    push(
+        public_inputs(user_id.clone(), "forward"),
+        valid_proof.clone(),
+    )
+    .await
+    .unwrap_err();
+
+    // signed by the sender's own domain
+    push(
+        DkimPublicInputs {
+            from_domain: "attacker.example".to_string(),
+            ..public_inputs(user_id.clone(), &email.subject)
+        },
+        valid_proof.clone(),
+    )
+    .await
+    .unwrap_err();
+
+    // with a link target only for a link
+    push(
+        DkimPublicInputs {
+            target_hash: Some(UserId::new_email_address("bob@example.com")),
+            ..public_inputs(user_id.clone(), &email.subject)
+        },
+        valid_proof.clone(),
+    )
+    .await
+    .unwrap_err();
+
+    // and pass the verifier
+    push(
+        public_inputs(user_id.clone(), &email.subject),
+        cosmwasm_std::Binary::from(b"forged".to_vec()),
+    )
+    .await
+    .unwrap_err();
+
+    let response = push(
+        public_inputs(user_id.clone(), &email.subject),
+        valid_proof.clone(),
+    )
+    .await
+    .unwrap();
+
+    let event = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events.event_first_by_type(EmailEvent::EVENT_TYPE).unwrap();
+        EmailEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+
+    assert_eq!(event.email, email);
+
+    // a revoked key no longer counts
+    dkim_registry
+        .executor
+        .revoke_key(
+            "example.com",
+            "sel",
+            DkimPublicInputs::public_key_hash(&public_key),
+        )
+        .await
+        .unwrap();
+
+    push(public_inputs(user_id.clone(), &email.subject), valid_proof)
+        .await
+        .unwrap_err();
+}
+
+/// Expects the service handler to have a max email age, and the proxy to hold enough funds
+/// to execute `withdraw_subject`. `now` is the current block time.
+pub async fn push_timestamped_email(
+    service_handler: impl Into<ServiceHandlerContract>,
+    proxy: impl Into<ProxyContract>,
+    withdraw_subject: String,
+    now: cosmwasm_std::Timestamp,
+) {
+    let ServiceHandlerContract {
+        querier, executor, ..
+    } = service_handler.into();
+    let proxy = proxy.into();
+
+    let max_age_seconds = querier.max_email_age_seconds().await.unwrap().unwrap();
+
+    let user_registry = UserRegistryContract::new(
+        querier.inner.clone(),
+        executor.inner.clone(),
+        querier.user_registry_address().await.unwrap(),
+    );
+
+    let user_id = UserId::new_email_address("alice@example.com");
+
+    user_registry
+        .executor
+        .register_user_id(user_id.clone(), proxy.address.clone())
+        .await
+        .unwrap();
+
+    let email = UserIdEmail {
+        from: user_id.clone(),
+        subject: withdraw_subject,
+    };
+
+    let msg = CustomExecuteMsg::Email(email.clone());
+
+    // without a timestamp there's nothing to check the age against
+    executor.push_email(email.clone()).await.unwrap_err();
+
+    // too old
+    executor
+        .push_timestamped(msg.clone(), now.minus_seconds(max_age_seconds + 1))
+        .await
+        .unwrap_err();
+
+    // too far ahead, even allowing for clock skew
+    executor
+        .push_timestamped(msg.clone(), now.plus_seconds(MAX_CLOCK_SKEW_SECONDS + 1))
+        .await
+        .unwrap_err();
+
+    // one timestamp can't vouch for another
+    executor
+        .push_timestamped(
+            CustomExecuteMsg::Timestamped {
+                signed_at: now,
+                msg: Box::new(msg.clone()),
+            },
+            now,
+        )
+        .await
+        .unwrap_err();
+
+    let response = executor
+        .push_timestamped(msg, now.minus_seconds(max_age_seconds))
+        .await
+        .unwrap();
+
+    let event = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events.event_first_by_type(EmailEvent::EVENT_TYPE).unwrap();
+        EmailEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+
+    assert_eq!(event.email, email);
+}
+
+pub async fn simulate_email(
+    service_handler: impl Into<ServiceHandlerContract>,
+    proxy: impl Into<ProxyContract>,
+    withdraw_subject: String,
+) {
+    let ServiceHandlerContract {
+        querier, executor, ..
+    } = service_handler.into();
+    let proxy = proxy.into();
+
+    let user_registry = UserRegistryContract::new(
+        querier.inner.clone(),
+        executor.inner.clone(),
+        querier.user_registry_address().await.unwrap(),
+    );
+
+    let user_id = UserId::new_email_address("alice@example.com");
+
+    user_registry
+        .executor
+        .register_user_id(user_id.clone(), proxy.address.clone())
+        .await
+        .unwrap();
+
+    let msg = ExecuteMsg::Custom(CustomExecuteMsg::Email(UserIdEmail {
+        from: user_id.clone(),
+        subject: withdraw_subject,
+    }));
+
+    // simulating twice works, since the first one didn't spend the funds
+    for _ in 0..2 {
+        let simulation = executor.simulate(&msg, &[]).await.unwrap();
+
+        let events = simulation
+            .events::<EmailEvent>(EmailEvent::EVENT_TYPE)
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].email.from, user_id);
+    }
+
+    assert!(querier.all_emails().await.unwrap().is_empty());
+
+    // and it's the same call that would have gone through
+    executor.exec(&msg, &[]).await.unwrap();
+    executor.exec(&msg, &[]).await.unwrap_err();
 }
@@ -0,0 +1,453 @@
+use app_client::{
+    address::AnyAddr,
+    contracts::{
+        proxy::ProxyContract, service_handler::ServiceHandlerContract,
+        user_registry::UserRegistryContract,
+    },
+    executor::AnyMsg,
+};
+use app_contract_api::{
+    service_handler::{event::ConfirmationRequestedEvent, msg::PendingAction},
+    user_registry::{
+        event::{
+            AdminsUpdatedEvent, UserDeregisteredEvent, UserIdLinkedEvent, UserIdUnlinkedEvent,
+            UserProxyUpdatedEvent, UserRegisteredEvent,
+        },
+        msg::{ExecuteMsg, UserId},
+    },
+};
+use layer_climb::events::CosmosTxEvents;
+
+pub async fn link_user_ids(
+    service_handler: impl Into<ServiceHandlerContract>,
+    proxy: impl Into<ProxyContract>,
+) {
+    let service_handler = service_handler.into();
+    let proxy = proxy.into();
+
+    let user_registry = UserRegistryContract::new(
+        service_handler.querier.inner.clone(),
+        service_handler.executor.inner.clone(),
+        service_handler
+            .querier
+            .user_registry_address()
+            .await
+            .unwrap(),
+    );
+
+    let home = UserId::new_email_address("alice@example.com");
+    let work = UserId::new_email_address("alice@work.example.com");
+    let stranger = UserId::new_email_address("mallory@example.com");
+
+    user_registry
+        .executor
+        .register_user_id(home.clone(), proxy.address.clone())
+        .await
+        .unwrap();
+
+    let nonce = request_link(&service_handler, home.clone(), work.clone()).await;
+
+    // not allowed to link on behalf of users until it's the registry's service handler
+    service_handler
+        .executor
+        .confirm(work.clone(), nonce.clone())
+        .await
+        .unwrap_err();
+
+    user_registry
+        .executor
+        .set_service_handler(service_handler.address.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        user_registry.querier.service_handler().await.unwrap(),
+        Some(service_handler.address.clone())
+    );
+
+    // the linked address has to confirm, not the one asking
+    service_handler
+        .executor
+        .confirm(home.clone(), nonce.clone())
+        .await
+        .unwrap_err();
+    user_registry
+        .querier
+        .proxy_address_user_id(work.clone())
+        .await
+        .unwrap_err();
+
+    let response = service_handler
+        .executor
+        .confirm(work.clone(), nonce)
+        .await
+        .unwrap();
+
+    let linked = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(UserIdLinkedEvent::EVENT_TYPE)
+            .unwrap();
+        UserIdLinkedEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+    assert_eq!(linked.user_id, work);
+
+    // both identities resolve to the same proxy
+    assert_eq!(
+        user_registry
+            .querier
+            .proxy_address_user_id(work.clone())
+            .await
+            .unwrap(),
+        proxy.address
+    );
+
+    let account = user_registry.querier.account(home.clone()).await.unwrap();
+    assert_eq!(account.account_id, linked.account_id);
+    assert_eq!(account.user_ids.len(), 2);
+    assert!(account.user_ids.contains(&home));
+    assert!(account.user_ids.contains(&work));
+
+    // an identity can only belong to one account
+    let nonce = request_link(&service_handler, work.clone(), home.clone()).await;
+    service_handler
+        .executor
+        .confirm(home.clone(), nonce)
+        .await
+        .unwrap_err();
+
+    // only linked identities can act on the account
+    service_handler
+        .executor
+        .unlink(stranger, work.clone())
+        .await
+        .unwrap_err();
+
+    let response = service_handler
+        .executor
+        .unlink(work.clone(), home.clone())
+        .await
+        .unwrap();
+
+    let unlinked = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(UserIdUnlinkedEvent::EVENT_TYPE)
+            .unwrap();
+        UserIdUnlinkedEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+    assert_eq!(unlinked.user_id, home);
+
+    user_registry
+        .querier
+        .proxy_address_user_id(home.clone())
+        .await
+        .unwrap_err();
+
+    let account = user_registry.querier.account(work.clone()).await.unwrap();
+    assert_eq!(account.user_ids, vec![work.clone()]);
+
+    // the last identity stays
+    service_handler
+        .executor
+        .unlink(work.clone(), work)
+        .await
+        .unwrap_err();
+}
+
+/// Asks to link `user_id` to `from`'s account, gives the nonce `user_id` has to confirm with
+async fn request_link(
+    service_handler: &ServiceHandlerContract,
+    from: UserId,
+    user_id: UserId,
+) -> String {
+    let nonce = PendingAction::nonce_from_secret(b"operator-secret", user_id.as_str().as_bytes());
+
+    let response = service_handler
+        .executor
+        .link(from, user_id.clone(), &nonce)
+        .await
+        .unwrap();
+
+    let requested = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(ConfirmationRequestedEvent::EVENT_TYPE)
+            .unwrap();
+        ConfirmationRequestedEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+    assert_eq!(requested.user_id, user_id);
+    assert_eq!(requested.nonce_hash, PendingAction::nonce_hash(&nonce));
+
+    nonce
+}
+
+/// `admin` must be the registry's only admin, and the executor
+pub async fn admin_lifecycle(
+    user_registry: impl Into<UserRegistryContract>,
+    admin: AnyAddr,
+    other_admin: AnyAddr,
+    proxy: AnyAddr,
+    other_proxy: AnyAddr,
+) {
+    let UserRegistryContract {
+        querier, executor, ..
+    } = user_registry.into();
+
+    assert_eq!(querier.admins().await.unwrap(), vec![admin.clone()]);
+
+    let response = executor
+        .update_admins(vec![other_admin.clone()], vec![])
+        .await
+        .unwrap();
+
+    let updated = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(AdminsUpdatedEvent::EVENT_TYPE)
+            .unwrap();
+        AdminsUpdatedEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+    assert_eq!(updated.added.len(), 1);
+    assert!(updated.removed.is_empty());
+
+    assert_eq!(
+        querier.admins().await.unwrap(),
+        vec![admin.clone(), other_admin.clone()]
+    );
+
+    // can't remove everyone
+    executor
+        .update_admins(vec![], vec![admin.clone(), other_admin.clone()])
+        .await
+        .unwrap_err();
+
+    let alice = UserId::new_email_address("alice@example.com");
+    let bob = UserId::new_email_address("bob@example.com");
+
+    executor
+        .register_user_id(alice.clone(), proxy.clone())
+        .await
+        .unwrap();
+
+    let response = executor
+        .update_user_proxy(alice.clone(), other_proxy.clone())
+        .await
+        .unwrap();
+
+    let updated = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(UserProxyUpdatedEvent::EVENT_TYPE)
+            .unwrap();
+        UserProxyUpdatedEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+    assert_eq!(AnyAddr::from(updated.old_proxy_address), proxy);
+    assert_eq!(AnyAddr::from(updated.proxy_address), other_proxy);
+
+    // the old proxy is free again
+    executor
+        .register_user_id(bob.clone(), proxy.clone())
+        .await
+        .unwrap();
+
+    // but a proxy in use can't be taken
+    executor
+        .update_user_proxy(bob.clone(), other_proxy.clone())
+        .await
+        .unwrap_err();
+
+    assert_eq!(
+        querier.proxy_address_user_id(alice.clone()).await.unwrap(),
+        other_proxy
+    );
+    assert_eq!(
+        querier.proxy_address_user_id(bob.clone()).await.unwrap(),
+        proxy
+    );
+
+    let registrations = querier.all_registrations().await.unwrap();
+    assert_eq!(registrations.len(), 2);
+
+    // paging picks up where it left off
+    let first = querier.registrations(Some(1), None).await.unwrap();
+    let second = querier
+        .registrations(Some(1), Some(first[0].user_id.clone()))
+        .await
+        .unwrap();
+    assert_ne!(first[0].user_id, second[0].user_id);
+
+    let response = executor.deregister_user(alice.clone()).await.unwrap();
+
+    let deregistered = {
+        let events = CosmosTxEvents::from(&response);
+        let event = events
+            .event_first_by_type(UserDeregisteredEvent::EVENT_TYPE)
+            .unwrap();
+        UserDeregisteredEvent::try_from(&cosmwasm_std::Event::from(event)).unwrap()
+    };
+    assert_eq!(deregistered.user_ids, vec![alice.clone()]);
+
+    querier.proxy_address_user_id(alice).await.unwrap_err();
+    assert_eq!(querier.all_registrations().await.unwrap().len(), 1);
+
+    // hand over to the other admin, after which we're locked out
+    executor.update_admins(vec![], vec![admin]).await.unwrap();
+    assert_eq!(querier.admins().await.unwrap(), vec![other_admin]);
+
+    executor.deregister_user(bob).await.unwrap_err();
+}
+
+pub async fn lookup_and_enumeration(
+    user_registry: impl Into<UserRegistryContract>,
+    proxy: AnyAddr,
+    other_proxy: AnyAddr,
+) {
+    let UserRegistryContract {
+        querier, executor, ..
+    } = user_registry.into();
+
+    let count = querier.user_count().await.unwrap();
+    assert_eq!((count.user_ids, count.accounts), (0, 0));
+
+    let alice = UserId::new_email_address("alice@example.com");
+    let alice_work = UserId::new_email_address("alice@work.example.com");
+    let bob = UserId::new_email_address("bob@example.com");
+
+    executor
+        .register_user_id(alice.clone(), proxy.clone())
+        .await
+        .unwrap();
+    executor
+        .link_user_id(alice.clone(), alice_work.clone())
+        .await
+        .unwrap();
+    executor
+        .register_user_id(bob.clone(), other_proxy.clone())
+        .await
+        .unwrap();
+
+    // one account per proxy
+    executor
+        .register_user_id(
+            UserId::new_email_address("mallory@example.com"),
+            proxy.clone(),
+        )
+        .await
+        .unwrap_err();
+
+    let count = querier.user_count().await.unwrap();
+    assert_eq!((count.user_ids, count.accounts), (3, 2));
+
+    let owner = querier.proxy_owner(proxy.clone()).await.unwrap();
+    assert_eq!(
+        owner.account_id,
+        querier.account(alice.clone()).await.unwrap().account_id
+    );
+    assert_eq!(owner.user_ids.len(), 2);
+    assert!(owner.user_ids.contains(&alice));
+    assert!(owner.user_ids.contains(&alice_work));
+
+    let owner = querier.proxy_owner(other_proxy.clone()).await.unwrap();
+    assert_eq!(owner.user_ids, vec![bob.clone()]);
+
+    let mut user_ids = querier.all_users().await.unwrap();
+    user_ids.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    let mut expected = vec![alice.clone(), alice_work.clone(), bob.clone()];
+    expected.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    assert_eq!(user_ids, expected);
+
+    // paging picks up where it left off
+    let first = querier.users(Some(2), None).await.unwrap();
+    let rest = querier.users(Some(2), first.last().cloned()).await.unwrap();
+    assert_eq!((first.len(), rest.len()), (2, 1));
+
+    // raw addresses go through the same derivation as the operator
+    let found = querier
+        .user_by_email("Alice <alice@example.com>")
+        .await
+        .unwrap();
+    assert_eq!(found.user_id, alice);
+    assert_eq!(found.proxy_address.map(AnyAddr::from), Some(proxy.clone()));
+
+    let found = querier.user_by_email("nobody@example.com").await.unwrap();
+    assert_eq!(
+        found.user_id,
+        UserId::new_email_address("nobody@example.com")
+    );
+    assert_eq!(found.proxy_address, None);
+
+    executor.deregister_user(alice_work).await.unwrap();
+
+    querier.proxy_owner(proxy).await.unwrap_err();
+
+    let count = querier.user_count().await.unwrap();
+    assert_eq!((count.user_ids, count.accounts), (1, 1));
+}
+
+pub async fn register_users_in_one_tx(
+    user_registry: impl Into<UserRegistryContract>,
+    proxy: AnyAddr,
+    other_proxy: AnyAddr,
+) {
+    let UserRegistryContract {
+        querier,
+        executor,
+        address,
+    } = user_registry.into();
+
+    let alice = UserId::new_email_address("alice@example.com");
+    let bob = UserId::new_email_address("bob@example.com");
+
+    let register = |user_id: &UserId, proxy: &AnyAddr| {
+        AnyMsg::execute(
+            address.clone(),
+            &ExecuteMsg::RegisterUser {
+                user_id: user_id.clone(),
+                proxy_address: proxy.to_string(),
+            },
+            vec![],
+        )
+        .unwrap()
+    };
+
+    // registering alice twice fails the whole transaction
+    executor
+        .inner
+        .exec_multi(&[
+            register(&alice, &proxy),
+            register(&bob, &other_proxy),
+            register(&alice, &other_proxy),
+        ])
+        .await
+        .unwrap_err();
+
+    assert_eq!(querier.user_count().await.unwrap().user_ids, 0);
+
+    let response = executor
+        .inner
+        .exec_multi(&[register(&alice, &proxy), register(&bob, &other_proxy)])
+        .await
+        .unwrap();
+
+    assert_eq!(response.msg_events.len(), 2);
+
+    for (index, (user_id, proxy)) in [(&alice, &proxy), (&bob, &other_proxy)]
+        .into_iter()
+        .enumerate()
+    {
+        let registered = response
+            .events::<UserRegisteredEvent>(index, UserRegisteredEvent::EVENT_TYPE)
+            .unwrap();
+
+        assert_eq!(registered.len(), 1);
+        assert_eq!(&registered[0].user_id, user_id);
+        assert_eq!(
+            querier
+                .proxy_address_user_id(user_id.clone())
+                .await
+                .unwrap(),
+            *proxy
+        );
+    }
+}
@@ -1,4 +1,5 @@
 //! Abstraction specifically for the off-chain multi-test environment
+pub mod dkim_registry;
 pub mod proxy;
 pub mod service_handler;
 pub mod user_registry;
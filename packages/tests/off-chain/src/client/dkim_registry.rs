@@ -0,0 +1,66 @@
+use app_client::contracts::dkim_registry::{
+    DkimRegistryContract, DkimRegistryExecutor, DkimRegistryQuerier,
+};
+use cosmwasm_std::Addr;
+use cw_multi_test::{ContractWrapper, Executor};
+
+use crate::client::AppClient;
+
+#[derive(Clone)]
+pub struct DkimRegistryClient {
+    pub querier: DkimRegistryQuerier,
+    pub executor: DkimRegistryExecutor,
+    pub address: Addr,
+}
+
+impl From<DkimRegistryClient> for DkimRegistryContract {
+    fn from(client: DkimRegistryClient) -> Self {
+        DkimRegistryContract {
+            querier: client.querier,
+            executor: client.executor,
+            address: client.address.into(),
+        }
+    }
+}
+
+impl DkimRegistryClient {
+    pub fn new(app_client: AppClient) -> Self {
+        let admin = app_client.admin();
+        Self::new_with_admins(app_client, vec![admin])
+    }
+
+    pub fn new_with_admins(app_client: AppClient, admins: Vec<Addr>) -> Self {
+        let contract = ContractWrapper::new(
+            app_contract_dkim_registry::execute,
+            app_contract_dkim_registry::instantiate,
+            app_contract_dkim_registry::query,
+        );
+        let code_id = app_client.with_app_mut(|app| app.store_code(Box::new(contract)));
+
+        let msg = app_contract_api::dkim_registry::msg::InstantiateMsg {
+            admins: admins.iter().map(|a| a.to_string()).collect(),
+        };
+
+        let address = app_client.with_app_mut(|app| {
+            app.instantiate_contract(
+                code_id,
+                app_client.admin(),
+                &msg,
+                &[],
+                "dkim registry",
+                None,
+            )
+            .unwrap()
+        });
+
+        let querier = DkimRegistryQuerier::new(app_client.querier.clone(), address.clone().into());
+        let executor =
+            DkimRegistryExecutor::new(app_client.executor.clone(), address.clone().into());
+
+        Self {
+            querier,
+            executor,
+            address,
+        }
+    }
+}
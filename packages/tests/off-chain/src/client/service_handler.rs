@@ -1,6 +1,7 @@
 use app_client::contracts::service_handler::{
     ServiceHandlerContract, ServiceHandlerExecutor, ServiceHandlerQuerier,
 };
+use app_contract_api::service_handler::msg::ConfirmationConfig;
 use cosmwasm_std::Addr;
 use cw_multi_test::{ContractWrapper, Executor};
 
@@ -30,6 +31,75 @@ impl ServiceHandlerClient {
     }
 
     pub fn new_with_admin(app_client: AppClient, user_registry: Addr, admin: Addr) -> Self {
+        Self::new_inner(app_client, user_registry, admin, None, false, None, None)
+    }
+
+    pub fn new_with_confirmation(
+        app_client: AppClient,
+        user_registry: Addr,
+        confirmation: ConfirmationConfig,
+    ) -> Self {
+        let admin = app_client.admin();
+        Self::new_inner(
+            app_client,
+            user_registry,
+            admin,
+            Some(confirmation),
+            false,
+            None,
+            None,
+        )
+    }
+
+    pub fn new_with_private_emails(app_client: AppClient, user_registry: Addr) -> Self {
+        let admin = app_client.admin();
+        Self::new_inner(app_client, user_registry, admin, None, true, None, None)
+    }
+
+    pub fn new_with_proof_verifier(
+        app_client: AppClient,
+        user_registry: Addr,
+        proof_verifier: Addr,
+        dkim_registry: Addr,
+    ) -> Self {
+        let admin = app_client.admin();
+        Self::new_inner(
+            app_client,
+            user_registry,
+            admin,
+            None,
+            false,
+            Some((proof_verifier, dkim_registry)),
+            None,
+        )
+    }
+
+    pub fn new_with_max_email_age(
+        app_client: AppClient,
+        user_registry: Addr,
+        max_email_age_seconds: u64,
+    ) -> Self {
+        let admin = app_client.admin();
+        Self::new_inner(
+            app_client,
+            user_registry,
+            admin,
+            None,
+            false,
+            None,
+            Some(max_email_age_seconds),
+        )
+    }
+
+    fn new_inner(
+        app_client: AppClient,
+        user_registry: Addr,
+        admin: Addr,
+        confirmation: Option<ConfirmationConfig>,
+        private_emails: bool,
+        proof_verifier: Option<(Addr, Addr)>,
+        max_email_age_seconds: Option<u64>,
+    ) -> Self {
         let contract = ContractWrapper::new(
             app_contract_service_handler::execute,
             app_contract_service_handler::instantiate,
@@ -40,6 +110,13 @@ impl ServiceHandlerClient {
         let msg = app_contract_api::service_handler::msg::InstantiateMsg {
             auth: app_contract_api::service_handler::msg::Auth::Admin(admin.to_string()),
             user_registry: user_registry.to_string(),
+            confirmation,
+            private_emails,
+            proof_verifier: proof_verifier
+                .as_ref()
+                .map(|(verifier, _)| verifier.to_string()),
+            dkim_registry: proof_verifier.map(|(_, dkim_registry)| dkim_registry.to_string()),
+            max_email_age_seconds,
         };
 
         let address = app_client.with_app_mut(|app| {
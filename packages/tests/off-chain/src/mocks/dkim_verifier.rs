@@ -0,0 +1,43 @@
+//! Mock DKIM proof verifier contract for testing
+use app_contract_api::dkim_proof::{VerifierQueryMsg, VerifyDkimProofResponse};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdResult,
+};
+use cw_multi_test::{Contract, ContractWrapper};
+use cw_storage_plus::Item;
+
+const ACCEPTED_PROOF: Item<Binary> = Item::new("accepted-proof");
+
+#[cw_serde]
+pub struct InstantiateMsg {
+    /// The only proof bytes considered valid
+    pub accepted_proof: Binary,
+}
+
+fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    ACCEPTED_PROOF.save(deps.storage, &msg.accepted_proof)?;
+    Ok(Response::new())
+}
+
+fn execute(_deps: DepsMut, _env: Env, _info: MessageInfo, _msg: Empty) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+fn query(deps: Deps, _env: Env, msg: VerifierQueryMsg) -> StdResult<Binary> {
+    match msg {
+        VerifierQueryMsg::VerifyDkimProof { proof } => {
+            let valid = proof.proof == ACCEPTED_PROOF.load(deps.storage)?;
+            to_json_binary(&VerifyDkimProofResponse { valid })
+        }
+    }
+}
+
+pub fn contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query))
+}
@@ -1,2 +1,3 @@
 pub mod control_center;
+pub mod dkim_verifier;
 pub mod vault;
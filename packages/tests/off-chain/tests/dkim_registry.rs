@@ -0,0 +1,12 @@
+use app_utils::tracing::tracing_init;
+use off_chain_tests::client::{dkim_registry::DkimRegistryClient, AppClient};
+
+#[tokio::test]
+async fn pin_and_rotate_keys() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let dkim_registry = DkimRegistryClient::new(app_client.clone());
+
+    app_tests_common::shared_tests::dkim_registry::pin_and_rotate_keys(dkim_registry).await;
+}
@@ -1,7 +1,17 @@
+use app_contract_api::service_handler::msg::{
+    Auth, ConfirmationConfig, InstantiateMsg, MigrateMsg,
+};
+use app_contract_service_handler::error::ContractError;
 use app_utils::tracing::tracing_init;
+use cosmwasm_std::{BankMsg, Binary, Coin};
+use cw_multi_test::{ContractWrapper, Executor};
+use cw_storage_plus::Item;
+use off_chain_tests::client::dkim_registry::DkimRegistryClient;
+use off_chain_tests::client::proxy::ProxyClient;
 use off_chain_tests::client::service_handler::ServiceHandlerClient;
 use off_chain_tests::client::user_registry::UserRegistryClient;
 use off_chain_tests::client::AppClient;
+use off_chain_tests::mocks;
 
 #[tokio::test]
 async fn get_admin() {
@@ -16,3 +26,342 @@ async fn get_admin() {
     app_tests_common::shared_tests::service_handler::get_admin(&service_handler.querier, &admin)
         .await;
 }
+
+#[tokio::test]
+async fn confirm_withdrawal() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let user_registry = UserRegistryClient::new(app_client.clone());
+    let service_handler = ServiceHandlerClient::new_with_confirmation(
+        app_client.clone(),
+        user_registry.address,
+        ConfirmationConfig {
+            withdraw_threshold: 100_000u128.into(),
+            expires_after_seconds: 600,
+        },
+    );
+
+    let proxy = ProxyClient::new(
+        app_client.clone(),
+        ProxyClient::code_id(&app_client),
+        vec![service_handler.address.clone()],
+    );
+
+    // fund the proxy so the withdrawal can go through once confirmed
+    app_client.with_app_mut(|app| {
+        app.execute(
+            app_client.admin(),
+            BankMsg::Send {
+                to_address: proxy.address.to_string(),
+                amount: vec![Coin::new(500_000u128, "utoken")],
+            }
+            .into(),
+        )
+        .unwrap();
+    });
+
+    let subject = format!("withdraw {} utoken 500000", app_client.admin());
+
+    app_tests_common::shared_tests::service_handler::confirm_withdrawal(
+        service_handler,
+        proxy,
+        subject,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn expired_confirmation() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let user_registry = UserRegistryClient::new(app_client.clone());
+    let service_handler = ServiceHandlerClient::new_with_confirmation(
+        app_client.clone(),
+        user_registry.address,
+        ConfirmationConfig {
+            withdraw_threshold: 100_000u128.into(),
+            expires_after_seconds: 600,
+        },
+    );
+
+    let proxy = ProxyClient::new(
+        app_client.clone(),
+        ProxyClient::code_id(&app_client),
+        vec![service_handler.address.clone()],
+    );
+
+    let subject = format!("withdraw {} utoken 500000", app_client.admin());
+
+    app_tests_common::shared_tests::service_handler::expired_confirmation(
+        service_handler,
+        proxy,
+        subject,
+        || {
+            app_client.with_app_mut(|app| {
+                app.update_block(|block| block.time = block.time.plus_seconds(601))
+            })
+        },
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn push_private_email() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let user_registry = UserRegistryClient::new(app_client.clone());
+    let service_handler =
+        ServiceHandlerClient::new_with_private_emails(app_client.clone(), user_registry.address);
+
+    let proxy = ProxyClient::new(
+        app_client.clone(),
+        ProxyClient::code_id(&app_client),
+        vec![service_handler.address.clone()],
+    );
+
+    app_client.with_app_mut(|app| {
+        app.execute(
+            app_client.admin(),
+            BankMsg::Send {
+                to_address: proxy.address.to_string(),
+                amount: vec![Coin::new(500_000u128, "utoken")],
+            }
+            .into(),
+        )
+        .unwrap();
+    });
+
+    let subject = format!("withdraw {} utoken 500000", app_client.admin());
+
+    app_tests_common::shared_tests::service_handler::push_private_email(
+        service_handler,
+        proxy,
+        subject,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn push_proven_email() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let user_registry = UserRegistryClient::new(app_client.clone());
+
+    let valid_proof = Binary::from(b"valid".to_vec());
+
+    let proof_verifier = app_client.with_app_mut(|app| {
+        let code_id = app.store_code(mocks::dkim_verifier::contract());
+        app.instantiate_contract(
+            code_id,
+            app_client.admin(),
+            &mocks::dkim_verifier::InstantiateMsg {
+                accepted_proof: valid_proof.clone(),
+            },
+            &[],
+            "dkim verifier",
+            None,
+        )
+        .unwrap()
+    });
+
+    let dkim_registry = DkimRegistryClient::new(app_client.clone());
+
+    let service_handler = ServiceHandlerClient::new_with_proof_verifier(
+        app_client.clone(),
+        user_registry.address,
+        proof_verifier,
+        dkim_registry.address,
+    );
+
+    let proxy = ProxyClient::new(
+        app_client.clone(),
+        ProxyClient::code_id(&app_client),
+        vec![service_handler.address.clone()],
+    );
+
+    app_client.with_app_mut(|app| {
+        app.execute(
+            app_client.admin(),
+            BankMsg::Send {
+                to_address: proxy.address.to_string(),
+                amount: vec![Coin::new(500_000u128, "utoken")],
+            }
+            .into(),
+        )
+        .unwrap();
+    });
+
+    let subject = format!("withdraw {} utoken 500000", app_client.admin());
+
+    app_tests_common::shared_tests::service_handler::push_proven_email(
+        service_handler,
+        proxy,
+        subject,
+        valid_proof,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn push_timestamped_email() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let user_registry = UserRegistryClient::new(app_client.clone());
+    let service_handler = ServiceHandlerClient::new_with_max_email_age(
+        app_client.clone(),
+        user_registry.address,
+        3600,
+    );
+
+    let proxy = ProxyClient::new(
+        app_client.clone(),
+        ProxyClient::code_id(&app_client),
+        vec![service_handler.address.clone()],
+    );
+
+    app_client.with_app_mut(|app| {
+        app.execute(
+            app_client.admin(),
+            BankMsg::Send {
+                to_address: proxy.address.to_string(),
+                amount: vec![Coin::new(500_000u128, "utoken")],
+            }
+            .into(),
+        )
+        .unwrap();
+    });
+
+    let subject = format!("withdraw {} utoken 500000", app_client.admin());
+    let now = app_client.with_app(|app| app.block_info().time);
+
+    app_tests_common::shared_tests::service_handler::push_timestamped_email(
+        service_handler,
+        proxy,
+        subject,
+        now,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn simulate_email() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let user_registry = UserRegistryClient::new(app_client.clone());
+    let service_handler = ServiceHandlerClient::new(app_client.clone(), user_registry.address);
+
+    let proxy = ProxyClient::new(
+        app_client.clone(),
+        ProxyClient::code_id(&app_client),
+        vec![service_handler.address.clone()],
+    );
+
+    app_client.with_app_mut(|app| {
+        app.execute(
+            app_client.admin(),
+            BankMsg::Send {
+                to_address: proxy.address.to_string(),
+                amount: vec![Coin::new(500_000u128, "utoken")],
+            }
+            .into(),
+        )
+        .unwrap();
+    });
+
+    let subject = format!("withdraw {} utoken 500000", app_client.admin());
+
+    app_tests_common::shared_tests::service_handler::simulate_email(
+        service_handler,
+        proxy,
+        subject,
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn private_emails_with_proof_verifier() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let user_registry = UserRegistryClient::new(app_client.clone());
+    let dkim_registry = DkimRegistryClient::new(app_client.clone());
+    let admin = app_client.admin();
+
+    let code_id = app_client.with_app_mut(|app| {
+        app.store_code(Box::new(
+            ContractWrapper::new(
+                app_contract_service_handler::execute,
+                app_contract_service_handler::instantiate,
+                app_contract_service_handler::query,
+            )
+            .with_migrate(app_contract_service_handler::contract::migrate),
+        ))
+    });
+
+    let msg = |private_emails| InstantiateMsg {
+        auth: Auth::Admin(admin.to_string()),
+        user_registry: user_registry.address.to_string(),
+        confirmation: None,
+        private_emails,
+        // any address will do, it's never queried
+        proof_verifier: Some(admin.to_string()),
+        dkim_registry: Some(dkim_registry.address.to_string()),
+        max_email_age_seconds: None,
+    };
+
+    // proven emails carry their subject, so privacy mode would reject every one of them
+    let err = app_client.with_app_mut(|app| {
+        app.instantiate_contract(
+            code_id,
+            admin.clone(),
+            &msg(true),
+            &[],
+            "service handler",
+            None,
+        )
+        .unwrap_err()
+    });
+    assert!(matches!(
+        err.downcast_ref::<ContractError>(),
+        Some(ContractError::PrivateEmailsWithProofs)
+    ));
+
+    let address = app_client.with_app_mut(|app| {
+        app.instantiate_contract(
+            code_id,
+            admin.clone(),
+            &msg(false),
+            &[],
+            "service handler",
+            Some(admin.to_string()),
+        )
+        .unwrap()
+    });
+
+    app_client.with_app_mut(|app| {
+        app.migrate_contract(admin.clone(), address.clone(), &MigrateMsg {}, code_id)
+            .unwrap();
+    });
+
+    // as instantiated before the combination was rejected
+    app_client.with_app_mut(|app| {
+        Item::<bool>::new("private-emails")
+            .save(app.contract_storage_mut(&address).as_mut(), &true)
+            .unwrap();
+    });
+
+    let err = app_client.with_app_mut(|app| {
+        app.migrate_contract(admin.clone(), address.clone(), &MigrateMsg {}, code_id)
+            .unwrap_err()
+    });
+    assert!(matches!(
+        err.downcast_ref::<ContractError>(),
+        Some(ContractError::PrivateEmailsWithProofs)
+    ));
+}
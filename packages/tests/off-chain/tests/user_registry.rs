@@ -0,0 +1,83 @@
+use app_utils::tracing::tracing_init;
+use off_chain_tests::client::{
+    proxy::ProxyClient, service_handler::ServiceHandlerClient, user_registry::UserRegistryClient,
+    AppClient,
+};
+
+#[tokio::test]
+async fn link_user_ids() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let user_registry = UserRegistryClient::new(app_client.clone());
+    let service_handler = ServiceHandlerClient::new(app_client.clone(), user_registry.address);
+
+    let proxy = ProxyClient::new(
+        app_client.clone(),
+        ProxyClient::code_id(&app_client),
+        vec![service_handler.address.clone()],
+    );
+
+    app_tests_common::shared_tests::user_registry::link_user_ids(service_handler, proxy).await;
+}
+
+#[tokio::test]
+async fn admin_lifecycle() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let user_registry = UserRegistryClient::new(app_client.clone());
+
+    let proxy_code_id = ProxyClient::code_id(&app_client);
+    let proxy = ProxyClient::new(app_client.clone(), proxy_code_id, vec![]);
+    let other_proxy = ProxyClient::new(app_client.clone(), proxy_code_id, vec![]);
+
+    let other_admin = app_client.with_app(|app| app.api().addr_make("other-admin"));
+
+    app_tests_common::shared_tests::user_registry::admin_lifecycle(
+        user_registry,
+        app_client.admin().into(),
+        other_admin.into(),
+        proxy.address.into(),
+        other_proxy.address.into(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn lookup_and_enumeration() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let user_registry = UserRegistryClient::new(app_client.clone());
+
+    let proxy_code_id = ProxyClient::code_id(&app_client);
+    let proxy = ProxyClient::new(app_client.clone(), proxy_code_id, vec![]);
+    let other_proxy = ProxyClient::new(app_client.clone(), proxy_code_id, vec![]);
+
+    app_tests_common::shared_tests::user_registry::lookup_and_enumeration(
+        user_registry,
+        proxy.address.into(),
+        other_proxy.address.into(),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn register_users_in_one_tx() {
+    tracing_init();
+
+    let app_client = AppClient::new("admin");
+    let user_registry = UserRegistryClient::new(app_client.clone());
+
+    let proxy_code_id = ProxyClient::code_id(&app_client);
+    let proxy = ProxyClient::new(app_client.clone(), proxy_code_id, vec![]);
+    let other_proxy = ProxyClient::new(app_client.clone(), proxy_code_id, vec![]);
+
+    app_tests_common::shared_tests::user_registry::register_users_in_one_tx(
+        user_registry,
+        proxy.address.into(),
+        other_proxy.address.into(),
+    )
+    .await;
+}
@@ -38,6 +38,11 @@ impl ServiceHandlerClient {
         let msg = app_contract_api::service_handler::msg::InstantiateMsg {
             auth: app_contract_api::service_handler::msg::Auth::Admin(admin.to_string()),
             user_registry: user_registry.to_string(),
+            confirmation: None,
+            private_emails: false,
+            proof_verifier: None,
+            dkim_registry: None,
+            max_email_age_seconds: None,
         };
 
         let (address, _) = client